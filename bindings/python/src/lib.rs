@@ -7,7 +7,7 @@ use nlprule::{
     types::*,
 };
 use pyo3::prelude::*;
-use pyo3::types::PyString;
+use pyo3::types::{PyDict, PyString};
 use pyo3::{exceptions::PyValueError, types::PyBytes};
 use std::{
     fs::{self, File},
@@ -385,6 +385,11 @@ impl PyToken {
 /// * text (List[str]): A list of suggested replacements.
 /// * source (str): The ID of the rule that triggered this suggestion.
 /// * message (str): A human-readable message for this suggestion.
+/// * short_message (Optional[str]): A short, one-line title for this suggestion if the rule has one.
+/// * message_parts (List[Tuple[str, str]]): The message split into ("literal", text) and ("match", text) parts.
+/// * severity (Optional[str]): "error", "warning" or "hint" if a severity is configured for the rule.
+/// * token_span (Optional[Tuple[int, int]]): The indices of the covered tokens of the sentence, including the special SENT_START token.
+/// * tokens (List[Token]): The covered tokens if enabled in the rules options, otherwise empty.
 #[pyclass(name = "Suggestion", module = "nlprule")]
 struct PySuggestion {
     suggestion: Suggestion,
@@ -420,6 +425,64 @@ impl PySuggestion {
     fn message(&self) -> &str {
         &self.suggestion.message
     }
+
+    #[getter]
+    fn short_message(&self) -> Option<&str> {
+        self.suggestion.short_message.as_deref()
+    }
+
+    #[getter]
+    fn message_parts(&self) -> Vec<(&str, &str)> {
+        self.suggestion
+            .message_parts
+            .iter()
+            .map(|x| match x {
+                MessagePart::Literal(text) => ("literal", text.as_str()),
+                MessagePart::Match(text) => ("match", text.as_str()),
+            })
+            .collect()
+    }
+
+    #[getter]
+    fn severity(&self) -> Option<&str> {
+        self.suggestion.severity.map(|x| match x {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Hint => "hint",
+        })
+    }
+
+    #[getter]
+    fn token_span(&self) -> Option<(usize, usize)> {
+        self.suggestion.token_span
+    }
+
+    #[getter]
+    fn tokens<'py>(&self, py: Python<'py>) -> PyResult<Vec<&'py PyCell<PyToken>>> {
+        self.suggestion
+            .tokens
+            .iter()
+            .map(|x| PyCell::new(py, PyToken::from(x.clone())))
+            .collect()
+    }
+
+    #[getter]
+    fn features<'py>(&self, py: Python<'py>) -> PyResult<Option<&'py PyDict>> {
+        self.suggestion
+            .features
+            .as_ref()
+            .map(|features| {
+                let dict = PyDict::new(py);
+                dict.set_item("prev_text", &features.prev_text)?;
+                dict.set_item("prev_pos", &features.prev_pos)?;
+                dict.set_item("next_text", &features.next_text)?;
+                dict.set_item("next_pos", &features.next_pos)?;
+                dict.set_item("sentence_tokens", features.sentence_tokens)?;
+                dict.set_item("category", &features.category)?;
+                Ok(dict)
+            })
+            .transpose()
+    }
 }
 
 impl From<Suggestion> for PySuggestion {
@@ -628,6 +691,8 @@ impl PyExample {
 /// * category_id (str): ID of the category this rule is in.
 /// * category_name (str): A human-readable name of the category this rule is in.
 /// * category_type (Option[str]): The type of the category this rule is in e. g. "style" or "grammar".
+/// * on (bool): Whether this rule is currently used by the rules.
+/// * default_on (bool): Whether this rule is used by default, as specified in the rule XML.
 #[pyclass(name = "Rule", module = "nlprule")]
 struct PyRule {
     id: String,
@@ -638,6 +703,9 @@ struct PyRule {
     category_id: String,
     category_name: String,
     category_type: Option<String>,
+    temp_off: bool,
+    on: bool,
+    default_on: bool,
 }
 
 impl PyRule {
@@ -655,6 +723,9 @@ impl PyRule {
             category_id: rule.category_id().to_owned(),
             category_name: rule.category_name().to_owned(),
             category_type: rule.category_type().map(String::from),
+            temp_off: rule.temp_off(),
+            on: rule.on(),
+            default_on: rule.default_on(),
         })
     }
 }
@@ -700,6 +771,21 @@ impl PyRule {
     fn category_type(&self) -> Option<&str> {
         self.category_type.as_deref()
     }
+
+    #[getter]
+    fn temp_off(&self) -> bool {
+        self.temp_off
+    }
+
+    #[getter]
+    fn on(&self) -> bool {
+        self.on
+    }
+
+    #[getter]
+    fn default_on(&self) -> bool {
+        self.default_on
+    }
 }
 
 /// The grammatical rules.
@@ -733,8 +819,7 @@ impl PyRules {
     ) -> PyResult<Self> {
         let bytes = get_resource(code, "rules.bin.gz")?;
 
-        let rules: Rules = bincode::deserialize_from(bytes)
-            .map_err(|x| PyValueError::new_err(format!("{}", x)))?;
+        let rules = Rules::new_from(bytes).map_err(|x| PyValueError::new_err(format!("{}", x)))?;
         Ok(PyRules {
             rules,
             tokenizer,
@@ -750,8 +835,7 @@ impl PyRules {
         sentence_splitter: Option<PyObject>,
     ) -> PyResult<Self> {
         let rules = if let Some(path) = path {
-            let reader = BufReader::new(File::open(path).unwrap());
-            bincode::deserialize_from(reader).unwrap()
+            Rules::new(path).map_err(|x| PyValueError::new_err(format!("{}", x)))?
         } else {
             Rules::default()
         };
@@ -821,7 +905,7 @@ impl PyRules {
                 let tokenizer = tokenizer.tokenizer();
 
                 let mut output = Vec::new();
-                let mut offset = 0;
+                let (mut offset, mut byte_offset) = (0, 0);
 
                 for sentence in sentences.iter() {
                     let suggestions = self
@@ -829,13 +913,13 @@ impl PyRules {
                         .suggest(&sentence, &tokenizer)
                         .into_iter()
                         .map(|mut x| {
-                            x.start += offset;
-                            x.end += offset;
+                            x.shift(offset, byte_offset);
                             PyCell::new(py, PySuggestion::from(x))
                         })
                         .collect::<PyResult<Vec<_>>>()?;
                     output.extend(suggestions);
                     offset += sentence.chars().count();
+                    byte_offset += sentence.len();
                 }
 
                 Ok(output)
@@ -900,17 +984,7 @@ impl PyRules {
     fn apply_suggestions(py: Python, text: &str, suggestions: Vec<Py<PySuggestion>>) -> String {
         let suggestions: Vec<Suggestion> = suggestions
             .into_iter()
-            .map(|x| {
-                let x = x.borrow(py);
-
-                Suggestion {
-                    source: x.source().to_string(),
-                    message: x.message().to_string(),
-                    replacements: x.replacements().iter().map(|x| x.to_string()).collect(),
-                    start: x.start(),
-                    end: x.end(),
-                }
-            })
+            .map(|x| x.borrow(py).suggestion.clone())
             .collect();
 
         apply_suggestions(text, &suggestions)
@@ -0,0 +1,34 @@
+use clap::Clap;
+use nlprule::health::check_pack;
+
+#[derive(Clap)]
+#[clap(
+    version = "1.0",
+    author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+)]
+struct Opts {
+    #[clap(long, short)]
+    tokenizer: String,
+    #[clap(long, short)]
+    rules: String,
+}
+
+fn main() {
+    env_logger::init();
+    let opts = Opts::parse();
+
+    let report = match check_pack(opts.tokenizer, opts.rules) {
+        Ok(report) => report,
+        Err(error) => {
+            eprintln!("Error loading binaries: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    println!("{:#?}", report);
+    if report.is_healthy() {
+        std::process::exit(0);
+    } else {
+        std::process::exit(1);
+    }
+}
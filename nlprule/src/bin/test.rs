@@ -1,5 +1,8 @@
 use clap::Clap;
-use nlprule::{rules::Rules, tokenizer::Tokenizer};
+use nlprule::{
+    rules::{Rules, TestSelection},
+    tokenizer::Tokenizer,
+};
 
 #[derive(Clap)]
 #[clap(
@@ -13,6 +16,12 @@ struct Opts {
     rules: String,
     #[clap(long, short)]
     ids: Vec<String>,
+    /// Only test this many randomly chosen rules.
+    #[clap(long)]
+    sample: Option<usize>,
+    /// Seed to choose the rules with `--sample`.
+    #[clap(long, default_value = "0")]
+    seed: u64,
 }
 
 fn main() {
@@ -20,20 +29,22 @@ fn main() {
     let opts = Opts::parse();
 
     let tokenizer = Tokenizer::new(opts.tokenizer).unwrap();
-    let rules_container = Rules::new(opts.rules).unwrap();
-    let rules = rules_container.rules();
+    let rules = Rules::new(opts.rules).unwrap();
 
-    println!("Runnable rules: {}", rules.len());
+    println!("Runnable rules: {}", rules.rules().len());
 
-    let mut passes = 0;
-    for rule in rules {
-        if opts.ids.is_empty() || opts.ids.contains(&rule.id().to_string()) {
-            passes += rule.test(&tokenizer) as usize;
-        }
-    }
+    let selection = if let Some(n) = opts.sample {
+        TestSelection::Sample { n, seed: opts.seed }
+    } else if !opts.ids.is_empty() {
+        TestSelection::Ids(opts.ids)
+    } else {
+        TestSelection::All
+    };
+
+    let report = rules.test(&tokenizer, &selection);
 
-    println!("Rules passing tests: {}", passes);
-    if passes == rules.len() {
+    println!("Rules passing tests: {}", report.passed.len());
+    if report.success() {
         std::process::exit(0);
     } else {
         std::process::exit(1);
@@ -0,0 +1,135 @@
+use super::NativeCheck;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+/// Flags unpaired brackets and quotes. Token patterns can not track nesting so this is checked natively
+/// on the whole text. Returns suggestions without replacements at the offending characters.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Brackets {
+    /// Pairs of opening and closing characters. A pair with the same opening and closing character
+    /// (e. g. straight double quotes) is closed by the next occurence of the character.
+    pub pairs: Vec<(char, char)>,
+}
+
+impl Default for Brackets {
+    fn default() -> Self {
+        Brackets::english()
+    }
+}
+
+impl Brackets {
+    fn with_quotes(quotes: &[(char, char)]) -> Self {
+        let mut pairs = vec![('(', ')'), ('[', ']'), ('{', '}')];
+        pairs.extend(quotes);
+        Brackets { pairs }
+    }
+
+    /// Brackets and English quotes: “…”, ‘…’ and "…".
+    pub fn english() -> Self {
+        Brackets::with_quotes(&[('“', '”'), ('‘', '’'), ('"', '"')])
+    }
+
+    /// Brackets and German quotes: „…“, ‚…‘, »…« and "…".
+    pub fn german() -> Self {
+        Brackets::with_quotes(&[('„', '“'), ('‚', '‘'), ('»', '«'), ('"', '"')])
+    }
+
+    /// Brackets and French quotes: «…», ‹…› and "…".
+    pub fn french() -> Self {
+        Brackets::with_quotes(&[('«', '»'), ('‹', '›'), ('"', '"')])
+    }
+
+    fn unpaired(&self, c: char, start: usize) -> Suggestion {
+        Suggestion {
+            source: self.id().to_string(),
+            message: format!("Unpaired \"{}\".", c),
+            short_message: Some("Unpaired bracket or quote".to_string()),
+            start,
+            end: start + 1,
+            replacements: Vec::new(),
+            ..Default::default()
+        }
+    }
+}
+
+impl NativeCheck for Brackets {
+    fn id(&self) -> &'static str {
+        "UNPAIRED_BRACKETS"
+    }
+
+    fn document_scoped(&self) -> bool {
+        true
+    }
+
+    fn check(&self, text: &str) -> Vec<Suggestion> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut suggestions = Vec::new();
+        let mut stack: Vec<(char, usize)> = Vec::new();
+
+        for (i, c) in chars.iter().copied().enumerate() {
+            // the closing single quote is also used as apostrophe e. g. in "don’t"
+            if matches!(c, '’' | '‘' | '\'')
+                && i > 0
+                && chars[i - 1].is_alphanumeric()
+                && matches!(chars.get(i + 1), Some(x) if x.is_alphanumeric())
+            {
+                continue;
+            }
+
+            let is_open = self.pairs.iter().any(|(open, _)| *open == c);
+            let is_close = self.pairs.iter().any(|(_, close)| *close == c);
+
+            if is_close {
+                let position = stack.iter().rposition(|(open, _)| {
+                    self.pairs.iter().any(|pair| pair.0 == *open && pair.1 == c)
+                });
+
+                if let Some(position) = position {
+                    for (open, start) in stack.drain(position..).skip(1) {
+                        suggestions.push(self.unpaired(open, start));
+                    }
+                    continue;
+                } else if !is_open {
+                    suggestions.push(self.unpaired(c, i));
+                    continue;
+                }
+            }
+
+            if is_open {
+                stack.push((c, i));
+            }
+        }
+
+        for (open, start) in stack {
+            suggestions.push(self.unpaired(open, start));
+        }
+
+        suggestions.sort_by_key(|x| x.start);
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unpaired(brackets: &Brackets, text: &str) -> Vec<usize> {
+        brackets.check(text).iter().map(|x| x.start).collect()
+    }
+
+    #[test]
+    fn finds_unpaired() {
+        let english = Brackets::english();
+
+        assert_eq!(
+            unpaired(&english, "He said “don’t (go)”."),
+            Vec::<usize>::new()
+        );
+        assert_eq!(unpaired(&english, "A (b [c) d."), vec![5]);
+        assert_eq!(unpaired(&english, "A b) \"c"), vec![3, 5]);
+        assert_eq!(
+            unpaired(&Brackets::german(), "Er sagte „nein“."),
+            Vec::<usize>::new()
+        );
+    }
+}
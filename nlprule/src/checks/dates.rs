@@ -0,0 +1,266 @@
+use super::NativeCheck;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+/// The order of day and month in a date.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateOrder {
+    /// "March 5" and "March 5, 2021".
+    MonthDay,
+    /// "5 March" and "5 March 2021".
+    DayMonth,
+}
+
+/// Flags wrong English ordinal suffixes (e. g. "21th") and dates which are not in the preferred order
+/// (e. g. "5 March" if "March 5" is preferred). Suggests the corrected ordinal or the reordered date.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Dates {
+    /// Check the suffixes of ordinals written with digits. The suffixes are English.
+    pub ordinals: bool,
+    /// The preferred order of dates. `None` to not check the order.
+    pub order: Option<DateOrder>,
+    /// The names of the months. Only capitalized names are recognized since e. g. "may" and "march" are also verbs.
+    pub months: Vec<String>,
+}
+
+impl Default for Dates {
+    fn default() -> Self {
+        Dates::english(Some(DateOrder::MonthDay))
+    }
+}
+
+/// A run of alphanumeric characters with char indices.
+struct Word {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Gets the correct English suffix of an ordinal number.
+fn ordinal_suffix(number: u64) -> &'static str {
+    match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+/// Splits a word like "21st" into the number and the suffix. The suffix is empty for numbers.
+fn split_number(word: &str) -> Option<(&str, &str)> {
+    let digits = word
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(word.len());
+    if digits == 0 || digits > 18 {
+        return None;
+    }
+
+    let (number, suffix) = word.split_at(digits);
+    if suffix.is_empty()
+        || ["st", "nd", "rd", "th"]
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(suffix))
+    {
+        Some((number, suffix))
+    } else {
+        None
+    }
+}
+
+impl Dates {
+    /// English month names with the given preferred order.
+    pub fn english(order: Option<DateOrder>) -> Self {
+        Dates {
+            ordinals: true,
+            order,
+            months: [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ]
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+        }
+    }
+
+    fn is_month(&self, word: &Word) -> bool {
+        self.months.contains(&word.text)
+    }
+
+    /// Whether the word is a day of the month with an optional ordinal suffix.
+    fn is_day(word: &Word) -> bool {
+        split_number(&word.text)
+            .and_then(|(number, _)| number.parse::<u8>().ok())
+            .filter(|day| (1..=31).contains(day))
+            .is_some()
+    }
+
+    fn is_year(word: &Word) -> bool {
+        word.text.len() == 4 && word.text.chars().all(|c| c.is_ascii_digit())
+    }
+
+    fn check_ordinal(&self, word: &Word, suggestions: &mut Vec<Suggestion>) {
+        let (number, suffix) = match split_number(&word.text) {
+            Some((number, suffix)) if !suffix.is_empty() => (number, suffix),
+            _ => return,
+        };
+        let value: u64 = match number.parse() {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+
+        let expected = ordinal_suffix(value);
+        if suffix.eq_ignore_ascii_case(expected) {
+            return;
+        }
+
+        let expected = if suffix.chars().all(|c| c.is_uppercase()) {
+            expected.to_uppercase()
+        } else {
+            expected.to_string()
+        };
+
+        suggestions.push(Suggestion {
+            source: self.id().to_string(),
+            message: format!("The ordinal suffix of {} is \"{}\".", number, expected),
+            short_message: Some("Ordinal suffix".to_string()),
+            start: word.start,
+            end: word.end,
+            replacements: vec![format!("{}{}", number, expected)],
+            ..Default::default()
+        });
+    }
+
+    /// Checks the order of a date starting at `words[i]`. Returns the number of words the date spans.
+    fn check_order(
+        &self,
+        order: DateOrder,
+        chars: &[char],
+        words: &[Word],
+        i: usize,
+        suggestions: &mut Vec<Suggestion>,
+    ) -> usize {
+        let between = |a: &Word, b: &Word| chars[a.end..b.start].iter().collect::<String>();
+        let (first, second) = match (words.get(i), words.get(i + 1)) {
+            (Some(first), Some(second)) if between(first, second) == " " => (first, second),
+            _ => return 1,
+        };
+        let year = words
+            .get(i + 2)
+            .filter(|year| Dates::is_year(year))
+            .map(|year| (year, between(second, year)));
+
+        let (day, month, year, found) = if self.is_month(first) && Dates::is_day(second) {
+            let year = year.filter(|(_, sep)| sep == ", " || sep == " ");
+            (second, first, year.map(|x| x.0), DateOrder::MonthDay)
+        } else if Dates::is_day(first) && self.is_month(second) {
+            let year = year.filter(|(_, sep)| sep == " ");
+            (first, second, year.map(|x| x.0), DateOrder::DayMonth)
+        } else {
+            return 1;
+        };
+
+        let n_words = if year.is_some() { 3 } else { 2 };
+        if found == order {
+            return n_words;
+        }
+
+        let replacement = match (order, year) {
+            (DateOrder::MonthDay, Some(year)) => {
+                format!("{} {}, {}", month.text, day.text, year.text)
+            }
+            (DateOrder::MonthDay, None) => format!("{} {}", month.text, day.text),
+            (DateOrder::DayMonth, Some(year)) => {
+                format!("{} {} {}", day.text, month.text, year.text)
+            }
+            (DateOrder::DayMonth, None) => format!("{} {}", day.text, month.text),
+        };
+
+        suggestions.push(Suggestion {
+            source: self.id().to_string(),
+            message: format!("Write the date as \"{}\".", replacement),
+            short_message: Some("Date format".to_string()),
+            start: first.start,
+            end: year.unwrap_or(second).end,
+            replacements: vec![replacement],
+            ..Default::default()
+        });
+
+        n_words
+    }
+}
+
+impl NativeCheck for Dates {
+    fn id(&self) -> &'static str {
+        "DATES"
+    }
+
+    fn check(&self, text: &str) -> Vec<Suggestion> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut words: Vec<Word> = Vec::new();
+        for (i, c) in chars.iter().enumerate() {
+            if !c.is_alphanumeric() {
+                continue;
+            }
+
+            match words.last_mut() {
+                Some(word) if word.end == i => {
+                    word.end += 1;
+                    word.text.push(*c);
+                }
+                _ => words.push(Word {
+                    start: i,
+                    end: i + 1,
+                    text: c.to_string(),
+                }),
+            }
+        }
+
+        let mut suggestions = Vec::new();
+        if self.ordinals {
+            for word in &words {
+                self.check_ordinal(word, &mut suggestions);
+            }
+        }
+
+        if let Some(order) = self.order {
+            let mut i = 0;
+            while i < words.len() {
+                i += self.check_order(order, &chars, &words, i, &mut suggestions);
+            }
+        }
+
+        suggestions.sort_by_key(|x| x.start);
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_ordinals_and_dates() {
+        let text = "On 5 March 2021 the 21th and 12th runners met on March 3.";
+        let suggestions = Dates::english(Some(DateOrder::MonthDay)).check(text);
+
+        let spans: Vec<_> = suggestions
+            .iter()
+            .map(|x| (x.start, x.end, x.replacements[0].as_str()))
+            .collect();
+        assert_eq!(spans, vec![(3, 15, "March 5, 2021"), (20, 24, "21st")]);
+        assert_eq!(ordinal_suffix(112), "th");
+        assert_eq!(ordinal_suffix(102), "nd");
+    }
+}
@@ -0,0 +1,153 @@
+use super::NativeCheck;
+use crate::types::*;
+
+/// Flags words which are written in Latin script but contain letters from other scripts which look like Latin
+/// letters (e. g. the Cyrillic "а" in "bаnk") or zero-width characters. These are common copy-paste artifacts
+/// and keep the tagger from recognizing the word. Suggests the word with the characters replaced by their
+/// Latin counterparts.
+pub struct Homoglyphs;
+
+fn is_zero_width(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_alphabetic() && (c as u32) < 0x250
+}
+
+fn latin_counterpart(c: char) -> Option<char> {
+    Some(match c {
+        // Cyrillic
+        'а' => 'a',
+        'е' => 'e',
+        'о' => 'o',
+        'р' => 'p',
+        'с' => 'c',
+        'у' => 'y',
+        'х' => 'x',
+        'і' => 'i',
+        'ј' => 'j',
+        'ѕ' => 's',
+        'ԁ' => 'd',
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Т' => 'T',
+        'Х' => 'X',
+        'І' => 'I',
+        'Ј' => 'J',
+        'Ѕ' => 'S',
+        // Greek
+        'ο' => 'o',
+        'ν' => 'v',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        _ => return None,
+    })
+}
+
+impl Homoglyphs {
+    fn check_word(&self, word: &[char], start: usize, suggestions: &mut Vec<Suggestion>) {
+        let has_latin = word.iter().any(|c| is_latin(*c));
+        let has_confusable = word
+            .iter()
+            .any(|c| is_zero_width(*c) || latin_counterpart(*c).is_some());
+
+        if !has_latin || !has_confusable {
+            return;
+        }
+
+        let replacement: String = word
+            .iter()
+            .filter(|c| !is_zero_width(**c))
+            .map(|c| latin_counterpart(*c).unwrap_or(*c))
+            .collect();
+
+        // words with letters from other scripts which have no Latin counterpart are not Latin words
+        if !replacement
+            .chars()
+            .all(|c| !c.is_alphabetic() || is_latin(c))
+        {
+            return;
+        }
+
+        suggestions.push(Suggestion {
+            source: self.id().to_string(),
+            message: "This word contains characters which look like Latin letters but are not."
+                .to_string(),
+            short_message: Some("Confusable characters".to_string()),
+            start,
+            end: start + word.len(),
+            replacements: vec![replacement],
+            ..Default::default()
+        });
+    }
+}
+
+impl NativeCheck for Homoglyphs {
+    fn id(&self) -> &'static str {
+        "HOMOGLYPHS"
+    }
+
+    fn check(&self, text: &str) -> Vec<Suggestion> {
+        let mut suggestions = Vec::new();
+        let mut word = Vec::new();
+        let mut start = 0;
+
+        for (i, c) in text.chars().enumerate() {
+            if c.is_alphanumeric() || is_zero_width(c) {
+                if word.is_empty() {
+                    start = i;
+                }
+                word.push(c);
+            } else if !word.is_empty() {
+                self.check_word(&word, start, &mut suggestions);
+                word.clear();
+            }
+        }
+
+        if !word.is_empty() {
+            self.check_word(&word, start, &mut suggestions);
+        }
+
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_homoglyphs() {
+        // Cyrillic "а" in "bаnk", zero-width space in "hel\u{200B}lo", purely Cyrillic "нет"
+        let suggestions = Homoglyphs.check("My bаnk says hel\u{200B}lo and нет.");
+
+        let spans: Vec<_> = suggestions
+            .iter()
+            .map(|x| (x.start, x.end, x.replacements[0].as_str()))
+            .collect();
+        assert_eq!(spans, vec![(3, 7, "bank"), (13, 19, "hello")]);
+    }
+}
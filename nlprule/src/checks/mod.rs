@@ -0,0 +1,113 @@
+//! Checks implemented natively instead of as LanguageTool rules, for errors token patterns can not express.
+//! Checks are enabled with the [NativeChecks] in the [RulesOptions][crate::rules::RulesOptions].
+
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+mod brackets;
+mod dates;
+mod homoglyph;
+mod ocr;
+mod phrases;
+mod punctuation;
+mod typography;
+
+pub use brackets::Brackets;
+pub use dates::{DateOrder, Dates};
+pub use homoglyph::Homoglyphs;
+pub use ocr::OcrArtifacts;
+pub use phrases::{Phrase, PhraseDictionary};
+pub use punctuation::SmartPunctuation;
+pub use typography::Typography;
+
+/// A check implemented in Rust which runs on the whole text.
+pub trait NativeCheck {
+    /// A unique identifier of this check. Used as the source of the suggestions.
+    fn id(&self) -> &'static str;
+
+    /// Computes suggestions for the text. Indices are character indices like for rule suggestions.
+    fn check(&self, text: &str) -> Vec<Suggestion>;
+
+    /// Computes suggestions for the finalized tokens of a text. Checks which use the tokenization override this,
+    /// by default the text of the tokens is checked with [NativeCheck::check].
+    fn check_tokens(&self, tokens: &[Token]) -> Vec<Suggestion> {
+        self.check(tokens[0].text)
+    }
+
+    /// Whether rule suggestions overlapping a suggestion of this check are dropped, e. g. because the rules
+    /// can not match the flagged text reliably.
+    fn suppresses_rules(&self) -> bool {
+        false
+    }
+
+    /// Whether this check needs the whole text instead of a single sentence, e. g. to pair brackets across
+    /// sentences. When a text is checked sentence by sentence (see [Rules::suggest][crate::Rules::suggest]),
+    /// such checks run once on the tokens of the whole text and their suggestions are passed to the sentences they
+    /// are in. These tokens are not tagged. Suggestions spanning two sentences are dropped.
+    fn document_scoped(&self) -> bool {
+        false
+    }
+}
+
+/// Which native checks to run in addition to the rules.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NativeChecks {
+    /// Flag Cyrillic and Greek letters and zero-width characters inside Latin words, see [Homoglyphs].
+    #[serde(default)]
+    pub homoglyphs: bool,
+    /// Flag unpaired brackets and quotes with the given language-specific pairs, see [Brackets].
+    #[serde(default)]
+    pub brackets: Option<Brackets>,
+    /// Flag multiple spaces, whitespace before punctuation and trailing whitespace, see [Typography].
+    #[serde(default)]
+    pub typography: Option<Typography>,
+    /// Suggest apostrophes in contractions and ellipsis characters, see [SmartPunctuation].
+    #[serde(default)]
+    pub smart_punctuation: Option<SmartPunctuation>,
+    /// Flag wrong ordinal suffixes and dates not in the preferred order, see [Dates].
+    #[serde(default)]
+    pub dates: Option<Dates>,
+    /// Flag probable OCR errors with the given confusions, see [OcrArtifacts].
+    #[serde(default)]
+    pub ocr_artifacts: Option<OcrArtifacts>,
+    /// Flag incorrect variants of idioms and other fixed phrases, see [PhraseDictionary].
+    #[serde(default)]
+    pub phrases: Option<PhraseDictionary>,
+}
+
+impl NativeChecks {
+    /// Gets the enabled checks.
+    pub fn enabled(&self) -> Vec<Box<dyn NativeCheck>> {
+        let mut checks: Vec<Box<dyn NativeCheck>> = Vec::new();
+
+        if self.homoglyphs {
+            checks.push(Box::new(Homoglyphs));
+        }
+
+        if let Some(brackets) = &self.brackets {
+            checks.push(Box::new(brackets.clone()));
+        }
+
+        if let Some(typography) = &self.typography {
+            checks.push(Box::new(typography.clone()));
+        }
+
+        if let Some(smart_punctuation) = &self.smart_punctuation {
+            checks.push(Box::new(smart_punctuation.clone()));
+        }
+
+        if let Some(dates) = &self.dates {
+            checks.push(Box::new(dates.clone()));
+        }
+
+        if let Some(ocr_artifacts) = &self.ocr_artifacts {
+            checks.push(Box::new(ocr_artifacts.clone()));
+        }
+
+        if let Some(phrases) = &self.phrases {
+            checks.push(Box::new(phrases.clone()));
+        }
+
+        checks
+    }
+}
@@ -0,0 +1,134 @@
+use super::NativeCheck;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+/// Flags words in OCR output which are probably recognition errors: words unknown to the tagger which become known
+/// words after undoing one systematic confusion, e. g. "rnodern" (from "modern") or "he1p" (from "help").
+/// Suggests the known words. Rule suggestions overlapping a flagged word are dropped since the rules see the
+/// garbled word and their matches are likely wrong. Needs the tokenization, so [NativeCheck::check] finds nothing.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OcrArtifacts {
+    /// Pairs of the text OCR produces and the text it was likely produced from, e. g. `("rn", "m")`.
+    pub confusions: Vec<(String, String)>,
+    /// The maximum number of suggested words per flagged word.
+    pub max_candidates: usize,
+}
+
+impl Default for OcrArtifacts {
+    fn default() -> Self {
+        OcrArtifacts::latin()
+    }
+}
+
+impl OcrArtifacts {
+    /// Common confusions of Latin script OCR.
+    pub fn latin() -> Self {
+        OcrArtifacts {
+            confusions: [
+                ("rn", "m"),
+                ("m", "rn"),
+                ("cl", "d"),
+                ("vv", "w"),
+                ("li", "h"),
+                ("ii", "u"),
+                ("1", "l"),
+                ("1", "i"),
+                ("l", "i"),
+                ("I", "l"),
+                ("0", "o"),
+                ("0", "O"),
+                ("5", "s"),
+                ("8", "B"),
+            ]
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect(),
+            max_candidates: 3,
+        }
+    }
+
+    /// Gets the words which result from undoing one confusion at one position, in the order of the confusions.
+    fn candidates(&self, word: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = Vec::new();
+
+        for (from, to) in &self.confusions {
+            for (i, _) in word.match_indices(from.as_str()) {
+                let candidate = format!("{}{}{}", &word[..i], to, &word[i + from.len()..]);
+                if candidate != word && !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+impl NativeCheck for OcrArtifacts {
+    fn id(&self) -> &'static str {
+        "OCR_ARTIFACTS"
+    }
+
+    fn suppresses_rules(&self) -> bool {
+        true
+    }
+
+    fn check(&self, _text: &str) -> Vec<Suggestion> {
+        Vec::new()
+    }
+
+    fn check_tokens(&self, tokens: &[Token]) -> Vec<Suggestion> {
+        let mut suggestions = Vec::new();
+
+        // the first token is the special sentence start token
+        for token in &tokens[1..] {
+            let word = token.word.text.as_ref();
+            if word.chars().count() < 2
+                || !word.chars().any(|c| c.is_alphabetic())
+                || !word.chars().all(|c| c.is_alphanumeric())
+                || !token.tagger.get_tags(word, true, false).is_empty()
+            {
+                continue;
+            }
+
+            let replacements: Vec<_> = self
+                .candidates(word)
+                .into_iter()
+                .filter(|x| !token.tagger.get_tags(x, true, false).is_empty())
+                .take(self.max_candidates)
+                .collect();
+            if replacements.is_empty() {
+                continue;
+            }
+
+            suggestions.push(Suggestion {
+                source: self.id().to_string(),
+                message: format!(
+                    "\"{}\" is probably a recognition error. Did you mean \"{}\"?",
+                    word, replacements[0]
+                ),
+                short_message: Some("Possible OCR error".to_string()),
+                start: token.char_span.0,
+                end: token.char_span.1,
+                replacements,
+                ..Default::default()
+            });
+        }
+
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undoes_one_confusion() {
+        let ocr = OcrArtifacts::latin();
+
+        assert_eq!(&ocr.candidates("rnodern")[..2], ["modern", "rnodem"]);
+        assert!(ocr.candidates("he1p").contains(&"help".to_string()));
+        assert!(ocr.candidates("word").is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use super::NativeCheck;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A fixed phrase such as an idiom and its common incorrect variants.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Phrase {
+    /// The correct phrase e. g. "for all intents and purposes".
+    pub correct: String,
+    /// The incorrect variants e. g. "for all intensive purposes". They are matched case-insensitively and
+    /// whitespace matches any run of whitespace.
+    pub incorrect: Vec<String>,
+    /// The message of the suggestions. Defaults to "Did you mean \"{correct}\"?".
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// The first word of a phrase, used to look up the phrases which can start at a token.
+fn first_word(text: &str) -> Option<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .next()
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_lowercase())
+}
+
+/// Gets how many chars of `text` match `phrase`, case-insensitively and with whitespace in the phrase matching any
+/// run of whitespace in the text. `None` if the text does not start with the phrase.
+fn match_len(text: &str, phrase: &str) -> Option<usize> {
+    let mut text = text.chars().peekable();
+    let mut len = 0;
+
+    for c in phrase
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+    {
+        if c == ' ' {
+            let mut any = false;
+            while text.peek().map(|x| x.is_whitespace()) == Some(true) {
+                text.next();
+                len += 1;
+                any = true;
+            }
+            if !any {
+                return None;
+            }
+        } else {
+            match text.next() {
+                Some(x) if x.to_lowercase().eq(c.to_lowercase()) => len += 1,
+                _ => return None,
+            }
+        }
+    }
+
+    Some(len)
+}
+
+#[derive(Debug, Default)]
+struct PhraseIndex {
+    phrases: Vec<Phrase>,
+    // the first word of the incorrect variants to the (phrase index, variant index) of the variants
+    by_first_word: DefaultHashMap<String, Vec<(usize, usize)>>,
+}
+
+/// Flags incorrect variants of fixed phrases such as idioms (e. g. "for all intensive purposes") and suggests the
+/// correct phrase. The variants are looked up by the first word at each token, so this scales to many phrases
+/// unlike one rule per phrase. Variants starting with a non-alphanumeric char are ignored. Matches have to start
+/// and end at token boundaries; at each position the longest variant wins. Needs the tokenization, so
+/// [NativeCheck::check] finds nothing.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(from = "Vec<Phrase>", into = "Vec<Phrase>")]
+pub struct PhraseDictionary {
+    // shared since the checks are cloned for each check, see [NativeChecks::enabled][super::NativeChecks::enabled]
+    index: Arc<PhraseIndex>,
+}
+
+impl From<Vec<Phrase>> for PhraseDictionary {
+    fn from(phrases: Vec<Phrase>) -> Self {
+        PhraseDictionary::new(phrases)
+    }
+}
+
+impl From<PhraseDictionary> for Vec<Phrase> {
+    fn from(dictionary: PhraseDictionary) -> Self {
+        dictionary.index.phrases.clone()
+    }
+}
+
+impl PhraseDictionary {
+    /// Builds the dictionary of the phrases.
+    pub fn new(phrases: Vec<Phrase>) -> Self {
+        let mut by_first_word: DefaultHashMap<String, Vec<(usize, usize)>> =
+            DefaultHashMap::default();
+
+        for (i, phrase) in phrases.iter().enumerate() {
+            for (j, variant) in phrase.incorrect.iter().enumerate() {
+                if let Some(word) = first_word(variant) {
+                    by_first_word.entry(word).or_default().push((i, j));
+                }
+            }
+        }
+
+        PhraseDictionary {
+            index: Arc::new(PhraseIndex {
+                phrases,
+                by_first_word,
+            }),
+        }
+    }
+
+    /// Gets the phrases in this dictionary.
+    pub fn phrases(&self) -> &[Phrase] {
+        &self.index.phrases
+    }
+}
+
+impl NativeCheck for PhraseDictionary {
+    fn id(&self) -> &'static str {
+        "PHRASE_DICTIONARY"
+    }
+
+    fn check(&self, _text: &str) -> Vec<Suggestion> {
+        Vec::new()
+    }
+
+    fn check_tokens(&self, tokens: &[Token]) -> Vec<Suggestion> {
+        let text = tokens[0].text;
+        let token_ends: DefaultHashSet<_> = tokens[1..].iter().map(|x| x.char_span.1).collect();
+        let mut suggestions = Vec::new();
+        let mut next_free = 0;
+
+        // the first token is the special sentence start token
+        for token in &tokens[1..] {
+            if token.char_span.0 < next_free {
+                continue;
+            }
+
+            let candidates = match self
+                .index
+                .by_first_word
+                .get(&token.word.text.as_ref().to_lowercase())
+            {
+                Some(candidates) => candidates,
+                None => continue,
+            };
+
+            let rest = &text[token.byte_span.0..];
+            let best = candidates
+                .iter()
+                .filter_map(|&(i, j)| {
+                    let len = match_len(rest, &self.index.phrases[i].incorrect[j])?;
+                    Some((i, len)).filter(|_| token_ends.contains(&(token.char_span.0 + len)))
+                })
+                .max_by_key(|&(_, len)| len);
+
+            if let Some((i, len)) = best {
+                let phrase = &self.index.phrases[i];
+                let (start, end) = (token.char_span.0, token.char_span.0 + len);
+                let replacement = if rest.chars().next().map(char::is_uppercase) == Some(true) {
+                    crate::text::apply_to_first(&phrase.correct, |c| c.to_uppercase().collect())
+                } else {
+                    phrase.correct.clone()
+                };
+
+                suggestions.push(Suggestion {
+                    source: self.id().to_string(),
+                    message: phrase
+                        .message
+                        .clone()
+                        .unwrap_or_else(|| format!("Did you mean \"{}\"?", phrase.correct)),
+                    short_message: Some("Incorrect phrase".to_string()),
+                    start,
+                    end,
+                    replacements: vec![replacement],
+                    ..Default::default()
+                });
+                next_free = end;
+            }
+        }
+
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_phrases() {
+        assert_eq!(
+            match_len(
+                "For all  intensive purposes, he",
+                "for all intensive purposes"
+            ),
+            Some(27)
+        );
+        assert_eq!(
+            match_len("for all intents", "for all intensive purposes"),
+            None
+        );
+        assert_eq!(first_word("could of"), Some("could".to_string()));
+        assert_eq!(first_word("'tis"), None);
+
+        let dictionary = PhraseDictionary::new(vec![Phrase {
+            correct: "could have".to_string(),
+            incorrect: vec!["could of".to_string()],
+            message: None,
+        }]);
+        let serialized = bincode::serialize(&dictionary).unwrap();
+        let deserialized: PhraseDictionary = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized.phrases(), dictionary.phrases());
+        assert_eq!(deserialized.index.by_first_word["could"], vec![(0, 0)]);
+    }
+}
@@ -0,0 +1,116 @@
+use super::NativeCheck;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+/// Suggests typographic punctuation: an apostrophe instead of a straight quote in contractions (e. g. "don't")
+/// and an ellipsis character instead of three dots. Only straight quotes between two letters are replaced so
+/// quotations in straight quotes are left alone.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SmartPunctuation {
+    /// The apostrophe which replaces straight quotes in contractions. `None` to not check apostrophes.
+    pub apostrophe: Option<char>,
+    /// Whether to replace exactly three dots with "…".
+    pub ellipsis: bool,
+}
+
+impl Default for SmartPunctuation {
+    fn default() -> Self {
+        SmartPunctuation::english()
+    }
+}
+
+impl SmartPunctuation {
+    /// "’" in contractions and "…". Also used for e. g. French and German.
+    pub fn english() -> Self {
+        SmartPunctuation {
+            apostrophe: Some('’'),
+            ellipsis: true,
+        }
+    }
+
+    /// The modifier letter apostrophe "ʼ" which is part of words like "мʼята" and "…".
+    pub fn ukrainian() -> Self {
+        SmartPunctuation {
+            apostrophe: Some('ʼ'),
+            ellipsis: true,
+        }
+    }
+}
+
+impl NativeCheck for SmartPunctuation {
+    fn id(&self) -> &'static str {
+        "SMART_PUNCTUATION"
+    }
+
+    fn document_scoped(&self) -> bool {
+        true
+    }
+
+    fn check(&self, text: &str) -> Vec<Suggestion> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut suggestions = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if let Some(apostrophe) = self.apostrophe {
+                if chars[i] == '\''
+                    && i > 0
+                    && chars[i - 1].is_alphabetic()
+                    && matches!(chars.get(i + 1), Some(x) if x.is_alphabetic())
+                {
+                    suggestions.push(Suggestion {
+                        source: self.id().to_string(),
+                        message: format!("Use the apostrophe \"{}\".", apostrophe),
+                        short_message: Some("Apostrophe".to_string()),
+                        start: i,
+                        end: i + 1,
+                        replacements: vec![apostrophe.to_string()],
+                        ..Default::default()
+                    });
+                }
+            }
+
+            if self.ellipsis && chars[i] == '.' {
+                let start = i;
+                while i < chars.len() && chars[i] == '.' {
+                    i += 1;
+                }
+
+                // two dots are likely a typo and four or more are used on purpose
+                if i - start == 3 {
+                    suggestions.push(Suggestion {
+                        source: self.id().to_string(),
+                        message: "Use the ellipsis character \"…\".".to_string(),
+                        short_message: Some("Ellipsis".to_string()),
+                        start,
+                        end: i,
+                        replacements: vec!["…".to_string()],
+                        ..Default::default()
+                    });
+                }
+                continue;
+            }
+
+            i += 1;
+        }
+
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_smart_punctuation() {
+        let text = "I don't know... 'Yes' and so.... on";
+        let suggestions = SmartPunctuation::english().check(text);
+
+        let spans: Vec<_> = suggestions
+            .iter()
+            .map(|x| (x.start, x.end, x.replacements[0].as_str()))
+            .collect();
+        assert_eq!(spans, vec![(5, 6, "’"), (12, 15, "…")]);
+    }
+}
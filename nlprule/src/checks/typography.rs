@@ -0,0 +1,225 @@
+use super::NativeCheck;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+
+/// Flags multiple spaces between words, whitespace before punctuation and whitespace at the end of lines.
+/// Uses the whitespace between the tokens of the tokenizer so e. g. the "." in ".5" is not treated as punctuation.
+/// All suggestions replace the whitespace with a single space or remove it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Typography {
+    /// Flag two or more spaces between words.
+    pub multiple_spaces: bool,
+    /// Flag spaces and tabs at the end of a line or of the text.
+    pub trailing_spaces: bool,
+    /// Tokens which must not be preceded by whitespace. Empty to not check whitespace before punctuation.
+    pub no_space_before: Vec<String>,
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Typography::english()
+    }
+}
+
+/// Whitespace between two tokens, with char indices. `next` is the token after the whitespace if there is one.
+struct Gap<'a> {
+    start: usize,
+    end: usize,
+    next: Option<&'a str>,
+}
+
+impl Typography {
+    fn with_no_space_before(no_space_before: &[&str]) -> Self {
+        Typography {
+            multiple_spaces: true,
+            trailing_spaces: true,
+            no_space_before: no_space_before.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    /// No space before `,`, `.`, `;`, `:`, `!`, `?`, `…` and closing brackets.
+    pub fn english() -> Self {
+        Typography::with_no_space_before(&[",", ".", ";", ":", "!", "?", "…", ")", "]", "}"])
+    }
+
+    /// No space before `,`, `.`, `…` and closing brackets. French requires a (narrow) space before `;`, `:`, `!` and `?`.
+    pub fn french() -> Self {
+        Typography::with_no_space_before(&[",", ".", "…", ")", "]", "}"])
+    }
+
+    fn suggestion(&self, start: usize, end: usize, replacement: &str, message: &str) -> Suggestion {
+        Suggestion {
+            source: self.id().to_string(),
+            message: message.to_string(),
+            short_message: Some("Whitespace".to_string()),
+            start,
+            end,
+            replacements: vec![replacement.to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn check_gaps<'a>(
+        &self,
+        chars: &[char],
+        gaps: impl Iterator<Item = Gap<'a>>,
+    ) -> Vec<Suggestion> {
+        let mut suggestions = Vec::new();
+
+        for gap in gaps {
+            let whitespace = &chars[gap.start..gap.end];
+            let has_newline = whitespace.iter().any(|c| *c == '\n' || *c == '\r');
+
+            if self.trailing_spaces {
+                // spaces before each line break and, at the end of the text, before the end
+                let mut line_start = gap.start;
+                for (i, c) in whitespace.iter().enumerate() {
+                    let i = gap.start + i;
+                    if *c == '\n' || *c == '\r' {
+                        if i > line_start {
+                            suggestions.push(self.suggestion(
+                                line_start,
+                                i,
+                                "",
+                                "Remove the whitespace at the end of the line.",
+                            ));
+                        }
+                        line_start = i + 1;
+                    }
+                }
+                if gap.next.is_none() && gap.end > line_start {
+                    suggestions.push(self.suggestion(
+                        line_start,
+                        gap.end,
+                        "",
+                        "Remove the whitespace at the end of the text.",
+                    ));
+                }
+            }
+
+            // leading whitespace and whitespace around line breaks is indentation or layout
+            let (next, is_inline) = match gap.next {
+                Some(next) => (next, gap.start > 0 && !has_newline),
+                None => continue,
+            };
+            if !is_inline {
+                continue;
+            }
+
+            if self.no_space_before.iter().any(|x| x == next) {
+                suggestions.push(self.suggestion(
+                    gap.start,
+                    gap.end,
+                    "",
+                    &format!("Remove the whitespace before \"{}\".", next),
+                ));
+            } else if self.multiple_spaces
+                && whitespace.len() > 1
+                && whitespace.iter().all(|c| *c == ' ')
+            {
+                suggestions.push(self.suggestion(
+                    gap.start,
+                    gap.end,
+                    " ",
+                    "Use a single space between words.",
+                ));
+            }
+        }
+
+        suggestions
+    }
+}
+
+impl NativeCheck for Typography {
+    fn id(&self) -> &'static str {
+        "TYPOGRAPHY"
+    }
+
+    fn document_scoped(&self) -> bool {
+        true
+    }
+
+    /// Approximates the tokens by splitting the text at whitespace, see [NativeCheck::check_tokens].
+    fn check(&self, text: &str) -> Vec<Suggestion> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut gaps = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if !chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            let next_end = (i..chars.len())
+                .find(|j| chars[*j].is_whitespace())
+                .unwrap_or(chars.len());
+            gaps.push((start, i, chars[i..next_end].iter().collect::<String>()));
+        }
+
+        self.check_gaps(
+            &chars,
+            gaps.iter().map(|(start, end, next)| Gap {
+                start: *start,
+                end: *end,
+                next: if next.is_empty() {
+                    None
+                } else {
+                    Some(next.as_str())
+                },
+            }),
+        )
+    }
+
+    fn check_tokens(&self, tokens: &[Token]) -> Vec<Suggestion> {
+        let text = tokens[0].text;
+        let chars: Vec<char> = text.chars().collect();
+
+        // the first token is the special sentence start token which has an empty span
+        let mut gaps = Vec::new();
+        let mut prev_end = 0;
+        for token in &tokens[1..] {
+            if token.char_span.0 > prev_end {
+                gaps.push(Gap {
+                    start: prev_end,
+                    end: token.char_span.0,
+                    next: Some(token.word.text.as_ref()),
+                });
+            }
+            prev_end = token.char_span.1;
+        }
+        if chars.len() > prev_end {
+            gaps.push(Gap {
+                start: prev_end,
+                end: chars.len(),
+                next: None,
+            });
+        }
+
+        self.check_gaps(&chars, gaps.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_whitespace_errors() {
+        let text = "A  b , c .5 d. \nNext line  ";
+        let suggestions = Typography::english().check(text);
+
+        let spans: Vec<_> = suggestions
+            .iter()
+            .map(|x| (x.start, x.end, x.replacements[0].as_str()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![(1, 3, " "), (4, 5, ""), (14, 15, ""), (25, 27, "")]
+        );
+    }
+}
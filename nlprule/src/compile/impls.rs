@@ -6,15 +6,16 @@ use crate::{
     rule::{
         disambiguation::POSFilter,
         engine::composition::{Matcher, PosMatcher, TextMatcher},
-        DisambiguationRule, MatchGraph, Rule,
+        located, DisambiguationRule, MatchGraph, Rule,
     },
     rules::{Rules, RulesOptions},
     tokenizer::{chunk, Tokenizer, TokenizerOptions},
     types::*,
     utils::parallelism::MaybeParallelIterator,
+    Error,
 };
 
-use super::parse_structure::BuildInfo;
+use super::parse_structure::{structure_hash, BuildInfo};
 
 impl TextMatcher {
     pub fn new(matcher: Matcher, info: &mut BuildInfo) -> Self {
@@ -73,77 +74,166 @@ impl PosMatcher {
     }
 }
 
+fn source_location(path: &str, line: Option<String>) -> Option<SourceLocation> {
+    line.and_then(|x| x.parse().ok())
+        .map(|line| SourceLocation {
+            path: path.to_string(),
+            line,
+        })
+}
+
 impl Rules {
+    /// Reads the grammar rules from an XML file. Fails if the file can not be read or is not valid XML,
+    /// rules which can not be converted are skipped.
     pub fn from_xml<P: AsRef<std::path::Path>>(
         path: P,
         build_info: &mut BuildInfo,
         options: RulesOptions,
-    ) -> Self {
-        use log::warn;
-        use std::collections::HashMap;
+    ) -> Result<Self, Error> {
+        Rules::from_xml_files(&[path], build_info, options)
+    }
 
-        let rules = super::parse_structure::read_rules(path);
-        let mut errors: HashMap<String, usize> = HashMap::new();
+    /// Reads the grammar rules from multiple XML files e. g. `grammar.xml` and `style.xml`, in the given order.
+    /// IDs do not depend on the other files, a rule with the ID of a rule in a previous file is skipped with an error.
+    /// The [source location][Rule::source_location] of each rule tells which file it is from.
+    pub fn from_xml_files<P: AsRef<std::path::Path>>(
+        paths: &[P],
+        build_info: &mut BuildInfo,
+        options: RulesOptions,
+    ) -> Result<Self, Error> {
+        use log::{info, warn};
+        use std::collections::{BTreeMap, HashMap, HashSet};
 
-        let rules: Vec<_> = rules
-            .into_iter()
-            .filter_map(|x| match x {
-                Ok((rule_structure, group, category)) => {
-                    let id = rule_structure.id.as_ref().map_or_else(
-                        || {
-                            let group = group.as_ref().expect("must have group if ID not set");
-                            format!("{}.{}", group.id, group.n)
-                        },
-                        |x| x.clone(),
-                    );
-                    let category = category.expect("grammar rules must have category");
-                    let off = rule_structure
-                        .default
-                        .as_ref()
-                        .map(|x| x == "off")
-                        .or_else(|| {
-                            group
-                                .as_ref()
-                                .and_then(|x| x.default.as_ref().map(|x| x == "off"))
-                        })
-                        .or_else(|| category.default.as_ref().map(|x| x == "off"))
-                        .unwrap_or(false);
-                    let name = rule_structure.name.as_ref().map_or_else(
-                        || {
-                            let group = group.as_ref().expect("must have group if name not set");
-                            group.name.clone()
-                        },
-                        |x| x.clone(),
-                    );
+        let mut errors: HashMap<String, usize> = HashMap::new();
+        let mut ids = HashSet::new();
+        let mut rules = Vec::new();
 
-                    match Rule::from_rule_structure(rule_structure, build_info) {
-                        Ok(mut rule) => {
-                            if (options.ids.is_empty() || options.ids.contains(&id))
-                                && !options.ignore_ids.contains(&id)
-                            {
-                                rule.id = id;
-                                rule.name = name;
-                                rule.on = !off;
-                                rule.category_id = category.id;
-                                rule.category_name = category.name;
-                                rule.category_type = category.kind;
-                                Some(rule)
-                            } else {
+        for path in paths {
+            let path_str = path.as_ref().display().to_string();
+            let file_rules: Vec<_> = super::parse_structure::read_rules(path)
+                .map_err(|x| Error::InvalidInput(format!("{}: {}", path_str, x)))?
+                .into_iter()
+                .filter_map(|x| match x {
+                    Ok((mut rule_structure, group, category)) => {
+                        // the line is not part of the structure hash, so moving a rule does not change it
+                        let source_location =
+                            source_location(&path_str, rule_structure.line.take());
+                        let id = rule_structure.id.as_ref().map_or_else(
+                            || {
+                                let group = group.as_ref().expect("must have group if ID not set");
+                                format!("{}.{}", group.id, group.n)
+                            },
+                            |x| x.clone(),
+                        );
+                        let category = category.expect("grammar rules must have category");
+                        // the innermost `default` attribute wins, rules temporarily disabled upstream are kept but off
+                        let default = rule_structure
+                            .default
+                            .clone()
+                            .or_else(|| group.as_ref().and_then(|x| x.default.clone()))
+                            .or_else(|| category.default.clone());
+                        let temp_off = default.as_deref() == Some("temp_off");
+                        // like `default`, the innermost `priority` attribute wins
+                        let priority = rule_structure
+                            .priority
+                            .clone()
+                            .or_else(|| group.as_ref().and_then(|x| x.priority.clone()))
+                            .or_else(|| category.priority.clone());
+                        let priority = match priority.map(|x| x.parse::<i32>()) {
+                            Some(Ok(priority)) => priority,
+                            Some(Err(x)) => {
+                                info!(
+                                    "Rule {}: invalid priority: {}",
+                                    located(&id, &source_location),
+                                    x
+                                );
+                                *errors.entry(format!("[Priority] {}", x)).or_insert(0) += 1;
+                                0
+                            }
+                            None => 0,
+                        };
+                        let off = temp_off || default.as_deref() == Some("off");
+                        let name = rule_structure.name.as_ref().map_or_else(
+                            || {
+                                let group =
+                                    group.as_ref().expect("must have group if name not set");
+                                group.name.clone()
+                            },
+                            |x| x.clone(),
+                        );
+
+                        let key = structure_hash(&rule_structure);
+                        let rule = build_info.convert_cached(key, |info| {
+                            Rule::from_rule_structure(rule_structure, info)
+                        });
+
+                        match rule {
+                            Ok(mut rule) => {
+                                if (options.ids.is_empty() || options.ids.contains(&id))
+                                    && !options.ignore_ids.contains(&id)
+                                {
+                                    rule.id = id;
+                                    rule.name = name;
+                                    // like the name, the short message and the URL can be set on the group
+                                    if let Some(group) = &group {
+                                        rule.short =
+                                            rule.short.take().or_else(|| group.short.clone());
+                                        rule.url = rule.url.take().or_else(|| group.url.clone());
+                                    }
+                                    rule.on = !off;
+                                    rule.temp_off = temp_off;
+                                    rule.priority = priority;
+                                    rule.category_id = category.id;
+                                    rule.category_name = category.name;
+                                    rule.category_type = category.kind;
+                                    rule.source_location = source_location;
+                                    if options.safe_ids.contains(&rule.id) {
+                                        rule.safe = true;
+                                    } else if options.unsafe_ids.contains(&rule.id) {
+                                        rule.safe = false;
+                                    }
+                                    Some(rule)
+                                } else {
+                                    None
+                                }
+                            }
+                            Err(x) => {
+                                info!("Rule {}: {}", located(&id, &source_location), x);
+                                *errors.entry(format!("[Rule] {}", x)).or_insert(0) += 1;
                                 None
                             }
                         }
-                        Err(x) => {
-                            *errors.entry(format!("[Rule] {}", x)).or_insert(0) += 1;
-                            None
-                        }
                     }
+                    Err(x) => {
+                        *errors.entry(format!("[Structure] {}", x)).or_insert(0) += 1;
+                        None
+                    }
+                })
+                .collect();
+
+            let mut categories: BTreeMap<&str, usize> = BTreeMap::new();
+            let n_rules = rules.len();
+            for rule in file_rules {
+                if !ids.insert(rule.id.clone()) {
+                    info!(
+                        "Rule {}: duplicate ID",
+                        located(&rule.id, &rule.source_location)
+                    );
+                    *errors.entry("[Rule] duplicate ID".to_string()).or_insert(0) += 1;
+                    continue;
                 }
-                Err(x) => {
-                    *errors.entry(format!("[Structure] {}", x)).or_insert(0) += 1;
-                    None
-                }
-            })
-            .collect();
+                rules.push(rule);
+            }
+            for rule in &rules[n_rules..] {
+                *categories.entry(rule.category_id()).or_insert(0) += 1;
+            }
+            info!(
+                "{}: {} rules by category: {:?}",
+                path_str,
+                rules.len() - n_rules,
+                categories
+            );
+        }
 
         if !errors.is_empty() {
             let mut errors: Vec<(String, usize)> = errors.into_iter().collect();
@@ -152,7 +242,49 @@ impl Rules {
             warn!("Errors constructing Rules: {:#?}", &errors);
         }
 
-        Rules { rules }
+        let mut rules = Rules {
+            rules,
+            options,
+            metadata: Metadata::default(),
+            message_hook: None,
+            reranker: None,
+            sampler: None,
+            sentence_cache: None,
+        };
+
+        let disabled = rules.disable_unmatchable(build_info.tagger().tag_store().len());
+        if !disabled.is_empty() {
+            warn!(
+                "Disabled {} rules with POS tags not in the tagset: {:?}",
+                disabled.len(),
+                disabled
+            );
+        }
+
+        rules.sort_by_priority();
+
+        // the rules as built are the defaults
+        for rule in rules.rules.iter_mut() {
+            rule.default_on = rule.on;
+        }
+
+        Ok(rules)
+    }
+
+    /// Adds translated messages in the given locale. `messages` maps rule IDs to message texts.
+    /// A message for the ID of a rule group is used for all rules in the group without a message of their own.
+    pub fn add_localized_messages(
+        &mut self,
+        locale: &str,
+        messages: &DefaultHashMap<String, String>,
+    ) {
+        for rule in self.rules.iter_mut() {
+            let group_id = rule.id.rsplitn(2, '.').last().unwrap_or(&rule.id);
+
+            if let Some(text) = messages.get(&rule.id).or_else(|| messages.get(group_id)) {
+                rule.add_localized_message(locale, text);
+            }
+        }
     }
 }
 
@@ -165,13 +297,15 @@ impl Tokenizer {
     ) -> Result<Self, Box<dyn std::error::Error>> {
         use log::warn;
 
-        let rules = super::parse_structure::read_disambiguation_rules(path);
+        let path_str = path.as_ref().display().to_string();
+        let rules = super::parse_structure::read_disambiguation_rules(path)?;
         let mut error = None;
 
         let rules: Vec<_> = rules
             .into_iter()
             .filter_map(|x| match x {
-                Ok((rule_structure, group, _)) => {
+                Ok((mut rule_structure, group, _)) => {
+                    let source_location = source_location(&path_str, rule_structure.line.take());
                     let id = rule_structure.id.as_ref().map_or_else(
                         || {
                             let group = group.expect("must have group if ID not set");
@@ -180,13 +314,19 @@ impl Tokenizer {
                         |x| x.clone(),
                     );
 
-                    match DisambiguationRule::from_rule_structure(rule_structure, build_info) {
+                    let key = structure_hash(&rule_structure);
+                    let rule = build_info.convert_cached(key, |info| {
+                        DisambiguationRule::from_rule_structure(rule_structure, info)
+                    });
+
+                    match rule {
                         Ok(mut rule) => {
                             if error.is_none()
                                 && (options.ids.is_empty() || options.ids.contains(&id))
                                 && !options.ignore_ids.contains(&id)
                             {
                                 rule.id = id;
+                                rule.source_location = source_location;
 
                                 Some(rule)
                             } else {
@@ -194,7 +334,8 @@ impl Tokenizer {
                             }
                         }
                         Err(x) => {
-                            error = Some(format!("[Rule] {}", x));
+                            error =
+                                Some(format!("[Rule] {}: {}", located(&id, &source_location), x));
                             None
                         }
                     }
@@ -219,6 +360,10 @@ impl Tokenizer {
             chunker,
             rules,
             options,
+            metadata: Metadata::default(),
+            upos_mapping: None,
+            placeholders: None,
+            sentence_exceptions: None,
         })
     }
 }
@@ -1,121 +1,868 @@
+//! Compilation of the LanguageTool resources of a language into the [Tokenizer] and [Rules] binaries, see
+//! [compile_language]. Only available with the `compile` feature so users who load prebuilt binaries do not depend
+//! on the XML and JSON parsers. The [BuildOptions] of the `compile` binary can only be parsed from the command line
+//! with the `bin` feature.
+
 use std::{
     collections::HashSet,
     fs::{read_to_string, File},
     hash::{Hash, Hasher},
     io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+#[cfg(feature = "bin")]
 use clap::Clap;
+use serde::de::DeserializeOwned;
 
 use crate::{
-    rules::{Rules, RulesOptions},
-    tokenizer::{chunk::Chunker, tag::Tagger, Tokenizer, TokenizerOptions},
-    types::DefaultHasher,
+    corpus::CorpusMatches,
+    rules::{Rules, RulesOptions, TestReport, TestSelection},
+    tokenizer::{
+        chunk::Chunker,
+        sentence::{SentenceException, SentenceExceptions},
+        tag::Tagger,
+        upos::{Upos, UposMapping},
+        Tokenizer, TokenizerOptions,
+    },
+    types::{DefaultHashMap, DefaultHasher, Metadata},
+    utils, Error,
 };
 
-use self::parse_structure::{BuildInfo, RegexCache};
+use self::parse_structure::{BuildInfo, RegexCache, RuleCache};
 
 mod impls;
 mod parse_structure;
 mod structure;
 
-#[derive(Clap)]
-#[clap(
-    version = env!("CARGO_PKG_VERSION"),
-    author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+/// The command line options of the `compile` binary, see [compile]. Parsing them from the command line needs
+/// the `bin` feature.
+#[cfg_attr(feature = "bin", derive(Clap))]
+#[cfg_attr(
+    feature = "bin",
+    clap(
+        version = env!("CARGO_PKG_VERSION"),
+        author = "Benjamin Minixhofer <bminixhofer@gmail.com>"
+    )
 )]
 pub struct BuildOptions {
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub tag_paths: Vec<String>,
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub tag_remove_paths: Vec<String>,
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub disambiguation_path: String,
-    #[clap(long)]
-    pub grammar_path: String,
-    #[clap(long)]
+    /// The XML files with the grammar rules. File names can contain `*` to match any characters, paths starting
+    /// with `!` exclude the files they match.
+    #[cfg_attr(feature = "bin", clap(long = "grammar-path", required = true))]
+    pub grammar_paths: Vec<String>,
+    #[cfg_attr(feature = "bin", clap(long))]
     pub tokenizer_config_path: String,
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub rules_config_path: String,
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub chunker_path: Option<String>,
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub common_words_path: Option<String>,
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub regex_cache_path: String,
-    #[clap(long)]
+    /// Where to cache converted rules. If set, only rules whose XML changed since the last compilation are converted.
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub rule_cache_path: Option<String>,
+    /// JSON files mapping rule IDs to translated messages.
+    /// The locale is the file stem e. g. "de" for "messages/de.json".
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub messages_paths: Vec<String>,
+    /// The version of LanguageTool the XML files are from, stored in the metadata of the binaries.
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub lt_version: Option<String>,
+    /// JSON file mapping prefixes of the tags to Universal POS tags e. g. `{"NN": "NOUN"}`.
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub upos_mapping_path: Option<String>,
+    /// JSON file with exceptions to sentence boundaries e. g. `[{"before": "\\b[A-Z]\\.", "after": null}]`.
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub sentence_exceptions_path: Option<String>,
+    /// Do not store the XML path and line of each rule in the binaries.
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub strip_source_locations: bool,
+    /// File with one text without errors per line. If set, the compiled rules are checked on it.
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub clean_corpus_path: Option<String>,
+    /// The maximum number of matches of a rule on the clean corpus.
+    #[cfg_attr(feature = "bin", clap(long, default_value = "0"))]
+    pub max_corpus_matches: usize,
+    /// Fail instead of warning if a rule matches more often than `max_corpus_matches` on the clean corpus.
+    #[cfg_attr(feature = "bin", clap(long))]
+    pub fail_on_corpus_matches: bool,
+    #[cfg_attr(feature = "bin", clap(long))]
     pub out_tokenizer_path: String,
-    #[clap(long)]
+    #[cfg_attr(feature = "bin", clap(long))]
     pub out_rules_path: String,
 }
 
-pub fn compile(opts: &BuildOptions) {
-    let common_words = opts
-        .common_words_path
-        .as_ref()
-        .map_or_else(HashSet::new, |path| {
-            read_to_string(path)
-                .unwrap()
-                .lines()
-                .map(|x| x.to_string())
-                .collect()
-        });
+/// The paths to the LanguageTool resources of one language, see [compile_language].
+#[derive(Debug, Clone, Default)]
+pub struct LanguagePaths {
+    /// Files where each line contains a word, its lemma and its tag separated by tabs.
+    pub tag_paths: Vec<String>,
+    /// Files in the same format as `tag_paths` with entries to remove.
+    pub tag_remove_paths: Vec<String>,
+    /// The XML file with the disambiguation rules.
+    pub disambiguation_path: String,
+    /// The XML files with the grammar rules e. g. `grammar.xml` and `style.xml`. The file name of a path can contain
+    /// `*` to match any characters, e. g. `data/en/*.xml`. Paths starting with `!` exclude the files they match, e. g.
+    /// `!data/en/grammar-premium.xml`. The files are read in the given order, the files matching one path sorted by name.
+    pub grammar_paths: Vec<String>,
+    /// JSON file with the chunker model.
+    pub chunker_path: Option<String>,
+    /// File with one common word per line. These words are always in the word store of the tagger.
+    pub common_words_path: Option<String>,
+    /// JSON files mapping rule IDs to translated messages.
+    /// The locale is the file stem e. g. "de" for "messages/de.json".
+    pub messages_paths: Vec<String>,
+    /// JSON file mapping prefixes of the tags to Universal POS tags e. g. `{"NN": "NOUN"}`.
+    pub upos_mapping_path: Option<String>,
+    /// JSON file with exceptions to sentence boundaries e. g. `[{"before": "\\b[A-Z]\\.", "after": null}]`.
+    pub sentence_exceptions_path: Option<String>,
+}
+
+/// Checks the freshly compiled rules on a corpus of text without errors, to catch rules which match too often
+/// (e. g. because of a wrong translation of a Java regex) before the binaries are released, see [CorpusMatches].
+#[derive(Debug, Clone, Default)]
+pub struct CorpusGate {
+    /// File with one text without errors per line.
+    pub path: String,
+    /// The maximum number of matches of a rule on the corpus.
+    pub max_matches: usize,
+    /// Whether compiling fails if a rule matches more often. Otherwise a warning is logged for each rule.
+    pub fail: bool,
+}
 
-    let tokenizer_options: TokenizerOptions =
-        serde_json::from_str(&read_to_string(&opts.tokenizer_config_path).unwrap()).unwrap();
-    let rules_options: RulesOptions =
-        serde_json::from_str(&read_to_string(&opts.rules_config_path).unwrap()).unwrap();
+impl CorpusGate {
+    /// Checks the rules on the corpus and logs or returns the rules exceeding the maximum number of matches.
+    fn check(&self, rules: &Rules, tokenizer: &Tokenizer) -> Result<(), Error> {
+        let mut matches = CorpusMatches::new();
+        matches.add_lines(BufReader::new(File::open(&self.path)?), rules, tokenizer)?;
 
-    let tagger = Tagger::from_dumps(
-        &opts.tag_paths,
-        &opts.tag_remove_paths,
-        &tokenizer_options.extra_tags,
-        &common_words,
-    )
-    .unwrap();
+        let exceeding = matches.exceeding(self.max_matches);
+        if exceeding.is_empty() {
+            return Ok(());
+        }
+
+        if self.fail {
+            return Err(Error::InvalidInput(format!(
+                "rules match more than {} times on the clean corpus {}: {:?}",
+                self.max_matches, self.path, exceeding
+            )));
+        }
+        for (id, count) in exceeding {
+            log::warn!(
+                "Rule {} matches {} times on the clean corpus {}",
+                id,
+                count,
+                self.path
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Options for [compile_language].
+#[derive(Clone, Default)]
+pub struct CompileOptions {
+    /// The options of the tokenizer.
+    pub tokenizer_options: TokenizerOptions,
+    /// The options of the rules.
+    pub rules_options: RulesOptions,
+    /// The version of LanguageTool the XML files are from, stored in the metadata of the binaries.
+    pub lt_version: Option<String>,
+    /// Do not store the XML path and line of each rule in the binaries.
+    pub strip_source_locations: bool,
+    /// Where to cache translated regexes. Speeds up compiling the same language again.
+    pub regex_cache_path: Option<String>,
+    /// Where to cache converted rules. If set, only rules whose XML changed since the last compilation are converted.
+    pub rule_cache_path: Option<String>,
+    /// If set, the compiled rules are checked on a corpus of text without errors.
+    pub corpus_gate: Option<CorpusGate>,
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    serde_json::from_reader(reader).map_err(|x| Error::InvalidInput(format!("{}: {}", path, x)))
+}
+
+/// Whether the `name` matches the `pattern` in which `*` matches any characters.
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<_> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.len() >= part.len() && rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.is_empty()
+}
+
+/// Expands paths with wildcards and exclusions, see [LanguagePaths::grammar_paths].
+fn expand_paths(paths: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let matching = |pattern: &str| -> Result<Vec<PathBuf>, Error> {
+        let pattern = Path::new(pattern);
+        let name = pattern.file_name().and_then(|x| x.to_str()).unwrap_or("");
+        if !name.contains('*') {
+            return Ok(vec![pattern.to_path_buf()]);
+        }
+
+        let dir = match pattern.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut matches: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|x| x.path()))
+            .filter(|path| {
+                matches!(path.file_name().and_then(|x| x.to_str()), Some(x) if matches_wildcard(name, x))
+            })
+            .collect();
+        matches.sort();
+        Ok(matches)
+    };
+
+    let mut excluded = HashSet::new();
+    for path in paths.iter().filter_map(|x| x.strip_prefix('!')) {
+        excluded.extend(matching(path)?);
+    }
+
+    let mut expanded = Vec::new();
+    for path in paths.iter().filter(|x| !x.starts_with('!')) {
+        let matches = matching(path)?;
+        if matches.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "{} does not match any file",
+                path
+            )));
+        }
+        for path in matches {
+            if !excluded.contains(&path) && !expanded.contains(&path) {
+                expanded.push(path);
+            }
+        }
+    }
+
+    Ok(expanded)
+}
+
+fn metadata<P: AsRef<Path>>(
+    lt_version: &Option<String>,
+    source_paths: &[P],
+) -> Result<Metadata, Error> {
+    // the hash is stored in the binaries, so it must be the same across Rust versions
+    let mut hasher = utils::StableHasher::default();
+    for path in source_paths {
+        hasher.write(read_to_string(path)?.as_bytes());
+        // separates the files so e. g. moving text from one file to the next changes the hash
+        hasher.write_u8(0xff);
+    }
 
+    Ok(Metadata {
+        lt_version: lt_version.clone(),
+        compiled_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|x| x.as_secs()),
+        nlprule_version: env!("CARGO_PKG_VERSION").to_string(),
+        source_hash: Some(format!("{:016x}", hasher.finish())),
+    })
+}
+
+fn word_store_hash(tagger: &Tagger) -> u64 {
     let mut hasher = DefaultHasher::default();
     let mut word_store = tagger.word_store().iter().collect::<Vec<_>>();
     word_store.sort_by(|a, b| a.1.cmp(b.1));
     word_store.hash(&mut hasher);
-    let word_store_hash = hasher.finish();
-
-    let regex_cache = if let Ok(file) = File::open(&opts.regex_cache_path) {
-        let cache: RegexCache = bincode::deserialize_from(BufReader::new(file)).unwrap();
-        if *cache.word_hash() == word_store_hash {
-            cache
-        } else {
-            RegexCache::new(word_store_hash)
+    hasher.finish()
+}
+
+fn tag_store_hash(tagger: &Tagger) -> u64 {
+    let mut hasher = DefaultHasher::default();
+    let mut tag_store = tagger.tag_store().iter().collect::<Vec<_>>();
+    tag_store.sort_by(|a, b| a.1.cmp(b.1));
+    tag_store.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Compiles the LanguageTool resources of one language into a [Tokenizer] and [Rules], which can be serialized
+/// with `bincode` to get the binaries loaded by [Tokenizer::new] and [Rules::new].
+///
+/// ```no_run
+/// use nlprule::compile::{compile_language, CompileOptions, LanguagePaths};
+/// use std::{fs::File, io::BufWriter};
+///
+/// let paths = LanguagePaths {
+///     tag_paths: vec!["data/en/tags/output.dump".into()],
+///     disambiguation_path: "data/en/disambiguation.xml".into(),
+///     grammar_paths: vec!["data/en/grammar.xml".into(), "data/en/style.xml".into()],
+///     ..Default::default()
+/// };
+/// let (tokenizer, rules) = compile_language(&paths, CompileOptions::default())?;
+///
+/// bincode::serialize_into(BufWriter::new(File::create("en_tokenizer.bin")?), &tokenizer)?;
+/// bincode::serialize_into(BufWriter::new(File::create("en_rules.bin")?), &rules)?;
+/// # Ok::<(), nlprule::Error>(())
+/// ```
+///
+/// # Errors
+/// If a file can not be read or a JSON file or the disambiguation rules are invalid, or if the rules do not pass the
+/// [CorpusGate] with `fail` set. Grammar rules which can not be converted are skipped with a warning.
+pub fn compile_language(
+    paths: &LanguagePaths,
+    options: CompileOptions,
+) -> Result<(Tokenizer, Rules), Error> {
+    let common_words = match &paths.common_words_path {
+        Some(path) => read_to_string(path)?
+            .lines()
+            .map(|x| x.to_string())
+            .collect(),
+        None => HashSet::new(),
+    };
+
+    let tagger = Tagger::from_dumps(
+        &paths.tag_paths,
+        &paths.tag_remove_paths,
+        &options.tokenizer_options.extra_tags,
+        &common_words,
+    )?;
+
+    let word_store_hash = word_store_hash(&tagger);
+    let tag_store_hash = tag_store_hash(&tagger);
+
+    let regex_cache = match options
+        .regex_cache_path
+        .as_ref()
+        .and_then(|path| File::open(path).ok())
+    {
+        Some(file) => {
+            let cache: RegexCache = bincode::deserialize_from(BufReader::new(file))?;
+            if *cache.word_hash() == word_store_hash {
+                cache
+            } else {
+                RegexCache::new(word_store_hash)
+            }
         }
-    } else {
-        RegexCache::new(word_store_hash)
+        None => RegexCache::new(word_store_hash),
     };
 
     let mut build_info = BuildInfo::new(Arc::new(tagger), regex_cache);
+    if let Some(path) = &options.rule_cache_path {
+        let rule_cache = File::open(path)
+            .ok()
+            .and_then(|file| bincode::deserialize_from::<_, RuleCache>(BufReader::new(file)).ok())
+            .filter(|cache| cache.is_valid(word_store_hash, tag_store_hash))
+            .unwrap_or_else(|| RuleCache::new(word_store_hash, tag_store_hash));
+        build_info.set_rule_cache(rule_cache);
+    }
+
+    let chunker = match &paths.chunker_path {
+        Some(path) => Some(Chunker::from_json(BufReader::new(File::open(path)?))),
+        None => None,
+    };
 
-    let tokenizer = Tokenizer::from_xml(
-        &opts.disambiguation_path,
+    let mut tokenizer = Tokenizer::from_xml(
+        &paths.disambiguation_path,
         &mut build_info,
-        if let Some(path) = &opts.chunker_path {
-            let reader = BufReader::new(File::open(path).unwrap());
-            let chunker = Chunker::from_json(reader);
-            Some(chunker)
-        } else {
-            None
-        },
-        tokenizer_options,
+        chunker,
+        options.tokenizer_options,
     )
-    .unwrap();
+    .map_err(|x| Error::InvalidInput(format!("{}: {}", paths.disambiguation_path, x)))?;
+    tokenizer.metadata = metadata(&options.lt_version, &[&paths.disambiguation_path])?;
+    if let Some(path) = &paths.upos_mapping_path {
+        let prefixes: DefaultHashMap<String, Upos> = read_json(path)?;
+        tokenizer.set_upos_mapping(Some(UposMapping::new(prefixes.into_iter().collect())));
+    }
+    if let Some(path) = &paths.sentence_exceptions_path {
+        let exceptions: Vec<SentenceException> = read_json(path)?;
+        tokenizer.set_sentence_exceptions(Some(SentenceExceptions::new(&exceptions)?));
+    }
+    if options.strip_source_locations {
+        tokenizer.strip_source_locations();
+    }
 
-    let f = BufWriter::new(File::create(&opts.out_tokenizer_path).unwrap());
-    bincode::serialize_into(f, &tokenizer).unwrap();
+    let grammar_paths = expand_paths(&paths.grammar_paths)?;
+    let mut rules = Rules::from_xml_files(&grammar_paths, &mut build_info, options.rules_options)?;
+    rules.metadata = metadata(&options.lt_version, &grammar_paths)?;
+    if options.strip_source_locations {
+        rules.strip_source_locations();
+    }
+
+    for path in &paths.messages_paths {
+        let locale = Path::new(path)
+            .file_stem()
+            .and_then(|x| x.to_str())
+            .ok_or_else(|| {
+                Error::InvalidInput(format!("messages path {} must have a file stem", path))
+            })?;
+        let messages: DefaultHashMap<String, String> = read_json(path)?;
 
-    let rules = Rules::from_xml(&opts.grammar_path, &mut build_info, rules_options);
+        rules.add_localized_messages(locale, &messages);
+    }
 
-    let f = BufWriter::new(File::create(&opts.regex_cache_path).unwrap());
-    bincode::serialize_into(f, build_info.mut_regex_cache()).unwrap();
+    if !build_info.regex_warnings().is_empty() {
+        let mut warnings: Vec<_> = build_info.regex_warnings().iter().collect();
+        warnings.sort_by_key(|x| std::cmp::Reverse(*x.1));
+
+        log::warn!("Warnings translating Java regexes: {:#?}", warnings);
+    }
+
+    if let Some(gate) = &options.corpus_gate {
+        gate.check(&rules, &tokenizer)?;
+    }
+
+    if let (Some(path), Some(rule_cache)) = (&options.rule_cache_path, build_info.mut_rule_cache())
+    {
+        rule_cache.prune();
+        bincode::serialize_into(BufWriter::new(File::create(path)?), rule_cache)?;
+    }
+
+    if let Some(path) = &options.regex_cache_path {
+        bincode::serialize_into(
+            BufWriter::new(File::create(path)?),
+            build_info.mut_regex_cache(),
+        )?;
+    }
+
+    Ok((tokenizer, rules))
+}
+
+/// Compiles the binaries as configured with the command line options of the `compile` binary and writes them to
+/// the output paths. Panics on errors, see [compile_language] to handle them.
+pub fn compile(opts: &BuildOptions) {
+    let paths = LanguagePaths {
+        tag_paths: opts.tag_paths.clone(),
+        tag_remove_paths: opts.tag_remove_paths.clone(),
+        disambiguation_path: opts.disambiguation_path.clone(),
+        grammar_paths: opts.grammar_paths.clone(),
+        chunker_path: opts.chunker_path.clone(),
+        common_words_path: opts.common_words_path.clone(),
+        messages_paths: opts.messages_paths.clone(),
+        upos_mapping_path: opts.upos_mapping_path.clone(),
+        sentence_exceptions_path: opts.sentence_exceptions_path.clone(),
+    };
+    let options = CompileOptions {
+        tokenizer_options: read_json(&opts.tokenizer_config_path).unwrap(),
+        rules_options: read_json(&opts.rules_config_path).unwrap(),
+        lt_version: opts.lt_version.clone(),
+        strip_source_locations: opts.strip_source_locations,
+        regex_cache_path: Some(opts.regex_cache_path.clone()),
+        rule_cache_path: opts.rule_cache_path.clone(),
+        corpus_gate: opts.clean_corpus_path.as_ref().map(|path| CorpusGate {
+            path: path.clone(),
+            max_matches: opts.max_corpus_matches,
+            fail: opts.fail_on_corpus_matches,
+        }),
+    };
+
+    let (tokenizer, rules) = compile_language(&paths, options).unwrap();
+
+    let f = BufWriter::new(File::create(&opts.out_tokenizer_path).unwrap());
+    bincode::serialize_into(f, &tokenizer).unwrap();
 
     let f = BufWriter::new(File::create(&opts.out_rules_path).unwrap());
     bincode::serialize_into(f, &rules).unwrap();
 }
+
+/// Compiles and tests the grammar rules in one XML file against an existing tokenizer, see [watch].
+pub struct RulesWatcher<'a> {
+    grammar_path: PathBuf,
+    tokenizer: &'a Tokenizer,
+    options: RulesOptions,
+    build_info: BuildInfo,
+}
+
+impl<'a> RulesWatcher<'a> {
+    /// Creates a new watcher for the grammar rules in `grammar_path`.
+    /// The rules are converted with the tagger of `tokenizer`, which is also used to run the tests.
+    pub fn new<P: AsRef<Path>>(
+        grammar_path: P,
+        tokenizer: &'a Tokenizer,
+        options: RulesOptions,
+    ) -> Self {
+        let word_store_hash = word_store_hash(tokenizer.tagger());
+        let mut build_info =
+            BuildInfo::new(tokenizer.tagger().clone(), RegexCache::new(word_store_hash));
+        // only rules which changed since the last run have to be converted again
+        build_info.set_rule_cache(RuleCache::new(
+            word_store_hash,
+            tag_store_hash(tokenizer.tagger()),
+        ));
+
+        RulesWatcher {
+            grammar_path: grammar_path.as_ref().to_path_buf(),
+            tokenizer,
+            options,
+            build_info,
+        }
+    }
+
+    /// Compiles the rules and runs their tests. If the file can not be read or is not valid XML,
+    /// no tests are run and the [error][TestReport::error] is reported instead.
+    pub fn run(&mut self) -> TestReport {
+        let rules = match Rules::from_xml(
+            &self.grammar_path,
+            &mut self.build_info,
+            self.options.clone(),
+        ) {
+            Ok(rules) => rules,
+            Err(error) => {
+                return TestReport {
+                    error: Some(error.to_string()),
+                    ..TestReport::default()
+                }
+            }
+        };
+        if let Some(rule_cache) = self.build_info.mut_rule_cache() {
+            rule_cache.prune();
+        }
+
+        rules.test(self.tokenizer, &TestSelection::All)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.grammar_path)
+            .and_then(|x| x.modified())
+            .ok()
+    }
+}
+
+/// Watches an XML file with grammar rules for rule authoring: each time the file changes,
+/// its rules are compiled and tested against the `tokenizer` and `on_report` is called with the result.
+/// The file is checked for changes every `interval`. Returns as soon as `on_report` returns `false`.
+///
+/// The full tokenizer and rule binaries do not have to be rebuilt for this, e. g.
+///
+/// ```no_run
+/// use nlprule::{compile::watch, rules::RulesOptions, Tokenizer};
+/// use std::time::Duration;
+///
+/// let tokenizer = Tokenizer::new("storage/en_tokenizer.bin")?;
+/// watch("my_rules.xml", &tokenizer, RulesOptions::default(), Duration::from_secs(1), |report| {
+///     println!("passed: {}, failed: {:?}", report.passed.len(), report.failed);
+///     true
+/// });
+/// # Ok::<(), nlprule::Error>(())
+/// ```
+pub fn watch<P, F>(
+    grammar_path: P,
+    tokenizer: &Tokenizer,
+    options: RulesOptions,
+    interval: Duration,
+    mut on_report: F,
+) where
+    P: AsRef<Path>,
+    F: FnMut(&TestReport) -> bool,
+{
+    let mut watcher = RulesWatcher::new(grammar_path, tokenizer, options);
+    let mut last_modified = None;
+
+    loop {
+        let modified = watcher.modified();
+
+        // the file can be missing for a moment while an editor saves it
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+
+            if !on_report(&watcher.run()) {
+                return;
+            }
+        }
+
+        std::thread::sleep(interval);
+    }
+}
+
+/// Builds small tokenizers and rule sets from strings for unit tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Writes `content` to a new file in the temporary directory.
+    pub fn temp_file(content: &str) -> PathBuf {
+        static N: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "nlprule-test-{}-{}",
+            std::process::id(),
+            N.fetch_add(1, Ordering::SeqCst)
+        ));
+        File::create(&path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .unwrap();
+        path
+    }
+
+    /// A tokenizer without disambiguation rules whose tagger only knows the `(word, lemma, tag)` entries.
+    pub fn tokenizer(entries: &[(&str, &str, &str)]) -> Tokenizer {
+        let dump: String = entries
+            .iter()
+            .map(|(word, lemma, tag)| format!("{}\t{}\t{}\n", word, lemma, tag))
+            .collect();
+        let path = temp_file(&dump);
+        let tagger = Tagger::from_dumps(
+            &[path.to_str().unwrap()],
+            &[] as &[&str],
+            &[] as &[&str],
+            &HashSet::new(),
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        Tokenizer {
+            tagger: Arc::new(tagger.unwrap()),
+            ..Tokenizer::default()
+        }
+    }
+
+    /// Like [tokenizer], but with the disambiguation rules from the `<rule>` and `<rulegroup>` elements in `xml`.
+    pub fn disambiguator(entries: &[(&str, &str, &str)], xml: &str) -> Tokenizer {
+        let tokenizer = tokenizer(entries);
+        let path = temp_file(&format!("<rules lang=\"en\">{}</rules>", xml));
+        let mut build_info = BuildInfo::new(
+            tokenizer.tagger().clone(),
+            RegexCache::new(word_store_hash(tokenizer.tagger())),
+        );
+        let tokenizer =
+            Tokenizer::from_xml(&path, &mut build_info, None, TokenizerOptions::default());
+        std::fs::remove_file(&path).unwrap();
+
+        tokenizer.unwrap()
+    }
+
+    /// Builds rules from the `<rule>` and `<rulegroup>` elements in `xml`, they are put in a category with the ID
+    /// "TEST". The rules are converted with the tagger of `tokenizer`.
+    pub fn rules(xml: &str, tokenizer: &Tokenizer, options: RulesOptions) -> Rules {
+        let path = temp_file(&format!(
+            "<rules lang=\"en\"><category id=\"TEST\" name=\"Test\">{}</category></rules>",
+            xml
+        ));
+        let mut build_info = BuildInfo::new(
+            tokenizer.tagger().clone(),
+            RegexCache::new(word_store_hash(tokenizer.tagger())),
+        );
+        let rules = Rules::from_xml(&path, &mut build_info, options);
+        std::fs::remove_file(&path).unwrap();
+
+        rules.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches_wildcard("*.xml", "grammar.xml"));
+        assert!(matches_wildcard("grammar*.xml", "grammar-premium.xml"));
+        assert!(matches_wildcard("grammar.xml", "grammar.xml"));
+        assert!(matches_wildcard("g*r*.xml", "grammar.xml"));
+        assert!(!matches_wildcard("*.xml", "grammar.xml.bak"));
+        assert!(!matches_wildcard("style*", "grammar.xml"));
+        assert!(!matches_wildcard("a*a", "a"));
+    }
+
+    #[test]
+    fn embeds_metadata() {
+        let a = testing::temp_file("<rules/>");
+        let b = testing::temp_file("<rules></rules>");
+        let hash = |paths: &[&PathBuf]| metadata(&None, paths).unwrap().source_hash;
+
+        let built = metadata(&Some("5.2".to_string()), &[&a, &b]).unwrap();
+        assert_eq!(built.lt_version.as_deref(), Some("5.2"));
+        assert_eq!(built.nlprule_version, env!("CARGO_PKG_VERSION"));
+        assert!(built.compiled_at.is_some());
+        assert_eq!(built.source_hash, hash(&[&a, &b]));
+        assert_ne!(built.source_hash, hash(&[&b, &a]));
+        assert_ne!(hash(&[&a]), hash(&[&b]));
+        // the hash is stable, so it can be compared with hashes of other builds
+        assert_eq!(hash(&[&a]).as_deref(), Some("e4bf36a93ee96bdc"));
+
+        let rules = Rules {
+            metadata: built.clone(),
+            ..Rules::default()
+        };
+        let binary = bincode::serialize(&rules).unwrap();
+        assert_eq!(Rules::new_from(&binary[..]).unwrap().metadata(), &built);
+
+        std::fs::remove_file(&a).unwrap();
+        assert!(matches!(metadata(&None, &[&a]), Err(Error::Io(_))));
+        std::fs::remove_file(&b).unwrap();
+    }
+
+    #[test]
+    fn inherits_group_attributes() {
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rulegroup id="TEH" name="teh">
+            <short>Possible typo</short>
+            <url>https://example.com/teh</url>
+            <rule>
+                <pattern><token>teh</token></pattern>
+                <message>Did you mean <suggestion>the</suggestion>?</message>
+                <example correction="the">Read <marker>teh</marker> book.</example>
+            </rule>
+            <rule name="teh!">
+                <pattern><token>teh!</token></pattern>
+                <message>Did you mean <suggestion>the</suggestion>?</message>
+                <short>Typo</short>
+                <example correction="the">Read <marker>teh!</marker> book.</example>
+            </rule>
+        </rulegroup>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let attributes: Vec<_> = rules
+            .rules()
+            .iter()
+            .map(|x| (x.name(), x.short(), x.url()))
+            .collect();
+        assert_eq!(
+            attributes,
+            vec![
+                (
+                    "teh",
+                    Some("Possible typo"),
+                    Some("https://example.com/teh")
+                ),
+                ("teh!", Some("Typo"), Some("https://example.com/teh")),
+            ]
+        );
+    }
+
+    #[test]
+    fn unifies_grammar_rules() {
+        let tokenizer = testing::tokenizer(&[
+            ("this", "this", "DT:SG"),
+            ("these", "this", "DT:PL"),
+            ("big", "big", "JJ"),
+            ("cat", "cat", "NN:SG"),
+            ("cats", "cat", "NN:PL"),
+        ]);
+        let xml = r#"<unification feature="number">
+            <equivalence type="sg"><token postag=".*:SG" postag_regexp="yes"/></equivalence>
+            <equivalence type="pl"><token postag=".*:PL" postag_regexp="yes"/></equivalence>
+        </unification>
+        <rule id="LIKE_THIS" name="like this">
+            <pattern>
+                <token>like</token>
+                <marker>
+                    <unify>
+                        <feature id="number"/>
+                        <token postag="DT:.*" postag_regexp="yes"/>
+                        <unify-ignore><token postag="JJ"/></unify-ignore>
+                        <token postag="NN:.*" postag_regexp="yes"/>
+                    </unify>
+                </marker>
+            </pattern>
+            <message>Did you mean <suggestion>\2 \4</suggestion>?</message>
+            <example correction="this cat">I like <marker>this big cat</marker>.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let spans = |text: &str| -> Vec<_> {
+            rules
+                .suggest(text, &tokenizer)
+                .iter()
+                .map(|x| (x.start, x.end))
+                .collect()
+        };
+        // the adjective is ignored, the determiner and the noun have to agree in number
+        assert_eq!(spans("I like this big cat."), vec![(7, 19)]);
+        assert_eq!(spans("I like these big cats."), vec![(7, 21)]);
+        assert!(spans("I like this big cats.").is_empty());
+        assert!(spans("I like these big cat.").is_empty());
+    }
+
+    #[test]
+    fn reports_malformed_rules() {
+        let tokenizer = testing::tokenizer(&[]);
+        let path = testing::temp_file("<rules lang=\"en\"><category id=\"TEST\" name=\"Test\">");
+        let mut watcher = RulesWatcher::new(&path, &tokenizer, RulesOptions::default());
+
+        let report = watcher.run();
+        assert!(report.error.unwrap().contains("error reading XML"));
+
+        // the watcher can still compile the rules once the file is fixed
+        std::fs::write(
+            &path,
+            r#"<rules lang="en"><category id="TEST" name="Test">
+            <rule id="TEH" name="teh">
+                <pattern><token>teh</token></pattern>
+                <message>Did you mean <suggestion>the</suggestion>?</message>
+                <example correction="the">Read <marker>teh</marker> book.</example>
+            </rule>
+        </category></rules>"#,
+        )
+        .unwrap();
+        let report = watcher.run();
+        assert!(report.success());
+        assert_eq!(report.passed, vec!["TEH".to_string()]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn caches_rules_with_regex_warnings() {
+        let tokenizer = testing::tokenizer(&[]);
+        let hashes = (
+            word_store_hash(tokenizer.tagger()),
+            tag_store_hash(tokenizer.tagger()),
+        );
+        let path = testing::temp_file(
+            r#"<rules lang="en"><category id="TEST" name="Test">
+            <rule id="TEH" name="teh">
+                <pattern><token regexp="yes">\p{Lower}eh</token></pattern>
+                <message>Did you mean <suggestion>the</suggestion>?</message>
+                <example correction="the">Read <marker>teh</marker> book.</example>
+            </rule>
+        </category></rules>"#,
+        );
+
+        let compile = |rule_cache: RuleCache| {
+            let mut build_info =
+                BuildInfo::new(tokenizer.tagger().clone(), RegexCache::new(hashes.0));
+            build_info.set_rule_cache(rule_cache);
+            let rules = Rules::from_xml(&path, &mut build_info, RulesOptions::default()).unwrap();
+            let rule_cache = build_info.mut_rule_cache().unwrap();
+            rule_cache.prune();
+            let rule_cache: RuleCache =
+                bincode::deserialize(&bincode::serialize(rule_cache).unwrap()).unwrap();
+            (rules, build_info.regex_warnings().clone(), rule_cache)
+        };
+
+        let (rules, warnings, rule_cache) = compile(RuleCache::new(hashes.0, hashes.1));
+        assert_eq!(warnings.len(), 1);
+        assert!(rule_cache.is_valid(hashes.0, hashes.1));
+        assert!(!rule_cache.is_valid(hashes.0, hashes.1 + 1));
+
+        // the cached rule is used and its warnings are reported again
+        let (cached_rules, cached_warnings, mut rule_cache) = compile(rule_cache);
+        assert_eq!(cached_warnings, warnings);
+        assert_eq!(cached_rules.rules()[0].id(), rules.rules()[0].id());
+        assert_eq!(cached_rules.suggest("Read teh book.", &tokenizer).len(), 1);
+
+        rule_cache.insert(0, &"rule".to_string(), vec!["warning".to_string()]);
+        assert_eq!(
+            rule_cache.get::<String>(0),
+            Some(("rule".to_string(), vec!["warning".to_string()]))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
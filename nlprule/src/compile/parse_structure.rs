@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{hash::Hasher, sync::Arc};
 
 use super::structure;
 use crate::{filter::get_filter, utils, utils::regex::SerializeRegex, Error};
@@ -53,9 +53,95 @@ impl RegexCache {
     }
 }
 
+/// Hashes the structure of a rule as read from the XML. The hash is the same across Rust versions and platforms.
+pub fn structure_hash<T: Serialize>(data: &T) -> u64 {
+    let mut hasher = utils::StableHasher::default();
+    hasher.write(&bincode::serialize(data).expect("rule structure must be serializable"));
+    hasher.finish()
+}
+
+/// The version of the format of converted rules. Must be increased whenever the conversion in `src/compile` or
+/// the serialized representation of rules changes, so cached rules converted by older code are not used.
+const RULE_CACHE_FORMAT: u32 = 1;
+
+/// A converted rule together with the warnings about translating its regexes.
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedRule {
+    rule: Vec<u8>,
+    regex_warnings: Vec<String>,
+}
+
+/// Converted rules keyed by the [structure_hash] of the XML they were converted from,
+/// so only rules which changed since the last compilation have to be converted again.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RuleCache {
+    cache: DefaultHashMap<u64, CachedRule>,
+    // conversion depends on the word and tag store of the tagger and the code of nlprule
+    word_hash: u64,
+    tag_hash: u64,
+    version: String,
+    format: u32,
+    #[serde(skip)]
+    used: DefaultHashSet<u64>,
+}
+
+impl RuleCache {
+    pub fn new(word_hash: u64, tag_hash: u64) -> Self {
+        RuleCache {
+            cache: DefaultHashMap::default(),
+            word_hash,
+            tag_hash,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            format: RULE_CACHE_FORMAT,
+            used: DefaultHashSet::default(),
+        }
+    }
+
+    /// Whether the cached rules can be used with the given word store and tag store hash.
+    pub fn is_valid(&self, word_hash: u64, tag_hash: u64) -> bool {
+        self.word_hash == word_hash
+            && self.tag_hash == tag_hash
+            && self.version == env!("CARGO_PKG_VERSION")
+            && self.format == RULE_CACHE_FORMAT
+    }
+
+    /// Gets a cached rule and the warnings about translating its regexes.
+    pub fn get<T: serde::de::DeserializeOwned>(&mut self, key: u64) -> Option<(T, Vec<String>)> {
+        let cached = self.cache.get(&key)?;
+        let rule = bincode::deserialize(&cached.rule).ok()?;
+        let regex_warnings = cached.regex_warnings.clone();
+        self.used.insert(key);
+        Some((rule, regex_warnings))
+    }
+
+    pub fn insert<T: Serialize>(&mut self, key: u64, rule: &T, regex_warnings: Vec<String>) {
+        if let Ok(bytes) = bincode::serialize(rule) {
+            self.cache.insert(
+                key,
+                CachedRule {
+                    rule: bytes,
+                    regex_warnings,
+                },
+            );
+            self.used.insert(key);
+        }
+    }
+
+    /// Removes all rules which have not been used since the cache was loaded or last pruned.
+    pub fn prune(&mut self) {
+        let used = &self.used;
+        self.cache.retain(|key, _| used.contains(key));
+        self.used.clear();
+    }
+}
+
 pub struct BuildInfo {
     tagger: Arc<Tagger>,
     regex_cache: RegexCache,
+    rule_cache: Option<RuleCache>,
+    regex_warnings: DefaultHashMap<String, usize>,
+    // the warnings of the rule which is currently converted
+    rule_regex_warnings: Vec<String>,
 }
 
 impl BuildInfo {
@@ -63,7 +149,65 @@ impl BuildInfo {
         BuildInfo {
             tagger,
             regex_cache,
+            rule_cache: None,
+            regex_warnings: DefaultHashMap::default(),
+            rule_regex_warnings: Vec::new(),
+        }
+    }
+
+    /// Sets a cache of converted rules, see [RuleCache].
+    pub fn set_rule_cache(&mut self, rule_cache: RuleCache) {
+        self.rule_cache = Some(rule_cache);
+    }
+
+    pub fn mut_rule_cache(&mut self) -> Option<&mut RuleCache> {
+        self.rule_cache.as_mut()
+    }
+
+    /// Converts a rule with `convert` or gets it from the rule cache if one is set and contains `key`.
+    /// The regex warnings of a cached rule are recorded again, so they are the same with and without the cache.
+    pub fn convert_cached<T, F>(&mut self, key: u64, convert: F) -> Result<T, Error>
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+        F: FnOnce(&mut BuildInfo) -> Result<T, Error>,
+    {
+        if let Some((rule, warnings)) = self.rule_cache.as_mut().and_then(|x| x.get(key)) {
+            for warning in warnings {
+                *self.regex_warnings.entry(warning).or_insert(0) += 1;
+            }
+            return Ok(rule);
+        }
+
+        self.rule_regex_warnings.clear();
+        let rule = convert(self);
+        if let (Ok(rule), Some(cache)) = (&rule, self.rule_cache.as_mut()) {
+            cache.insert(key, rule, std::mem::take(&mut self.rule_regex_warnings));
         }
+        rule
+    }
+
+    /// Creates a regex from a Java regex in the XML and records warnings about its translation.
+    pub fn regex(
+        &mut self,
+        regex_str: &str,
+        must_fully_match: bool,
+        case_sensitive: bool,
+    ) -> Result<SerializeRegex, Error> {
+        let (regex, warnings) =
+            SerializeRegex::new_with_warnings(regex_str, must_fully_match, case_sensitive)?;
+
+        for warning in warnings {
+            log::info!("Regex \"{}\": {}", regex_str, warning);
+            self.rule_regex_warnings.push(warning.clone());
+            *self.regex_warnings.entry(warning).or_insert(0) += 1;
+        }
+
+        Ok(regex)
+    }
+
+    /// Gets how often each warning about the translation of Java regexes occurred.
+    pub fn regex_warnings(&self) -> &DefaultHashMap<String, usize> {
+        &self.regex_warnings
     }
 
     pub fn tagger(&self) -> &Arc<Tagger> {
@@ -137,7 +281,7 @@ fn parse_match_attribs(
 
     if text.is_some() || text_match_idx.is_some() {
         let matcher = if is_regex && text_match_idx.is_none() {
-            let regex = SerializeRegex::new(text.unwrap().trim(), true, case_sensitive);
+            let regex = info.regex(text.unwrap().trim(), true, case_sensitive);
             Matcher::new_regex(regex?, negate, inflected)
         } else {
             Matcher::new_string(
@@ -165,7 +309,7 @@ fn parse_match_attribs(
 
     if let Some(postag) = attribs.postag() {
         let raw_matcher = if is_postag_regexp {
-            let regex = SerializeRegex::new(&postag.trim(), true, true);
+            let regex = info.regex(postag.trim(), true, true);
             Matcher::new_regex(regex?, negate_pos, true)
         } else {
             Matcher::new_string(
@@ -205,7 +349,7 @@ fn parse_match_attribs(
             atoms.push(chunk_atom.into());
         }
         (None, Some(chunk_re)) => {
-            let regex = SerializeRegex::new(chunk_re.trim(), true, true)?;
+            let regex = info.regex(chunk_re.trim(), true, true)?;
             let chunk_atom = ChunkAtom {
                 matcher: Matcher::new_regex(regex, false, true),
             };
@@ -399,12 +543,6 @@ fn parse_match(
         }
     }
 
-    let case_conversion = if let Some(conversion) = &m.case_conversion {
-        Some(conversion.as_str())
-    } else {
-        None
-    };
-
     let pos_replacer = if let Some(postag) = m.postag {
         if postag.contains("+DT") || postag.contains("+INDT") {
             return Err(Error::Unimplemented(
@@ -414,7 +552,7 @@ fn parse_match(
 
         let matcher = match m.postag_regex.as_deref() {
             Some("yes") => {
-                let regex = SerializeRegex::new(&postag, true, false)?;
+                let regex = info.regex(&postag, true, false)?;
                 Matcher::new_regex(regex, false, true)
             }
             None => Matcher::new_string(either::Left(postag), false, false, true),
@@ -428,33 +566,36 @@ fn parse_match(
     };
 
     let regex_replacer = match (m.regexp_match, m.regexp_replace) {
-        (Some(regex_match), Some(regex_replace)) => Some((
-            SerializeRegex::new(&regex_match, false, true)?,
-            regex_replace,
-        )),
+        (Some(regex_match), Some(regex_replace)) => {
+            Some((info.regex(&regex_match, false, true)?, regex_replace))
+        }
         _ => None,
     };
 
     Ok(Match {
         id,
-        conversion: match case_conversion {
-            Some("alllower") => Conversion::AllLower,
-            Some("startlower") => Conversion::StartLower,
-            Some("startupper") => Conversion::StartUpper,
-            Some("allupper") => Conversion::AllUpper,
-            Some(x) => {
-                return Err(Error::Unimplemented(format!(
-                    "case conversion {} not supported.",
-                    x
-                )))
-            }
-            None => Conversion::Nop,
-        },
+        conversion: parse_conversion(m.case_conversion.as_deref())?,
         pos_replacer,
         regex_replacer,
     })
 }
 
+fn parse_conversion(case_conversion: Option<&str>) -> Result<Conversion, Error> {
+    Ok(match case_conversion {
+        Some("alllower") => Conversion::AllLower,
+        Some("startlower") => Conversion::StartLower,
+        Some("startupper") => Conversion::StartUpper,
+        Some("allupper") => Conversion::AllUpper,
+        Some(x) => {
+            return Err(Error::Unimplemented(format!(
+                "case conversion {} not supported.",
+                x
+            )))
+        }
+        None => Conversion::Nop,
+    })
+}
+
 fn parse_synthesizer_text(text: &str) -> Vec<SynthesizerPart> {
     lazy_static! {
         static ref MATCH_REGEX: Regex = Regex::new(r"\\(\d)").unwrap();
@@ -488,16 +629,47 @@ fn parse_synthesizer_text(text: &str) -> Vec<SynthesizerPart> {
     if end_index < text.len() {
         parts.push(SynthesizerPart::Text((&text[end_index..]).to_string()))
     }
+
     parts
+        .into_iter()
+        .flat_map(|part| match part {
+            SynthesizerPart::Text(text) => split_separators(&text),
+            part => vec![part],
+        })
+        .collect()
 }
 
-fn parse_suggestion(
-    data: structure::Suggestion,
+/// Splits whitespace with line breaks, i. e. from the formatting of the XML, off the text as separators.
+/// Other whitespace such as double spaces or tabs is kept as part of the text.
+fn split_separators(text: &str) -> Vec<SynthesizerPart> {
+    lazy_static! {
+        static ref SEPARATOR_REGEX: Regex = Regex::new(r"\s*[\r\n]\s*").unwrap();
+    }
+
+    let mut parts = Vec::new();
+    let mut end_index = 0;
+
+    for (start, end) in SEPARATOR_REGEX.find_iter(text) {
+        if end_index != start {
+            parts.push(SynthesizerPart::Text(text[end_index..start].to_string()));
+        }
+        parts.push(SynthesizerPart::Separator(text[start..end].to_string()));
+        end_index = end;
+    }
+
+    if end_index < text.len() {
+        parts.push(SynthesizerPart::Text(text[end_index..].to_string()));
+    }
+    parts
+}
+
+fn parse_suggestion_parts(
+    data: Vec<structure::SuggestionPart>,
     composition: &Option<&Composition>,
     info: &mut BuildInfo,
-) -> Result<Synthesizer, Error> {
+) -> Result<Vec<SynthesizerPart>, Error> {
     let mut parts = Vec::new();
-    for part in data.parts {
+    for part in data {
         match part {
             structure::SuggestionPart::Text(text) => {
                 parts.extend(parse_synthesizer_text(text.as_str()));
@@ -505,11 +677,30 @@ fn parse_suggestion(
             structure::SuggestionPart::Match(m) => {
                 parts.push(SynthesizerPart::Match(parse_match(m, composition, info)?));
             }
+            structure::SuggestionPart::Marker(marker) => {
+                parts.push(SynthesizerPart::Marker(parse_suggestion_parts(
+                    marker.parts,
+                    composition,
+                    info,
+                )?));
+            }
         }
     }
 
+    Ok(parts)
+}
+
+fn parse_suggestion(
+    data: structure::Suggestion,
+    composition: &Option<&Composition>,
+    info: &mut BuildInfo,
+) -> Result<Synthesizer, Error> {
+    let conversion = parse_conversion(data.case_conversion.as_deref())?;
+    let parts = parse_suggestion_parts(data.parts, composition, info)?;
+
     Ok(Synthesizer {
         parts,
+        conversion,
         // use titlecase adjustment (i. e. make replacement title case if match is title case) if token rule
         use_titlecase_adjust: composition.is_some(),
     })
@@ -541,14 +732,21 @@ fn parse_parallel_tokens(
 }
 
 fn parse_unify_tokens(
-    tokens: &[structure::UnifyTokenCombination],
+    unify: &structure::Unify,
+    first_id: usize,
     case_sensitive: bool,
+    unifications: &Option<Vec<structure::Unification>>,
     info: &mut BuildInfo,
-) -> Result<Vec<Part>, Error> {
+) -> Result<(Vec<Part>, Vec<Unification>), Error> {
     let mut out = Vec::new();
+    let mut out_unifications = Vec::new();
+    let mut filters = Vec::new();
+    let mut ids = Vec::new();
 
-    for token_combination in tokens {
-        out.extend(match token_combination {
+    for token_combination in &unify.tokens {
+        let next_id = first_id + out.iter().filter(|x: &&Part| x.visible).count();
+
+        let parts = match token_combination {
             structure::UnifyTokenCombination::Token(token) => {
                 parse_token(token, case_sensitive, info)?
             }
@@ -569,22 +767,40 @@ fn parse_unify_tokens(
                     visible: true,
                 }]
             }
-            structure::UnifyTokenCombination::Feature(_) => vec![],
+            structure::UnifyTokenCombination::Feature(feature) => {
+                filters.push(parse_feature(feature, unifications, info)?);
+                continue;
+            }
             structure::UnifyTokenCombination::Ignore(ignore) => {
-                parse_tokens(&ignore.tokens, case_sensitive, info)?
+                let (parts, nested) =
+                    parse_tokens(&ignore.tokens, next_id, case_sensitive, unifications, info)?;
+                out.extend(parts);
+                out_unifications.extend(nested);
+                continue;
             }
-        });
+        };
+
+        // the tokens in `unify-ignore` are matched but do not have to agree
+        ids.extend((next_id..).take(parts.iter().filter(|x| x.visible).count()));
+        out.extend(parts);
+    }
+
+    if !filters.is_empty() && !ids.is_empty() {
+        out_unifications.push(Unification { filters, ids });
     }
 
-    Ok(out)
+    Ok((out, out_unifications))
 }
 
 fn parse_tokens(
     tokens: &[structure::TokenCombination],
+    first_id: usize,
     case_sensitive: bool,
+    unifications: &Option<Vec<structure::Unification>>,
     info: &mut BuildInfo,
-) -> Result<Vec<Part>, Error> {
+) -> Result<(Vec<Part>, Vec<Unification>), Error> {
     let mut out = Vec::new();
+    let mut out_unifications = Vec::new();
 
     for token_combination in tokens {
         out.extend(match token_combination {
@@ -607,22 +823,30 @@ fn parse_tokens(
                 }]
             }
             structure::TokenCombination::Unify(unify) => {
-                parse_unify_tokens(&unify.tokens, case_sensitive, info)?
+                let next_id = first_id + out.iter().filter(|x: &&Part| x.visible).count();
+                let (parts, unify_unifications) =
+                    parse_unify_tokens(unify, next_id, case_sensitive, unifications, info)?;
+                out_unifications.extend(unify_unifications);
+                parts
             }
         });
     }
 
-    Ok(out)
+    Ok((out, out_unifications))
 }
 
+/// Parses a pattern into a composition, the start and end group ID of the marker and the unifications the
+/// matched tokens have to satisfy.
 fn parse_pattern(
     pattern: structure::Pattern,
+    unifications: &Option<Vec<structure::Unification>>,
     info: &mut BuildInfo,
-) -> Result<(Composition, usize, usize), Error> {
+) -> Result<(Composition, usize, usize, Vec<Unification>), Error> {
     let mut start = None;
     let mut end = None;
 
     let mut composition_parts = Vec::new();
+    let mut pattern_unifications = Vec::new();
     let case_sensitive = match &pattern.case_sensitive {
         Some(string) => string == "yes",
         None => false,
@@ -636,7 +860,15 @@ fn parse_pattern(
             structure::PatternPart::Marker(marker) => {
                 start = Some(get_last_id(&composition_parts));
 
-                composition_parts.extend(parse_tokens(&marker.tokens, case_sensitive, info)?);
+                let (parts, marker_unifications) = parse_tokens(
+                    &marker.tokens,
+                    get_last_id(&composition_parts) as usize,
+                    case_sensitive,
+                    unifications,
+                    info,
+                )?;
+                composition_parts.extend(parts);
+                pattern_unifications.extend(marker_unifications);
 
                 end = Some(get_last_id(&composition_parts));
             }
@@ -660,7 +892,15 @@ fn parse_pattern(
                 });
             }
             structure::PatternPart::Unify(unify) => {
-                composition_parts.extend(parse_unify_tokens(&unify.tokens, case_sensitive, info)?)
+                let (parts, unify_unifications) = parse_unify_tokens(
+                    unify,
+                    get_last_id(&composition_parts) as usize,
+                    case_sensitive,
+                    unifications,
+                    info,
+                )?;
+                composition_parts.extend(parts);
+                pattern_unifications.extend(unify_unifications);
             }
         }
     }
@@ -670,17 +910,32 @@ fn parse_pattern(
 
     let composition = Composition::new(composition_parts);
 
-    Ok((composition, start, end))
+    Ok((composition, start, end, pattern_unifications))
 }
 
 impl Rule {
+    /// Adds a translated message. Matches can be referenced with `\1`, `\2` etc. like in the XML.
+    pub fn add_localized_message(&mut self, locale: &str, text: &str) {
+        self.localized_messages.insert(
+            locale.to_string(),
+            Synthesizer {
+                parts: parse_synthesizer_text(text),
+                conversion: Conversion::Nop,
+                use_titlecase_adjust: true,
+            },
+        );
+    }
+
     pub fn from_rule_structure(data: structure::Rule, info: &mut BuildInfo) -> Result<Rule, Error> {
+        let source_hash = structure_hash(&data);
+
         if data.filter.is_some() {
             return Err(Error::Unimplemented(
                 "rules with filter are not implemented.".into(),
             ));
         }
 
+        let unifications = &data.unifications;
         let (engine, start, end) = match (data.pattern, data.regex) {
             (Some(_), Some(_)) => Err(Error::Unexpected(
                 "must not contain both `pattern` and `regexp`.".into(),
@@ -689,7 +944,8 @@ impl Rule {
                 "either `pattern` or `regexp` must be supplied.".into(),
             )),
             (Some(pattern), None) => {
-                let (composition, start, end) = parse_pattern(pattern, info)?;
+                let (composition, start, end, pattern_unifications) =
+                    parse_pattern(pattern, unifications, info)?;
 
                 Ok((
                     Engine::Token(TokenEngine {
@@ -697,11 +953,14 @@ impl Rule {
                         antipatterns: if let Some(antipatterns) = data.antipatterns {
                             antipatterns
                                 .into_iter()
-                                .map(|pattern| parse_pattern(pattern, info).map(|x| x.0))
+                                .map(|pattern| {
+                                    parse_pattern(pattern, unifications, info).map(|x| x.0)
+                                })
                                 .collect::<Result<Vec<_>, Error>>()?
                         } else {
                             Vec::new()
                         },
+                        unifications: pattern_unifications,
                     }),
                     start,
                     end,
@@ -714,7 +973,7 @@ impl Rule {
                     x => panic!("unknown case_sensitive value {:?}", x),
                 };
                 let mark = regex.mark.map_or(0, |x| x.parse().unwrap());
-                let regex = SerializeRegex::new(&regex.text, false, case_sensitive)?;
+                let regex = info.regex(&regex.text, false, case_sensitive)?;
                 let id_to_idx: DefaultHashMap<usize, usize> =
                     (0..regex.captures_len() + 1).enumerate().collect();
                 Ok((Engine::Text(regex, id_to_idx), mark, mark + 1))
@@ -735,13 +994,26 @@ impl Rule {
                 structure::MessagePart::Suggestion(suggestion) => {
                     let suggester = parse_suggestion(suggestion.clone(), &maybe_composition, info)?;
                     // simpler to just parse a second time than cloning the result
-                    message_parts
-                        .extend(parse_suggestion(suggestion, &maybe_composition, info)?.parts);
+                    let inline = parse_suggestion(suggestion, &maybe_composition, info)?;
+                    // the conversion of the whole suggestion also applies to it in the message
+                    if inline.conversion.is_some() {
+                        message_parts
+                            .push(SynthesizerPart::Converted(inline.conversion, inline.parts));
+                    } else {
+                        message_parts.extend(inline.parts);
+                    }
                     suggesters.push(suggester);
                 }
                 structure::MessagePart::Text(text) => {
                     message_parts.extend(parse_synthesizer_text(text.as_str()));
                 }
+                structure::MessagePart::Marker(marker) => {
+                    message_parts.push(SynthesizerPart::Marker(parse_suggestion_parts(
+                        marker.parts,
+                        &maybe_composition,
+                        info,
+                    )?));
+                }
                 structure::MessagePart::Match(m) => {
                     message_parts.push(SynthesizerPart::Match(parse_match(
                         m,
@@ -813,9 +1085,11 @@ impl Rule {
                             suggestion = Some(Suggestion {
                                 source: "_Test".to_string(),
                                 message: "_Test".to_string(),
+                                short_message: None,
                                 start: char_length,
                                 end: char_length + length,
                                 replacements,
+                                ..Default::default()
                             });
                         }
 
@@ -830,6 +1104,13 @@ impl Rule {
             });
         }
 
+        // a rule is safe if it has one unambiguous correction, can be overridden in the options
+        let safe = suggesters.len() == 1
+            && examples.iter().all(|example| match &example.suggestion {
+                Some(suggestion) => suggestion.replacements.len() == 1,
+                None => true,
+            });
+
         Ok(Rule {
             engine,
             examples,
@@ -838,17 +1119,26 @@ impl Rule {
             suggesters,
             message: Synthesizer {
                 parts: message_parts,
+                conversion: Conversion::Nop,
                 use_titlecase_adjust: true,
             },
+            localized_messages: DefaultHashMap::default(),
             url: data.url.map(|x| x.to_string()),
             short: data.short.map(|x| x.to_string()),
             // attributes below need information from rule group / category, so are set later
             id: String::new(),
             name: String::new(),
             on: true,
+            default_on: true,
+            temp_off: false,
+            priority: 0,
             category_id: String::new(),
             category_name: String::new(),
             category_type: None,
+            source_location: None,
+            safe,
+            source_hash,
+            stats: Default::default(),
         })
     }
 }
@@ -902,11 +1192,7 @@ impl owned::WordData {
 fn parse_pos_filter(postag: &str, postag_regexp: Option<&str>, info: &mut BuildInfo) -> POSFilter {
     match postag_regexp.as_deref() {
         Some("yes") => POSFilter::new(PosMatcher::new(
-            Matcher::new_regex(
-                SerializeRegex::new(&postag, true, true).unwrap(),
-                false,
-                true,
-            ),
+            Matcher::new_regex(info.regex(postag, true, true).unwrap(), false, true),
             info,
         )),
         Some(_) | None => POSFilter::new(PosMatcher::new(
@@ -916,11 +1202,58 @@ fn parse_pos_filter(postag: &str, postag_regexp: Option<&str>, info: &mut BuildI
     }
 }
 
+/// Parses the filters of the types of a unification feature, e. g. one filter for singular and one for plural.
+fn parse_feature(
+    feature: &structure::Feature,
+    unifications: &Option<Vec<structure::Unification>>,
+    info: &mut BuildInfo,
+) -> Result<Vec<POSFilter>, Error> {
+    let unification = unifications
+        .iter()
+        .flatten()
+        .find(|x| x.feature == feature.id)
+        .ok_or_else(|| Error::Unexpected(format!("unknown unification feature: {}", feature.id)))?;
+
+    // a feature can refer to some of its types, e. g. `<feature id="number"><type id="sg"/></feature>`
+    let in_types = |equiv: &&structure::Equivalence| match &feature.types {
+        Some(types) => types.iter().any(|x| x.id == equiv.kind),
+        None => true,
+    };
+
+    if let Some(types) = &feature.types {
+        if let Some(unknown) = types
+            .iter()
+            .find(|x| !unification.equivalences.iter().any(|y| y.kind == x.id))
+        {
+            return Err(Error::Unexpected(format!(
+                "unknown type {} of unification feature {}",
+                unknown.id, feature.id
+            )));
+        }
+    }
+
+    Ok(unification
+        .equivalences
+        .iter()
+        .filter(in_types)
+        .map(|equiv| {
+            parse_pos_filter(
+                &equiv.token.postag,
+                equiv.token.postag_regexp.as_deref(),
+                info,
+            )
+        })
+        .collect())
+}
+
+/// Filters for each feature, the POS filter for each token and whether each token takes part in the unification.
+type UnifyParts = (Vec<Vec<POSFilter>>, Vec<Option<POSFilter>>, Vec<bool>);
+
 fn parse_unify(
     unify: &structure::Unify,
     unifications: &Option<Vec<structure::Unification>>,
     info: &mut BuildInfo,
-) -> (Vec<Vec<POSFilter>>, Vec<Option<POSFilter>>, Vec<bool>) {
+) -> Result<UnifyParts, Error> {
     let mut filters = Vec::new();
     let mut disambig = Vec::new();
     let mut mask = Vec::new();
@@ -928,27 +1261,7 @@ fn parse_unify(
     for token_combination in &unify.tokens {
         match token_combination {
             structure::UnifyTokenCombination::Feature(feature) => {
-                // TODO: no unwrap
-                let unification = unifications
-                    .as_ref()
-                    .unwrap()
-                    .iter()
-                    .find(|x| x.feature == feature.id)
-                    .unwrap();
-
-                filters.push(
-                    unification
-                        .equivalences
-                        .iter()
-                        .map(|equiv| {
-                            parse_pos_filter(
-                                &equiv.token.postag,
-                                equiv.token.postag_regexp.as_deref(),
-                                info,
-                            )
-                        })
-                        .collect(),
-                );
+                filters.push(parse_feature(feature, unifications, info)?);
             }
             structure::UnifyTokenCombination::And(tokens)
             | structure::UnifyTokenCombination::Or(tokens) => {
@@ -995,7 +1308,7 @@ fn parse_unify(
         }
     }
 
-    (filters, disambig, mask)
+    Ok((filters, disambig, mask))
 }
 
 impl DisambiguationRule {
@@ -1004,12 +1317,14 @@ impl DisambiguationRule {
         info: &mut BuildInfo,
     ) -> Result<DisambiguationRule, Error> {
         // might need the pattern later so clone it here
-        let (composition, start, end) = parse_pattern(data.pattern.clone(), info)?;
+        // agreement is only checked by the `unify` action of disambiguation rules, not when matching the pattern
+        let unifications = &data.unifications;
+        let (composition, start, end, _) = parse_pattern(data.pattern.clone(), unifications, info)?;
 
         let antipatterns = if let Some(antipatterns) = data.antipatterns {
             antipatterns
                 .into_iter()
-                .map(|pattern| parse_pattern(pattern, info).map(|x| x.0))
+                .map(|pattern| parse_pattern(pattern, unifications, info).map(|x| x.0))
                 .collect::<Result<Vec<_>, Error>>()?
         } else {
             Vec::new()
@@ -1153,7 +1468,7 @@ impl DisambiguationRule {
                 match &data.pattern.parts[..] {
                     [.., structure::PatternPart::Marker(marker)] => match &marker.tokens[..] {
                         [structure::TokenCombination::Unify(unify)] => {
-                            let (f, d, m) = parse_unify(&unify, &data.unifications, info);
+                            let (f, d, m) = parse_unify(unify, &data.unifications, info)?;
                             filters.extend(f);
                             disambig.extend(d);
                             mask.extend(m);
@@ -1161,7 +1476,7 @@ impl DisambiguationRule {
                         _ => panic!("only `unify` as only element in `marker` is implemented"),
                     },
                     [structure::PatternPart::Unify(unify)] => {
-                        let (f, d, m) = parse_unify(&unify, &data.unifications, info);
+                        let (f, d, m) = parse_unify(unify, &data.unifications, info)?;
                         filters.extend(f);
                         disambig.extend(d);
                         mask.extend(m);
@@ -1273,13 +1588,16 @@ impl DisambiguationRule {
             engine: Engine::Token(TokenEngine {
                 composition,
                 antipatterns,
+                unifications: Vec::new(),
             }),
             filter,
             disambiguations,
             start,
             end,
             examples,
+            // set later since it needs the path of the XML
             id: String::new(),
+            source_location: None,
         })
     }
 }
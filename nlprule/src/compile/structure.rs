@@ -1,39 +1,63 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::BufReader;
 use xml::reader::EventReader;
 
 mod preprocess {
+    use xml::common::Position;
     use xml::reader::EventReader;
     use xml::writer::EmitterConfig;
 
     use super::Category;
+    use crate::Error;
 
-    pub fn sanitize(input: impl std::io::Read, whitespace_sensitive_tags: &[&str]) -> String {
+    pub fn sanitize(
+        input: impl std::io::Read,
+        whitespace_sensitive_tags: &[&str],
+    ) -> Result<String, Error> {
         let mut sanitized = Vec::new();
 
         let mut writer = EmitterConfig::new()
             .perform_indent(true)
             .create_writer(&mut sanitized);
 
-        let parser = EventReader::new(input);
+        let mut parser = EventReader::new(input);
+        let mut events = Vec::new();
 
-        let events = parser
-            .into_iter()
-            .map(|x| x.expect("error reading XML"))
-            .filter(|x| {
+        loop {
+            let event = parser
+                .next()
+                .map_err(|x| Error::InvalidInput(format!("error reading XML: {}", x)))?;
+            // the line (1-based) the event starts on in the input
+            let line = (parser.position().row + 1).to_string();
+
+            match event {
+                xml::reader::XmlEvent::EndDocument => break,
                 // processing instructions break the writer and are useless to us
-                !matches!(x, xml::reader::XmlEvent::ProcessingInstruction { .. })
-            })
-            .collect::<Vec<_>>();
+                xml::reader::XmlEvent::ProcessingInstruction { .. } => {}
+                event => events.push((event, line)),
+            }
+        }
 
         let mut out_events: Vec<xml::writer::XmlEvent> = Vec::new();
         let mut parents = Vec::new();
 
-        for event in &events {
+        for (event, line) in &events {
             match event {
-                xml::reader::XmlEvent::StartElement { name, .. } => {
+                xml::reader::XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
                     parents.push(name.local_name.as_str());
+
+                    // keep the location of rules in the source, see `Rule::line`
+                    if name.local_name == "rule" {
+                        let mut element = xml::writer::XmlEvent::start_element(name.borrow());
+                        for attribute in attributes {
+                            element = element.attr(attribute.name.borrow(), &attribute.value);
+                        }
+                        out_events.push(element.attr("__line", line).into());
+                        continue;
+                    }
                 }
                 xml::reader::XmlEvent::EndElement { .. } => {
                     parents.pop();
@@ -70,22 +94,24 @@ mod preprocess {
         }
 
         for event in out_events {
-            writer.write(event).expect("error writing to output XML");
+            writer
+                .write(event)
+                .map_err(|x| Error::Unexpected(format!("error writing XML: {}", x)))?;
         }
 
-        std::str::from_utf8(&sanitized)
-            .expect("invalid UTF-8")
-            .to_string()
+        String::from_utf8(sanitized).map_err(|x| Error::Unexpected(x.to_string()))
     }
 
-    pub fn extract_rules(mut xml: impl std::io::Read) -> Vec<(String, Option<Category>)> {
+    pub fn extract_rules(
+        mut xml: impl std::io::Read,
+    ) -> Result<Vec<(String, Option<Category>)>, Error> {
         let mut string = String::new();
-        xml.read_to_string(&mut string)
-            .expect("error writing to string.");
+        xml.read_to_string(&mut string)?;
 
-        let document = roxmltree::Document::parse(&string).expect("error parsing XML");
+        let document = roxmltree::Document::parse(&string)
+            .map_err(|x| Error::InvalidInput(format!("error parsing XML: {}", x)))?;
 
-        document
+        Ok(document
             .descendants()
             .filter(|x| {
                 let name = x.tag_name().name();
@@ -109,6 +135,7 @@ mod preprocess {
                         name: parent.attribute("name").unwrap().to_owned(),
                         kind: parent.attribute("type").map(|x| x.to_owned()),
                         default: parent.attribute("default").map(|x| x.to_owned()),
+                        priority: parent.attribute("priority").map(|x| x.to_owned()),
                     })
                 } else {
                     None
@@ -116,7 +143,7 @@ mod preprocess {
 
                 (xml, category)
             })
-            .collect()
+            .collect())
     }
 }
 
@@ -125,6 +152,9 @@ pub struct Group {
     pub id: String,
     pub name: String,
     pub default: Option<String>,
+    pub priority: Option<String>,
+    pub short: Option<String>,
+    pub url: Option<String>,
     pub n: usize,
 }
 
@@ -134,9 +164,10 @@ pub struct Category {
     pub name: String,
     pub kind: Option<String>,
     pub default: Option<String>,
+    pub priority: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct XMLString {
     pub text: String,
 }
@@ -155,7 +186,7 @@ impl std::convert::Into<String> for XMLString {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct XMLText {
     pub text: XMLString,
 }
@@ -174,7 +205,7 @@ impl std::convert::Into<String> for XMLText {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Match {
     pub no: String,
@@ -189,49 +220,59 @@ pub struct Match {
     pub regexp_replace: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SuggestionMarker {
+    #[serde(rename = "$value")]
+    pub parts: Vec<SuggestionPart>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "lowercase")]
 pub enum SuggestionPart {
     Match(Match),
+    Marker(SuggestionMarker),
     Text(XMLString),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Suggestion {
+    pub case_conversion: Option<String>,
     #[serde(rename = "$value")]
     pub parts: Vec<SuggestionPart>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "lowercase")]
 pub enum MessagePart {
     Suggestion(Suggestion),
+    Marker(SuggestionMarker),
     Text(XMLString),
     Match(Match),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Message {
     #[serde(rename = "$value")]
     pub parts: Vec<MessagePart>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct ExampleMarker {
     pub text: XMLString,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "lowercase")]
 pub enum ExamplePart {
     Marker(ExampleMarker),
     Text(XMLString),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Example {
     pub correction: Option<String>,
@@ -241,7 +282,7 @@ pub struct Example {
     pub kind: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Exception {
     pub case_sensitive: Option<String>,
@@ -258,7 +299,7 @@ pub struct Exception {
     pub text: Option<XMLString>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(deny_unknown_fields)]
 #[allow(clippy::large_enum_variant)]
@@ -269,13 +310,13 @@ pub enum TokenPart {
     Sub(Sub),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Sub {
     pub no: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Token {
     pub min: Option<String>,
@@ -358,27 +399,36 @@ macro_rules! impl_match_attributes {
 impl_match_attributes!(&Exception);
 impl_match_attributes!(&Token);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct TokenVector {
     #[serde(rename = "token")]
     pub tokens: Vec<Token>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Feature {
     pub id: String,
+    /// Restricts the feature to some of its equivalence types, all types are used if this is `None`.
+    #[serde(rename = "type")]
+    pub types: Option<Vec<FeatureType>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeatureType {
+    pub id: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Ignore {
     #[serde(rename = "$value")]
     pub tokens: Vec<TokenCombination>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(deny_unknown_fields)]
 pub enum UnifyTokenCombination {
@@ -390,14 +440,14 @@ pub enum UnifyTokenCombination {
     Ignore(Ignore),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Unify {
     #[serde(rename = "$value")]
     pub tokens: Vec<UnifyTokenCombination>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(deny_unknown_fields)]
 pub enum TokenCombination {
@@ -407,14 +457,14 @@ pub enum TokenCombination {
     Unify(Unify),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct PatternMarker {
     #[serde(rename = "$value")]
     pub tokens: Vec<TokenCombination>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(deny_unknown_fields)]
 pub enum PatternPart {
@@ -425,7 +475,7 @@ pub enum PatternPart {
     Unify(Unify),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Pattern {
     pub case_sensitive: Option<String>,
@@ -433,7 +483,7 @@ pub struct Pattern {
     pub parts: Vec<PatternPart>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Regex {
     pub text: XMLString,
@@ -441,7 +491,7 @@ pub struct Regex {
     pub mark: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Rule {
     pub pattern: Option<Pattern>,
@@ -459,16 +509,23 @@ pub struct Rule {
     pub short: Option<XMLText>,
     pub url: Option<XMLText>,
     pub default: Option<String>,
+    pub priority: Option<String>,
     pub filter: Option<Filter>,
+    #[serde(rename = "__unused_unifications")]
+    pub unifications: Option<Vec<Unification>>,
+    /// The line the rule starts on in the XML file, added during preprocessing.
+    #[serde(rename = "__line")]
+    pub line: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct RuleGroup {
     pub id: String,
     #[serde(rename = "antipattern")]
     pub antipatterns: Option<Vec<Pattern>>,
     pub default: Option<String>,
+    pub priority: Option<String>,
     pub name: String,
     pub short: Option<XMLText>,
     pub url: Option<XMLText>,
@@ -476,15 +533,16 @@ pub struct RuleGroup {
     pub rules: Vec<Rule>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(deny_unknown_fields)]
 pub enum RuleContainer {
     Rule(Rule),
     RuleGroup(RuleGroup),
+    Unification(Unification),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct DisambiguationExample {
     #[serde(rename = "type")]
@@ -495,7 +553,7 @@ pub struct DisambiguationExample {
     pub parts: Vec<ExamplePart>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct WordData {
     pub pos: String,
@@ -503,14 +561,14 @@ pub struct WordData {
     pub lemma: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Filter {
     pub args: String,
     pub class: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct DisambiguationMatch {
     pub no: usize,
@@ -518,7 +576,7 @@ pub struct DisambiguationMatch {
     pub postag_regexp: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub enum DisambiguationPart {
     #[serde(rename = "wd")]
@@ -527,7 +585,7 @@ pub enum DisambiguationPart {
     Match(DisambiguationMatch),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Disambiguation {
     pub postag: Option<String>,
@@ -536,7 +594,7 @@ pub struct Disambiguation {
     pub word_datas: Option<Vec<DisambiguationPart>>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct DisambiguationRule {
     pub pattern: Pattern,
@@ -553,9 +611,12 @@ pub struct DisambiguationRule {
     #[serde(rename = "__unused_unifications")]
     pub unifications: Option<Vec<Unification>>,
     pub default: Option<String>,
+    /// The line the rule starts on in the XML file, added during preprocessing.
+    #[serde(rename = "__line")]
+    pub line: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct DisambiguationRuleGroup {
     pub id: String,
@@ -567,14 +628,14 @@ pub struct DisambiguationRuleGroup {
     pub default: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct EquivalenceToken {
     pub postag: String,
     pub postag_regexp: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Equivalence {
     pub token: EquivalenceToken,
@@ -582,7 +643,7 @@ pub struct Equivalence {
     pub kind: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Unification {
     #[serde(rename = "equivalence")]
@@ -590,7 +651,7 @@ pub struct Unification {
     pub feature: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[serde(deny_unknown_fields)]
 pub enum DisambiguationRuleContainer {
@@ -610,6 +671,9 @@ macro_rules! flatten_group {
         let group = Group {
             id: $rulegroup.id,
             default: $rulegroup.default,
+            priority: None,
+            short: None,
+            url: None,
             name: $rulegroup.name,
             n: 0,
         };
@@ -636,16 +700,20 @@ macro_rules! flatten_group {
 type GrammarRuleReading = (Rule, Option<Group>, Option<Category>);
 type DisambiguationRuleReading = (DisambiguationRule, Option<Group>, Option<Category>);
 
+/// Reads the grammar rules from an XML file. Fails if the file can not be read or is not valid XML,
+/// errors deserializing single rules are returned for each rule.
 pub fn read_rules<P: AsRef<std::path::Path>>(
     path: P,
-) -> Vec<Result<GrammarRuleReading, serde_xml_rs::Error>> {
-    let file = File::open(path).unwrap();
+) -> Result<Vec<Result<GrammarRuleReading, serde_xml_rs::Error>>, crate::Error> {
+    let file = File::open(path)?;
     let file = BufReader::new(file);
 
-    let sanitized = preprocess::sanitize(file, &["suggestion"]);
-    let rules = preprocess::extract_rules(sanitized.as_bytes());
+    let sanitized = preprocess::sanitize(file, &["suggestion"])?;
+    let rules = preprocess::extract_rules(sanitized.as_bytes())?;
+
+    let mut unifications = Vec::new();
 
-    rules
+    let rules: Vec<_> = rules
         .into_iter()
         .map(|(xml, category)| {
             let mut out = Vec::new();
@@ -659,27 +727,57 @@ pub fn read_rules<P: AsRef<std::path::Path>>(
                     RuleContainer::Rule(rule) => {
                         vec![Ok((rule, None, category))]
                     }
-                    RuleContainer::RuleGroup(rule_group) => flatten_group!(rule_group, category)
-                        .into_iter()
-                        .map(Ok)
-                        .collect(),
+                    RuleContainer::RuleGroup(rule_group) => {
+                        // only grammar rule groups have a priority, a short message and an URL
+                        let priority = rule_group.priority.clone();
+                        let short = rule_group.short.as_ref().map(|x| x.to_string());
+                        let url = rule_group.url.as_ref().map(|x| x.to_string());
+                        flatten_group!(rule_group, category)
+                            .into_iter()
+                            .map(|(rule, group, category)| {
+                                let group = group.map(|x| Group {
+                                    priority: priority.clone(),
+                                    short: short.clone(),
+                                    url: url.clone(),
+                                    ..x
+                                });
+                                Ok((rule, group, category))
+                            })
+                            .collect()
+                    }
+                    RuleContainer::Unification(unification) => {
+                        unifications.push(unification);
+
+                        vec![]
+                    }
                 },
                 Err(err) => vec![Err(err)],
             });
             out
         })
         .flatten()
-        .collect()
+        .collect();
+
+    Ok(rules
+        .into_iter()
+        .map(|result| {
+            result.map(|mut x| {
+                x.0.unifications = Some(unifications.clone());
+                x
+            })
+        })
+        .collect())
 }
 
+/// Reads the disambiguation rules from an XML file, see [read_rules].
 pub fn read_disambiguation_rules<P: AsRef<std::path::Path>>(
     path: P,
-) -> Vec<Result<DisambiguationRuleReading, serde_xml_rs::Error>> {
-    let file = File::open(path).unwrap();
+) -> Result<Vec<Result<DisambiguationRuleReading, serde_xml_rs::Error>>, crate::Error> {
+    let file = File::open(path)?;
     let file = BufReader::new(file);
 
-    let sanitized = preprocess::sanitize(file, &[]);
-    let rules = preprocess::extract_rules(sanitized.as_bytes());
+    let sanitized = preprocess::sanitize(file, &[])?;
+    let rules = preprocess::extract_rules(sanitized.as_bytes())?;
 
     let mut unifications = Vec::new();
 
@@ -718,7 +816,7 @@ pub fn read_disambiguation_rules<P: AsRef<std::path::Path>>(
         .flatten()
         .collect();
 
-    rules
+    Ok(rules
         .into_iter()
         .map(|result| match result {
             Ok(mut x) => {
@@ -728,5 +826,5 @@ pub fn read_disambiguation_rules<P: AsRef<std::path::Path>>(
             }
             Err(x) => Err(x),
         })
-        .collect()
+        .collect())
 }
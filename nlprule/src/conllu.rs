@@ -0,0 +1,232 @@
+//! Export of analyses in the [CoNLL-U](https://universaldependencies.org/format.html) format so the
+//! tokenization, tags and lemmas of nlprule can be used by other NLP tools, and import of CoNLL-U to apply rules to it.
+//!
+//! CoNLL-U has one reading per token, so only the first reading of each token is exported.
+//! Use the serialization of [Analysis] directly to keep all readings.
+
+use crate::{
+    tokenizer::{quote_depths, upos::UposMapping},
+    types::*,
+    Error,
+};
+
+/// Tags added by the tokenizer which are not part of the tagset.
+const SPECIAL_TAGS: [&str; 4] = ["", "SENT_START", "SENT_END", "UNKNOWN"];
+
+fn field(value: &str) -> &str {
+    if value.is_empty() {
+        "_"
+    } else {
+        value
+    }
+}
+
+fn is_sentence_end(token: &owned::Token) -> bool {
+    token.word.tags.iter().any(|x| x.pos.0 == "SENT_END")
+}
+
+/// Marks the last token as the end of a sentence, if it is not the sentence start token or marked already.
+fn end_sentence(tokens: &mut [owned::Token]) {
+    if let Some(token) = tokens.last_mut() {
+        if !is_sentence_end(token) && token.char_span.0 != token.char_span.1 {
+            let text = owned::WordId(token.word.text.0.clone(), None);
+            token.word.tags.push(owned::WordData::new(
+                text,
+                owned::PosId("SENT_END".to_string(), 0),
+            ));
+        }
+    }
+}
+
+/// Formats an analysis as CoNLL-U. Each sentence has a `sent_id` and `text` comment,
+/// LEMMA and XPOS are set from the first reading which is not a special tag of the tokenizer.
+/// UPOS is set from the XPOS tag if a mapping is given. FEATS, HEAD, DEPREL and DEPS are not set.
+/// MISC contains `SpaceAfter=No` where applicable.
+pub fn write(analysis: &Analysis, upos: Option<&UposMapping>) -> String {
+    // the sentence start token is not a real token
+    let tokens: Vec<_> = analysis
+        .tokens
+        .iter()
+        .filter(|x| x.char_span.0 != x.char_span.1)
+        .collect();
+
+    let mut output = String::new();
+    let mut sentence_start = 0;
+    let mut sentence_id = 1;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if i == sentence_start {
+            let end = tokens[i..]
+                .iter()
+                .position(|x| is_sentence_end(x))
+                .map_or(tokens.len() - 1, |x| i + x);
+
+            output.push_str(&format!(
+                "# sent_id = {}\n# text = {}\n",
+                sentence_id,
+                &analysis.text[token.byte_span.0..tokens[end].byte_span.1]
+            ));
+            sentence_id += 1;
+        }
+
+        let reading = token
+            .word
+            .tags
+            .iter()
+            .find(|x| !SPECIAL_TAGS.contains(&x.pos.0.as_str()));
+        let upos = reading
+            .and_then(|x| upos.and_then(|upos| upos.upos(x.pos.as_ref())))
+            .map_or("_", |x| x.as_str());
+        let space_after = match tokens.get(i + 1) {
+            Some(next) => next.has_space_before,
+            None => true,
+        };
+
+        output.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t_\t_\t_\t_\t{}\n",
+            i - sentence_start + 1,
+            token.word.text.0,
+            field(reading.map_or("", |x| x.lemma.0.as_str())),
+            upos,
+            field(reading.map_or("", |x| x.pos.0.as_str())),
+            if space_after { "_" } else { "SpaceAfter=No" }
+        ));
+
+        if is_sentence_end(token) || i + 1 == tokens.len() {
+            output.push('\n');
+            sentence_start = i + 1;
+        }
+    }
+
+    output
+}
+
+/// Reads CoNLL-U into an analysis which can be checked with [Rules::apply_tokens][crate::Rules::apply_tokens].
+/// The text is reconstructed from the word forms, separated by a space unless MISC contains `SpaceAfter=No`.
+/// Multiword tokens and empty nodes are skipped. LEMMA and XPOS are used as the only reading of each token,
+/// so the XPOS tags must be tags of the tokenizer the analysis is checked with.
+///
+/// # Errors
+/// If a token line does not have ten fields.
+pub fn read(input: &str) -> Result<Analysis, Error> {
+    let mut text = String::new();
+    let mut char_len = 0;
+    let mut tokens = vec![owned::Token {
+        word: owned::Word {
+            text: owned::WordId(String::new(), None),
+            tags: vec![owned::WordData::new(
+                owned::WordId(String::new(), None),
+                owned::PosId("SENT_START".to_string(), 0),
+            )],
+        },
+        char_span: (0, 0),
+        byte_span: (0, 0),
+        has_space_before: false,
+        chunks: Vec::new(),
+        quote_depth: 0,
+    }];
+    let mut space_after = false;
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+
+        if line.trim().is_empty() {
+            end_sentence(&mut tokens);
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<_> = line.split('\t').collect();
+        if fields.len() != 10 {
+            return Err(Error::InvalidInput(format!(
+                "CoNLL-U line {} must have 10 fields, found {}",
+                i + 1,
+                fields.len()
+            )));
+        }
+        if fields[0].contains(&['-', '.'][..]) {
+            continue;
+        }
+
+        let form = fields[1];
+        let has_space_before = space_after;
+        if has_space_before {
+            text.push(' ');
+            char_len += 1;
+        }
+
+        let byte_span = (text.len(), text.len() + form.len());
+        let char_span = (char_len, char_len + form.chars().count());
+        text.push_str(form);
+        char_len = char_span.1;
+        space_after = !fields[9].split('|').any(|x| x == "SpaceAfter=No");
+
+        let word_id = || owned::WordId(form.to_string(), None);
+        let mut tags = Vec::new();
+        if fields[4] != "_" {
+            let lemma = if fields[2] == "_" { form } else { fields[2] };
+            tags.push(owned::WordData::new(
+                owned::WordId(lemma.to_string(), None),
+                owned::PosId(fields[4].to_string(), 0),
+            ));
+        }
+        tags.push(owned::WordData::new(
+            word_id(),
+            owned::PosId(String::new(), 0),
+        ));
+        if tags.len() == 1 {
+            tags.push(owned::WordData::new(
+                word_id(),
+                owned::PosId("UNKNOWN".to_string(), 0),
+            ));
+        }
+
+        tokens.push(owned::Token {
+            word: owned::Word {
+                text: word_id(),
+                tags,
+            },
+            char_span,
+            byte_span,
+            has_space_before,
+            chunks: Vec::new(),
+            quote_depth: 0,
+        });
+    }
+
+    end_sentence(&mut tokens);
+
+    let words: Vec<_> = tokens[1..].iter().map(|x| x.word.text.0.as_str()).collect();
+    let depths = quote_depths(&words);
+    for (token, depth) in tokens[1..].iter_mut().zip(depths) {
+        token.quote_depth = depth;
+    }
+
+    Ok(Analysis { text, tokens })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips() {
+        let input = "# sent_id = 1\n# text = Hi, you.\n\
+                     1\tHi\thi\t_\tUH\t_\t_\t_\t_\tSpaceAfter=No\n\
+                     2\t,\t_\t_\t_\t_\t_\t_\t_\t_\n\
+                     3\tyou\tyou\t_\tPRP\t_\t_\t_\t_\tSpaceAfter=No\n\
+                     4\t.\t.\t_\t.\t_\t_\t_\t_\t_\n\n\
+                     # sent_id = 2\n# text = Bye\n\
+                     1\tBye\tbye\t_\tUH\t_\t_\t_\t_\t_\n\n";
+
+        let analysis = read(input).unwrap();
+        assert_eq!(analysis.text, "Hi, you. Bye");
+        assert_eq!(analysis.tokens[5].char_span, (9, 12));
+        assert!(analysis.tokens[5].has_space_before);
+
+        assert_eq!(write(&analysis, None), input);
+        assert!(write(&analysis, Some(&UposMapping::penn())).contains("1\tHi\thi\tINTJ\tUH"));
+    }
+}
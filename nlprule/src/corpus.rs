@@ -0,0 +1,277 @@
+//! Statistics over a user-provided corpus to derive artifacts for the compile step, e. g. the list of common words
+//! passed as `--common-words-path`. Common words are added to the word store of the tagger so they can be compared by ID.
+//! [CorpusMatches] counts the matches of rules on a corpus, e. g. to find rules which match too often on clean text.
+
+use crate::{rng::Rng, types::*, Rules, Tokenizer};
+use std::io::{self, BufRead, Write};
+
+/// Tags added by the tokenizer which are not part of the tagset.
+const SPECIAL_TAGS: [&str; 4] = ["", "SENT_START", "SENT_END", "UNKNOWN"];
+
+/// Word frequencies and suffix statistics of a corpus.
+///
+/// ```no_run
+/// use nlprule::{corpus::CorpusStats, Tokenizer};
+/// use std::{fs::File, io::BufReader};
+///
+/// let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+///
+/// let mut stats = CorpusStats::new(3);
+/// stats.add_lines(BufReader::new(File::open("corpus.txt")?), &tokenizer)?;
+/// stats.write_common_words(File::create("common_words.txt")?, 10_000)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CorpusStats {
+    max_suffix_len: usize,
+    n_tokens: usize,
+    frequencies: DefaultHashMap<String, usize>,
+    suffixes: DefaultHashMap<(String, String), usize>,
+}
+
+impl CorpusStats {
+    /// Creates empty statistics. Suffixes of up to `max_suffix_len` characters are counted.
+    pub fn new(max_suffix_len: usize) -> Self {
+        CorpusStats {
+            max_suffix_len,
+            ..Default::default()
+        }
+    }
+
+    /// Adds the tokens of a text. Suffixes are only counted for words the tagger knows.
+    pub fn add(&mut self, text: &str, tokenizer: &Tokenizer) {
+        for token in tokenizer.tokenize(text) {
+            let word = token.word.text.as_ref();
+            self.n_tokens += 1;
+            *self.frequencies.entry(word.to_string()).or_insert(0) += 1;
+
+            let chars: Vec<_> = word.chars().collect();
+            for tag in token
+                .word
+                .tags
+                .iter()
+                .map(|x| x.pos.as_ref())
+                .filter(|x| !SPECIAL_TAGS.contains(x))
+            {
+                for len in 1..=self.max_suffix_len.min(chars.len().saturating_sub(1)) {
+                    let suffix: String = chars[chars.len() - len..].iter().collect();
+                    *self.suffixes.entry((suffix, tag.to_string())).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    /// Adds each line of a corpus as a separate text.
+    pub fn add_lines<R: BufRead>(&mut self, reader: R, tokenizer: &Tokenizer) -> io::Result<()> {
+        for line in reader.lines() {
+            self.add(&line?, tokenizer);
+        }
+        Ok(())
+    }
+
+    /// Adds each line of a corpus as a separate text with probability `fraction`, to derive statistics from a
+    /// sample of a large corpus. The same `rng` seed always selects the same lines.
+    pub fn add_lines_sampled<R: BufRead>(
+        &mut self,
+        reader: R,
+        tokenizer: &Tokenizer,
+        fraction: f64,
+        rng: &mut Rng,
+    ) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if rng.chance(fraction) {
+                self.add(&line, tokenizer);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the number of tokens added.
+    pub fn n_tokens(&self) -> usize {
+        self.n_tokens
+    }
+
+    /// Gets how often a word occurred in the corpus.
+    pub fn frequency(&self, word: &str) -> usize {
+        self.frequencies.get(word).copied().unwrap_or(0)
+    }
+
+    /// Gets the words with their frequency, most frequent first. Words with the same frequency are sorted alphabetically.
+    pub fn frequencies(&self) -> Vec<(&str, usize)> {
+        let mut frequencies: Vec<_> = self
+            .frequencies
+            .iter()
+            .map(|(word, count)| (word.as_str(), *count))
+            .collect();
+        frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        frequencies
+    }
+
+    /// Gets the `n` most frequent words.
+    pub fn common_words(&self, n: usize) -> Vec<&str> {
+        self.frequencies()
+            .into_iter()
+            .take(n)
+            .map(|(word, _)| word)
+            .collect()
+    }
+
+    /// Gets the suffixes with the tags of the words they occur in and the count, most frequent first.
+    pub fn suffixes(&self) -> Vec<(&str, &str, usize)> {
+        let mut suffixes: Vec<_> = self
+            .suffixes
+            .iter()
+            .map(|((suffix, tag), count)| (suffix.as_str(), tag.as_str(), *count))
+            .collect();
+        suffixes.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| (a.0, a.1).cmp(&(b.0, b.1))));
+        suffixes
+    }
+
+    /// Writes the `n` most frequent words, one per line. This is the format of the common words file of the compile step.
+    pub fn write_common_words<W: Write>(&self, mut writer: W, n: usize) -> io::Result<()> {
+        for word in self.common_words(n) {
+            writeln!(writer, "{}", word)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the word frequencies as tab-separated word and count, one per line.
+    pub fn write_frequencies<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (word, count) in self.frequencies() {
+            writeln!(writer, "{}\t{}", word, count)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the suffix statistics as tab-separated suffix, tag and count, one per line.
+    pub fn write_suffixes<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (suffix, tag, count) in self.suffixes() {
+            writeln!(writer, "{}\t{}\t{}", suffix, tag, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// How often each rule matched on a corpus. On a corpus of clean text every match is a false positive, so rules
+/// with many matches are probably too eager. With the `compile` feature, this can be checked when compiling with a
+/// `compile::CorpusGate`.
+///
+/// ```no_run
+/// use nlprule::{corpus::CorpusMatches, Rules, Tokenizer};
+/// use std::{fs::File, io::BufReader};
+///
+/// let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+/// let rules = Rules::new("path/to/en_rules.bin")?;
+///
+/// let mut matches = CorpusMatches::new();
+/// matches.add_lines(BufReader::new(File::open("clean.txt")?), &rules, &tokenizer)?;
+/// for (id, count) in matches.exceeding(10) {
+///     println!("{} matched {} times", id, count);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CorpusMatches {
+    n_texts: usize,
+    counts: DefaultHashMap<String, usize>,
+}
+
+impl CorpusMatches {
+    /// Creates empty counts.
+    pub fn new() -> Self {
+        CorpusMatches::default()
+    }
+
+    /// Counts the suggestions of one text by their [source][Suggestion::source].
+    pub fn add_suggestions(&mut self, suggestions: &[Suggestion]) {
+        self.n_texts += 1;
+        for suggestion in suggestions {
+            *self.counts.entry(suggestion.source.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// Checks a text and counts the suggestions.
+    pub fn add(&mut self, text: &str, rules: &Rules, tokenizer: &Tokenizer) {
+        self.add_suggestions(&rules.suggest(text, tokenizer));
+    }
+
+    /// Checks each line of a corpus as a separate text.
+    pub fn add_lines<R: BufRead>(
+        &mut self,
+        reader: R,
+        rules: &Rules,
+        tokenizer: &Tokenizer,
+    ) -> io::Result<()> {
+        for line in reader.lines() {
+            self.add(&line?, rules, tokenizer);
+        }
+        Ok(())
+    }
+
+    /// Gets the number of texts added.
+    pub fn n_texts(&self) -> usize {
+        self.n_texts
+    }
+
+    /// Gets how often the rule or native check with this ID matched.
+    pub fn count(&self, id: &str) -> usize {
+        self.counts.get(id).copied().unwrap_or(0)
+    }
+
+    /// Gets the IDs of the rules which matched more than `max` times with their count, most matches first.
+    /// Rules with the same count are sorted by ID.
+    pub fn exceeding(&self, max: usize) -> Vec<(&str, usize)> {
+        let mut exceeding: Vec<_> = self
+            .counts
+            .iter()
+            .filter(|(_, count)| **count > max)
+            .map(|(id, count)| (id.as_str(), *count))
+            .collect();
+        exceeding.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        exceeding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_frequencies() {
+        let mut stats = CorpusStats::new(2);
+        stats
+            .add_lines(
+                "the cat and the dog\nthe end".as_bytes(),
+                &Tokenizer::default(),
+            )
+            .unwrap();
+
+        assert_eq!(stats.n_tokens(), 7);
+        assert_eq!(stats.frequency("the"), 3);
+        assert_eq!(stats.common_words(3), vec!["the", "and", "cat"]);
+
+        let mut output = Vec::new();
+        stats.write_common_words(&mut output, 2).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "the\nand\n");
+    }
+
+    #[test]
+    fn counts_matches() {
+        let suggestion = |source: &str| Suggestion {
+            source: source.to_string(),
+            ..Default::default()
+        };
+
+        let mut matches = CorpusMatches::new();
+        matches.add_suggestions(&[suggestion("B"), suggestion("A"), suggestion("B")]);
+        matches.add_suggestions(&[]);
+        matches.add_suggestions(&[suggestion("A"), suggestion("C")]);
+
+        assert_eq!(matches.n_texts(), 3);
+        assert_eq!(matches.count("A"), 2);
+        assert_eq!(matches.count("D"), 0);
+        assert_eq!(matches.exceeding(1), vec![("A", 2), ("B", 2)]);
+        assert!(matches.exceeding(2).is_empty());
+    }
+}
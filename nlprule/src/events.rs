@@ -0,0 +1,117 @@
+//! Structured events emitted while applying and testing rules.
+//!
+//! By default events are written to the [log] facade. Register a callback with [set_event_callback]
+//! to handle them directly instead of parsing log lines.
+
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, RwLock,
+};
+
+/// Something that happened while applying or testing rules.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleEvent<'a> {
+    /// A disambiguation rule changed the tokens.
+    DisambiguationApplied {
+        /// The ID of the rule.
+        rule_id: &'a str,
+    },
+    /// A grammar rule produced a suggestion which was kept after resolving overlaps.
+    RuleApplied {
+        /// The ID of the rule.
+        rule_id: &'a str,
+        /// The start character index of the suggestion.
+        start: usize,
+        /// The end character index of the suggestion.
+        end: usize,
+    },
+    /// A suggestion or message of a grammar rule failed to synthesize.
+    SynthesisFailed {
+        /// The ID of the rule.
+        rule_id: &'a str,
+    },
+    /// Matching a grammar rule was stopped because it exceeded [Limits::max_match_steps][crate::rules::Limits::max_match_steps].
+    StepLimitExceeded {
+        /// The ID of the rule.
+        rule_id: &'a str,
+        /// At how many positions matching was stopped.
+        count: usize,
+    },
+    /// A test of a rule failed.
+    TestFailed {
+        /// The ID of the rule.
+        rule_id: &'a str,
+        /// Whether the failure is tolerated e. g. because it is listed as known failure in the tokenizer options.
+        /// Tolerated failures are logged as warnings, other failures as errors.
+        known: bool,
+        /// A human-readable description of the failure.
+        message: String,
+    },
+}
+
+type EventCallback = dyn Fn(&RuleEvent) + Send + Sync;
+
+// checked before taking the lock so the default handler does not contend on it
+static HAS_CALLBACK: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref CALLBACK: RwLock<Option<Arc<EventCallback>>> = RwLock::new(None);
+}
+
+/// Sets the callback which receives all events, replacing the default [log_event].
+pub fn set_event_callback<F>(callback: F)
+where
+    F: Fn(&RuleEvent) + Send + Sync + 'static,
+{
+    *CALLBACK.write().unwrap() = Some(Arc::new(callback));
+    HAS_CALLBACK.store(true, Ordering::Release);
+}
+
+/// Removes the callback, events are passed to [log_event] again.
+pub fn clear_event_callback() {
+    HAS_CALLBACK.store(false, Ordering::Release);
+    *CALLBACK.write().unwrap() = None;
+}
+
+/// The default handler of events. Writes the events to the [log] facade.
+pub fn log_event(event: &RuleEvent) {
+    match event {
+        RuleEvent::DisambiguationApplied { rule_id } => info!("applying {}", rule_id),
+        RuleEvent::RuleApplied {
+            rule_id,
+            start,
+            end,
+        } => {
+            info!("{} matched at {}..{}", rule_id, start, end)
+        }
+        RuleEvent::SynthesisFailed { rule_id } => {
+            info!("{}: failed to synthesize suggestion", rule_id)
+        }
+        RuleEvent::StepLimitExceeded { rule_id, count } => {
+            warn!("{}: step limit exceeded at {} positions", rule_id, count)
+        }
+        RuleEvent::TestFailed { message, known, .. } => {
+            if *known {
+                warn!("{}", message)
+            } else {
+                error!("{}", message)
+            }
+        }
+    }
+}
+
+pub(crate) fn emit(event: RuleEvent) {
+    if !HAS_CALLBACK.load(Ordering::Acquire) {
+        log_event(&event);
+        return;
+    }
+
+    let callback = CALLBACK.read().unwrap().clone();
+
+    match callback {
+        Some(callback) => callback(&event),
+        None => log_event(&event),
+    }
+}
@@ -14,6 +14,13 @@ pub enum Filter {
 #[enum_dispatch(Filter)]
 pub trait Filterable {
     fn keep(&self, graph: &MatchGraph, tokenizer: &Tokenizer) -> bool;
+
+    /// The name of this filter as used in the LanguageTool XML.
+    fn name(&self) -> &'static str;
+
+    /// A human-readable reason why this filter does not keep the match.
+    /// Only meaningful if [keep][Filterable::keep] returns `false`.
+    fn reason(&self, graph: &MatchGraph, tokenizer: &Tokenizer) -> String;
 }
 
 trait FromArgs: Sized {
@@ -68,6 +75,42 @@ impl Filterable for NoDisambiguationEnglishPartialPosTagFilter {
             true
         }
     }
+
+    fn name(&self) -> &'static str {
+        "NoDisambiguationEnglishPartialPosTagFilter"
+    }
+
+    fn reason(&self, graph: &MatchGraph, tokenizer: &Tokenizer) -> String {
+        let tokens = graph
+            .by_id(self.index)
+            .map_or_else(Vec::new, |group| group.tokens(graph.tokens()));
+
+        let failing =
+            tokens.iter().find(
+                |token| match self.regexp.captures(token.word.text.as_ref()) {
+                    Some(captures) => !tokenizer
+                        .tagger()
+                        .get_tags(
+                            captures.at(2).unwrap(),
+                            tokenizer.options().always_add_lower_tags,
+                            tokenizer.options().use_compound_split_heuristic,
+                        )
+                        .iter()
+                        .any(|x| self.postag_regexp.is_match(x.pos.as_ref())),
+                    None => true,
+                },
+            );
+
+        match failing {
+            Some(token) => format!(
+                "no tag of the part of \"{}\" matching `{}` matches `{}`",
+                token.word.text.as_ref(),
+                self.regexp.as_str(),
+                self.postag_regexp.as_str()
+            ),
+            None => "all tokens matched".to_string(),
+        }
+    }
 }
 
 #[allow(dead_code)]
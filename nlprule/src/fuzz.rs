@@ -0,0 +1,103 @@
+//! Entry points for fuzzing. Each target panics if an invariant of the crate is violated,
+//! so any panic found by a fuzzer is a bug. Not part of the stable API.
+
+use crate::{rules::apply_suggestions, tokenizer::finalize, types::*, Rules, Tokenizer};
+
+/// Asserts that tokens are within the bounds of the text and on char boundaries.
+pub fn check_tokens(text: &str, tokens: &[Token]) {
+    let converter = SpanConverter::new(text);
+
+    for token in tokens {
+        assert!(
+            token.char_span.0 <= token.char_span.1 && token.char_span.1 <= converter.char_len()
+        );
+        assert_eq!(
+            converter.char_span(token.byte_span),
+            Some(token.char_span),
+            "byte span and char span of token {:?} must agree",
+            token.word.text
+        );
+    }
+}
+
+/// Asserts that suggestions are within the bounds of the text, sorted and do not overlap.
+pub fn check_suggestions(text: &str, suggestions: &[Suggestion]) {
+    let char_len = text.chars().count();
+    let mut prev_end = 0;
+    let mut prev_insertion = None;
+
+    for suggestion in suggestions {
+        assert!(
+            suggestion.start <= suggestion.end && suggestion.end <= char_len,
+            "suggestion {:?} must be within the text",
+            suggestion
+        );
+        assert!(
+            suggestion.start >= prev_end,
+            "suggestion {:?} must not overlap the previous suggestion",
+            suggestion
+        );
+        assert!(
+            suggestion.start != suggestion.end || prev_insertion != Some(suggestion.start),
+            "suggestion {:?} must not insert at the same position as the previous suggestion",
+            suggestion
+        );
+        prev_end = suggestion.end;
+        prev_insertion = if suggestion.start == suggestion.end {
+            Some(suggestion.start)
+        } else {
+            None
+        };
+    }
+}
+
+/// Tokenizes and disambiguates arbitrary bytes interpreted as UTF-8.
+pub fn tokenize(tokenizer: &Tokenizer, data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(text)));
+        check_tokens(text, &tokens);
+    }
+}
+
+/// Computes suggestions for arbitrary bytes interpreted as UTF-8.
+pub fn suggest(rules: &Rules, tokenizer: &Tokenizer, data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        check_suggestions(text, &rules.suggest(text, tokenizer));
+    }
+}
+
+/// Corrects arbitrary bytes interpreted as UTF-8 with the returned suggestions.
+pub fn correct(rules: &Rules, tokenizer: &Tokenizer, data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let suggestions = rules.suggest(text, tokenizer);
+        check_suggestions(text, &suggestions);
+        apply_suggestions(text, &suggestions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{
+        Brackets, Dates, NativeChecks, OcrArtifacts, PhraseDictionary, SmartPunctuation, Typography,
+    };
+    use quickcheck_macros::quickcheck;
+
+    #[quickcheck]
+    fn native_checks_keep_invariants(text: String) -> bool {
+        let checks = NativeChecks {
+            homoglyphs: true,
+            brackets: Some(Brackets::default()),
+            typography: Some(Typography::default()),
+            smart_punctuation: Some(SmartPunctuation::default()),
+            dates: Some(Dates::default()),
+            ocr_artifacts: Some(OcrArtifacts::default()),
+            phrases: Some(PhraseDictionary::default()),
+        };
+
+        for check in checks.enabled() {
+            check_suggestions(&text, &check.check(&text));
+        }
+        true
+    }
+}
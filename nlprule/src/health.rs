@@ -0,0 +1,168 @@
+//! Self-checks of a tokenizer and rules binary pair, e. g. in the release pipeline of a language pack.
+//! Problems which make a binary unusable (it can not be read, was built with an incompatible version or contains
+//! a regex which does not compile) are errors when loading it; [check] finds problems of binaries which load.
+
+use crate::{rules::TestSelection, types::*, Error, Rules, Tokenizer};
+use std::path::Path;
+
+/// The outcome of [check]. Each field lists the IDs of the offending rules, sorted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealthReport {
+    /// Disambiguation rules whose examples fail.
+    pub failed_disambiguation_tests: Vec<String>,
+    /// Grammar rules whose examples fail.
+    pub failed_rule_tests: Vec<String>,
+    /// Grammar rules whose suggestions or messages failed to synthesize, see
+    /// [Rule::synthesis_failures][crate::rule::Rule::synthesis_failures].
+    pub synthesis_failures: Vec<String>,
+    /// Grammar rules which refer to groups which do not exist.
+    pub invalid_group_references: Vec<String>,
+    /// Grammar rules which are on but can not match because their POS tags are not in the tagset of the tokenizer,
+    /// see [Rules::check_tagset].
+    pub unmatchable_rules: Vec<String>,
+    /// Grammar rules whose examples get other suggestions with the [sentence cache][Rules::set_sentence_cache].
+    pub cache_mismatches: Vec<String>,
+    /// Human-readable problems with the [Metadata] of the binaries, e. g. different LanguageTool versions.
+    pub metadata_issues: Vec<String>,
+}
+
+impl HealthReport {
+    /// Whether no problems were found.
+    pub fn is_healthy(&self) -> bool {
+        *self == HealthReport::default()
+    }
+}
+
+/// Loads a tokenizer and rules binary and [checks][check] them.
+///
+/// # Errors
+/// If one of the binaries can not be loaded, see [Rules::new] and [Tokenizer::new].
+pub fn check_pack<P1: AsRef<Path>, P2: AsRef<Path>>(
+    tokenizer_path: P1,
+    rules_path: P2,
+) -> Result<HealthReport, Error> {
+    let tokenizer = Tokenizer::new(tokenizer_path)?;
+    let mut rules = Rules::new(rules_path)?;
+
+    Ok(check(&tokenizer, &mut rules))
+}
+
+fn metadata_issues(tokenizer: &Metadata, rules: &Metadata) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    for (name, metadata) in [("tokenizer", tokenizer), ("rules", rules)].iter() {
+        if let Err(error) = metadata.check_version() {
+            issues.push(format!("{}: {}", name, error));
+        }
+    }
+
+    if tokenizer.lt_version != rules.lt_version {
+        issues.push(format!(
+            "tokenizer was built from LanguageTool {:?}, rules from {:?}",
+            tokenizer.lt_version, rules.lt_version
+        ));
+    }
+
+    issues
+}
+
+/// Compares the suggestions for the examples of the rules with and without the sentence cache.
+/// Checks each example twice with the cache so cached suggestions are compared too.
+fn cache_mismatches(tokenizer: &Tokenizer, rules: &mut Rules) -> Vec<String> {
+    let key = |suggestions: Vec<Suggestion>| -> Vec<_> {
+        suggestions
+            .into_iter()
+            .map(|x| (x.start, x.end, x.source, x.replacements))
+            .collect()
+    };
+
+    let previous = rules.sentence_cache.take();
+    rules.set_sentence_cache(Some(1024));
+
+    let mut mismatches = Vec::new();
+    for rule in rules.rules.iter().filter(|x| x.on()) {
+        for example in rule.examples() {
+            let cache = rules.sentence_cache.take();
+            let expected = key(rules.suggest(example.text(), tokenizer));
+            rules.sentence_cache = cache;
+
+            let first = key(rules.suggest(example.text(), tokenizer));
+            let second = key(rules.suggest(example.text(), tokenizer));
+            if first != expected || second != expected {
+                mismatches.push(rule.id().to_string());
+                break;
+            }
+        }
+    }
+
+    rules.sentence_cache = previous;
+    mismatches
+}
+
+/// Runs all self-checks of a tokenizer and rules pair: the tests of the disambiguation and grammar rules, group
+/// references, the tagset, the sentence cache and the metadata. Takes a while since all examples are checked.
+/// The rules are not changed; the sentence cache is replaced while checking and restored afterwards.
+pub fn check(tokenizer: &Tokenizer, rules: &mut Rules) -> HealthReport {
+    let n_tags = tokenizer.tagger().tag_store().len();
+    let rule_ids = |predicate: &dyn Fn(&crate::rule::Rule) -> bool| -> Vec<String> {
+        rules
+            .rules()
+            .iter()
+            .filter(|x| predicate(x))
+            .map(|x| x.id().to_string())
+            .collect()
+    };
+
+    let failed_rule_tests = rules.test(tokenizer, &TestSelection::All).failed;
+    let synthesis_failures = rule_ids(&|x| x.synthesis_failures().total() > 0);
+    let invalid_group_references = rule_ids(&|x| !x.missing_groups().is_empty());
+    let unmatchable_rules = rule_ids(&|x| x.on() && !x.engine.can_match_tagset(n_tags));
+
+    let mut report = HealthReport {
+        failed_disambiguation_tests: tokenizer
+            .rules()
+            .iter()
+            .filter(|x| !x.test(tokenizer))
+            .map(|x| x.id.to_string())
+            .collect(),
+        failed_rule_tests,
+        synthesis_failures,
+        invalid_group_references,
+        unmatchable_rules,
+        cache_mismatches: cache_mismatches(tokenizer, rules),
+        metadata_issues: metadata_issues(tokenizer.metadata(), rules.metadata()),
+    };
+
+    for ids in [
+        &mut report.failed_disambiguation_tests,
+        &mut report.failed_rule_tests,
+        &mut report.synthesis_failures,
+        &mut report.invalid_group_references,
+        &mut report.unmatchable_rules,
+        &mut report.cache_mismatches,
+    ]
+    .iter_mut()
+    {
+        ids.sort();
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_metadata_issues() {
+        let metadata = |lt_version: &str| Metadata {
+            lt_version: Some(lt_version.to_string()),
+            nlprule_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
+        };
+
+        assert!(metadata_issues(&metadata("5.2"), &metadata("5.2")).is_empty());
+        assert_eq!(metadata_issues(&metadata("5.1"), &metadata("5.2")).len(), 1);
+        assert!(HealthReport::default().is_healthy());
+    }
+}
@@ -0,0 +1,199 @@
+//! Checking of a text which is edited repeatedly, e. g. in an editor after every keystroke.
+
+use crate::{
+    rules::{Limits, SentenceWords},
+    types::*,
+    Error, Rules, Tokenizer,
+};
+use std::hash::{Hash, Hasher};
+
+fn sentence_hash(sentence: &str) -> u64 {
+    let mut hasher = DefaultHasher::default();
+    sentence.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Holds a text and its suggestions and updates them when the text is edited. The suggestions of each sentence are
+/// cached by the hash of the sentence and its context (e. g. whether it is in a quotation), so after an edit only the
+/// sentences which changed are tokenized and checked again. Sentences whose context changed are checked again too.
+/// The suggestions of the other sentences are shifted to their new position.
+///
+/// ```no_run
+/// use nlprule::{incremental::IncrementalChecker, Rules, Tokenizer};
+///
+/// let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+/// let rules = Rules::new("path/to/en_rules.bin")?;
+///
+/// let mut checker = IncrementalChecker::new("She was not been here. It is fine.", &rules, &tokenizer);
+/// // only the second sentence is checked again
+/// checker.edit((23, 25), "This")?;
+///
+/// assert_eq!(checker.text(), "She was not been here. This is fine.");
+/// println!("{:?}", checker.suggestions());
+/// # Ok::<(), nlprule::Error>(())
+/// ```
+pub struct IncrementalChecker<'a> {
+    rules: &'a Rules,
+    tokenizer: &'a Tokenizer,
+    text: String,
+    // the words of each sentence by the hash of the sentence, to compute the context without tokenizing again
+    words: DefaultHashMap<u64, SentenceWords>,
+    // suggestions relative to the start of the sentence, by the hash of the sentence and its context
+    cache: DefaultHashMap<u64, Vec<Suggestion>>,
+    suggestions: Vec<Suggestion>,
+}
+
+impl<'a> IncrementalChecker<'a> {
+    /// Creates a new checker by computing the suggestions for the text.
+    pub fn new(text: &str, rules: &'a Rules, tokenizer: &'a Tokenizer) -> Self {
+        let mut checker = IncrementalChecker {
+            rules,
+            tokenizer,
+            text: text.to_string(),
+            words: DefaultHashMap::default(),
+            cache: DefaultHashMap::default(),
+            suggestions: Vec::new(),
+        };
+        checker.update();
+        checker
+    }
+
+    /// Gets the current text with all edits applied.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Gets the suggestions for the current text, like [Rules::suggest] would return them.
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    /// Replaces the text in the char span `range` with `replacement` and updates the suggestions.
+    /// Returns how many sentences were checked again.
+    ///
+    /// # Errors
+    /// If the span is not within the text.
+    pub fn edit(&mut self, range: (usize, usize), replacement: &str) -> Result<usize, Error> {
+        let converter = SpanConverter::new(&self.text);
+        if range.0 > range.1 || range.1 > converter.char_len() {
+            return Err(Error::InvalidInput(format!(
+                "edit span {:?} is not within the text of {} chars",
+                range,
+                converter.char_len()
+            )));
+        }
+
+        let (byte_start, byte_end) = converter.byte_span(range);
+        self.text.replace_range(byte_start..byte_end, replacement);
+
+        Ok(self.update().1)
+    }
+
+    /// Recomputes the suggestions. Only sentences whose words are not cached are tokenized and only sentences whose
+    /// suggestions are not cached are checked. Returns how many sentences were tokenized and how many were checked.
+    fn update(&mut self) -> (usize, usize) {
+        let mut words = DefaultHashMap::default();
+        let mut n_tokenized = 0;
+
+        let (tokenizer, cached_words) = (self.tokenizer, &mut self.words);
+        let sentences = tokenizer
+            .sentencize(&self.text)
+            .into_iter()
+            .map(|sentence| {
+                let key = sentence_hash(sentence.text);
+                let (sentence_words, tokens) = match cached_words
+                    .remove(&key)
+                    .or_else(|| words.get(&key).cloned())
+                {
+                    Some(cached) => (cached, None),
+                    None => {
+                        n_tokenized += 1;
+                        let tokens = tokenizer.tokenize(sentence.text);
+                        (SentenceWords::new(&tokens), Some(tokens))
+                    }
+                };
+                words.insert(key, sentence_words.clone());
+                (sentence, sentence_words, tokens)
+            })
+            .collect();
+
+        let mut cache = DefaultHashMap::default();
+        let mut suggestions = Vec::new();
+        let mut n_checked = 0;
+
+        for context in self.rules.contexts(&self.text, self.tokenizer, sentences) {
+            let sentence = context.sentence;
+            let key = context.hash();
+
+            let sentence_suggestions = match self.cache.remove(&key).or_else(|| cache.remove(&key))
+            {
+                Some(cached) => cached,
+                None => {
+                    n_checked += 1;
+                    self.rules
+                        .check_in_context(context, self.tokenizer, &Limits::default())
+                        .1
+                        .suggestions
+                }
+            };
+
+            suggestions.extend(sentence_suggestions.iter().cloned().map(|mut suggestion| {
+                suggestion.shift(sentence.char_span.0, sentence.byte_span.0);
+                suggestion
+            }));
+            cache.insert(key, sentence_suggestions);
+        }
+
+        // sentences which are not in the text anymore are dropped from the caches
+        self.words = words;
+        self.cache = cache;
+        self.suggestions = suggestions;
+        (n_tokenized, n_checked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn only_tokenizes_edited_sentences() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, crate::rules::RulesOptions::default());
+
+        let text = "Read teh book. Read a note. Write teh letter.";
+        let mut checker = IncrementalChecker::new(text, &rules, &tokenizer);
+        let starts = |checker: &IncrementalChecker| {
+            checker
+                .suggestions()
+                .iter()
+                .map(|x| x.start)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(starts(&checker), vec![5, 34]);
+
+        checker.text.replace_range(20..21, "teh");
+        assert_eq!(checker.update(), (1, 1));
+        assert_eq!(
+            checker.text(),
+            "Read teh book. Read teh note. Write teh letter."
+        );
+        assert_eq!(starts(&checker), vec![5, 20, 36]);
+
+        // the suggestions are the same as for checking the whole text
+        assert_eq!(
+            checker.suggestions(),
+            &rules.suggest(checker.text(), &tokenizer)[..]
+        );
+        assert_eq!(checker.edit((0, 15), "").unwrap(), 0);
+        assert_eq!(starts(&checker), vec![5, 21]);
+    }
+}
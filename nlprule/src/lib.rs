@@ -17,7 +17,7 @@
 //!     rules.correct("She was not been here since Monday.", &tokenizer),
 //!     String::from("She was not here since Monday.")
 //! );
-//! # Ok::<(), bincode::Error>(())
+//! # Ok::<(), nlprule::Error>(())
 //! ```
 //!
 //! # Example: get suggestions and correct a text
@@ -38,14 +38,15 @@
 //!         end: 16,
 //!         replacements: vec!["was not".into(), "has not been".into()],
 //!         source: "WAS_BEEN.1".into(),
-//!         message: "Did you mean was not or has not been?".into()
+//!         message: "Did you mean was not or has not been?".into(),
+//!         ..Default::default()
 //!     }]
 //! );
 //!
 //! let corrected = apply_suggestions(text, &suggestions);
 //!
 //! assert_eq!(corrected, "She was not here since Monday.");
-//! # Ok::<(), bincode::Error>(())
+//! # Ok::<(), nlprule::Error>(())
 //! ```
 //!
 //! Binaries are distributed with [Github releases](https://github.com/bminixhofer/nlprule/releases).
@@ -55,22 +56,87 @@
 //! Almost all structures with a lifetime are bound to this lifetime.
 use thiserror::Error;
 
+pub mod checks;
 #[cfg(feature = "compile")]
 pub mod compile;
+pub mod conllu;
+pub mod corpus;
+pub mod events;
 mod filter;
+#[doc(hidden)]
+pub mod fuzz;
+pub mod health;
+pub mod incremental;
+pub mod merge;
+pub mod multi;
+pub mod render;
+pub mod rng;
 pub mod rule;
 pub mod rules;
+pub mod selector;
+pub mod session;
+#[cfg(any(test, feature = "snapshot"))]
+pub mod snapshot;
+pub mod stream;
+pub mod text;
 pub mod tokenizer;
+pub mod trace;
 pub mod types;
 pub(crate) mod utils;
 
 pub use rules::Rules;
 pub use tokenizer::Tokenizer;
 
+/// The category of an [Error], e. g. to map errors to HTTP status codes or alerts in a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// A binary could not be read, is corrupt or was built with an incompatible version of nlprule.
+    Model,
+    /// The input e. g. a CoNLL-U file is malformed.
+    Input,
+    /// Options or patterns given by the user are invalid.
+    Config,
+    /// A bug in nlprule or a rule construct which is not supported.
+    Internal,
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("unexpected condition: {0}")]
     Unexpected(String),
     #[error("feature not implemented: {0}")]
     Unimplemented(String),
+    #[error("invalid options: {0}")]
+    InvalidOptions(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("invalid regex: {regex}")]
+    Regex {
+        regex: String,
+        #[source]
+        source: onig::Error,
+    },
+    #[error("failed to read binary")]
+    Io(#[from] std::io::Error),
+    #[error("failed to deserialize binary")]
+    Deserialization(#[from] bincode::Error),
+    #[error("corrupt binary: {0}")]
+    CorruptBinary(String),
+    #[error("binary was built with nlprule {found}, but this is nlprule {expected}")]
+    IncompatibleVersion { found: String, expected: String },
+}
+
+impl Error {
+    /// Gets the category of this error.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::Io(_)
+            | Error::Deserialization(_)
+            | Error::CorruptBinary(_)
+            | Error::IncompatibleVersion { .. } => ErrorCategory::Model,
+            Error::InvalidInput(_) => ErrorCategory::Input,
+            Error::InvalidOptions(_) | Error::Regex { .. } => ErrorCategory::Config,
+            Error::Unexpected(_) | Error::Unimplemented(_) => ErrorCategory::Internal,
+        }
+    }
 }
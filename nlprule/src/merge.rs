@@ -0,0 +1,294 @@
+//! Merging of neighboring suggestions into one composite suggestion, e. g. to offer a single quick fix
+//! for several issues in the same clause.
+
+use crate::types::*;
+
+/// When to merge two suggestions. Overlapping suggestions are always merged since they can not be applied separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Merge suggestions which are only separated by whitespace.
+    Adjacent,
+    /// Merge suggestions which are separated by at most this many characters.
+    MaxGap(usize),
+    /// Merge all suggestions computed on the same sentence, see [Suggestion::sentence_span].
+    Sentence,
+}
+
+/// A suggestion combined from one or more suggestions.
+#[derive(Debug, Clone)]
+pub struct MergedSuggestion {
+    /// The combined suggestion. It spans all parts and each replacement applies the replacements of the parts
+    /// at the same index (or their first replacement if they have fewer). Its source is the sources of the parts joined by "+".
+    pub suggestion: Suggestion,
+    /// The suggestions this suggestion was merged from, sorted by start.
+    pub parts: Vec<Suggestion>,
+}
+
+fn severity_rank(severity: Option<Severity>) -> u8 {
+    match severity {
+        None => 0,
+        Some(Severity::Hint) => 1,
+        Some(Severity::Warning) => 2,
+        Some(Severity::Error) => 3,
+    }
+}
+
+/// Combines the parts of a group into one suggestion. A part which starts inside a previous part does not contribute
+/// to the replacements because applying both is not well defined.
+fn combine(chars: &[char], parts: Vec<Suggestion>) -> MergedSuggestion {
+    if parts.len() == 1 {
+        return MergedSuggestion {
+            suggestion: parts[0].clone(),
+            parts,
+        };
+    }
+
+    let start = parts[0].start;
+    let end = parts.iter().map(|x| x.end).max().unwrap_or(start);
+
+    let mut kept = Vec::new();
+    let mut cursor = start;
+    for part in &parts {
+        if part.start >= cursor {
+            cursor = part.end;
+            kept.push(part);
+        }
+    }
+
+    let n_replacements = kept.iter().map(|x| x.replacements.len()).max().unwrap_or(0);
+    let replacements = (0..n_replacements)
+        .map(|i| {
+            let mut replacement = String::new();
+            let mut cursor = start;
+            for part in &kept {
+                replacement.extend(&chars[cursor..part.start]);
+                match part
+                    .replacements
+                    .get(i)
+                    .or_else(|| part.replacements.first())
+                {
+                    Some(x) => replacement.push_str(x),
+                    None => replacement.extend(&chars[part.start..part.end]),
+                }
+                cursor = part.end;
+            }
+            replacement.extend(&chars[cursor..end]);
+            replacement
+        })
+        .collect();
+
+    // identical messages are only included once
+    let mut message_parts = Vec::new();
+    let mut messages_seen: Vec<&str> = Vec::new();
+    for part in &parts {
+        if messages_seen.contains(&part.message.as_str()) {
+            continue;
+        }
+        if !messages_seen.is_empty() {
+            message_parts.push(MessagePart::Literal(" ".to_string()));
+        }
+        messages_seen.push(&part.message);
+
+        if part.message_parts.is_empty() {
+            message_parts.push(MessagePart::Literal(part.message.clone()));
+        } else {
+            message_parts.extend(part.message_parts.iter().cloned());
+        }
+    }
+    let short_message = if parts
+        .iter()
+        .all(|x| x.short_message == parts[0].short_message)
+    {
+        parts[0].short_message.clone()
+    } else {
+        None
+    };
+
+    // token indices are relative to the sentence so they can only be combined within one sentence
+    let same_sentence = parts
+        .iter()
+        .all(|x| x.sentence_span == parts[0].sentence_span);
+    let token_span = parts
+        .iter()
+        .map(|x| x.token_span)
+        .collect::<Option<Vec<_>>>()
+        .filter(|_| same_sentence)
+        .map(|spans| {
+            (
+                spans.iter().map(|x| x.0).min().unwrap_or(0),
+                spans.iter().map(|x| x.1).max().unwrap_or(0),
+            )
+        });
+    let mut tokens: Vec<_> = if same_sentence {
+        parts
+            .iter()
+            .flat_map(|x| x.tokens.iter().cloned())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    tokens.sort_by_key(|x| x.char_span);
+    tokens.dedup_by_key(|x| x.char_span);
+    // the context before the first part and after the part which ends last
+    let last = parts.iter().max_by_key(|x| x.end).unwrap_or(&parts[0]);
+    let features = match (&parts[0].features, &last.features) {
+        (Some(first), Some(last)) if same_sentence => Some(ContextFeatures {
+            prev_text: first.prev_text.clone(),
+            prev_pos: first.prev_pos.clone(),
+            next_text: last.next_text.clone(),
+            next_pos: last.next_pos.clone(),
+            sentence_tokens: first.sentence_tokens,
+            category: first.category.clone().filter(|category| {
+                parts.iter().all(|x| {
+                    x.features.as_ref().and_then(|x| x.category.as_ref()) == Some(category)
+                })
+            }),
+        }),
+        _ => None,
+    };
+
+    let suggestion = Suggestion {
+        source: parts
+            .iter()
+            .map(|x| x.source.as_str())
+            .collect::<Vec<_>>()
+            .join("+"),
+        message: message_parts.iter().map(|x| x.text()).collect(),
+        message_parts,
+        short_message,
+        start,
+        end,
+        byte_span: (
+            parts.iter().map(|x| x.byte_span.0).min().unwrap_or(0),
+            parts.iter().map(|x| x.byte_span.1).max().unwrap_or(0),
+        ),
+        replacements,
+        severity: parts
+            .iter()
+            .map(|x| x.severity)
+            .max_by_key(|x| severity_rank(*x))
+            .flatten(),
+        sentence_span: (
+            parts.iter().map(|x| x.sentence_span.0).min().unwrap_or(0),
+            parts.iter().map(|x| x.sentence_span.1).max().unwrap_or(0),
+        ),
+        token_span,
+        tokens,
+        features,
+        // group IDs refer to the pattern of one rule, the groups of each rule are in the parts
+        groups: if parts.len() == 1 {
+            parts[0].groups.clone()
+        } else {
+            Vec::new()
+        },
+        priority: parts.iter().map(|x| x.priority).fold(0., f32::max),
+    };
+
+    MergedSuggestion { suggestion, parts }
+}
+
+/// Merges suggestions according to the strategy. `text` must be the text the indices of the suggestions refer to.
+/// Returns the merged suggestions sorted by start; suggestions which are not merged with any other are returned as is.
+pub fn merge_suggestions(
+    text: &str,
+    suggestions: &[Suggestion],
+    strategy: MergeStrategy,
+) -> Vec<MergedSuggestion> {
+    let chars: Vec<_> = text.chars().collect();
+
+    let mut suggestions = suggestions.to_vec();
+    suggestions.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| b.end.cmp(&a.end)));
+
+    let mut groups: Vec<Vec<Suggestion>> = Vec::new();
+    for suggestion in suggestions {
+        let merge = match groups.last() {
+            Some(group) => {
+                let end = group.iter().map(|x| x.end).max().unwrap_or(0);
+
+                suggestion.start < end
+                    || match strategy {
+                        MergeStrategy::Adjacent => chars[end..suggestion.start]
+                            .iter()
+                            .all(|x| x.is_whitespace()),
+                        MergeStrategy::MaxGap(max_gap) => suggestion.start - end <= max_gap,
+                        MergeStrategy::Sentence => {
+                            group[0].sentence_span == suggestion.sentence_span
+                        }
+                    }
+            }
+            None => false,
+        };
+
+        if merge {
+            groups
+                .last_mut()
+                .expect("group must exist")
+                .push(suggestion);
+        } else {
+            groups.push(vec![suggestion]);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|group| combine(&chars, group))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(source: &str, start: usize, end: usize, replacements: &[&str]) -> Suggestion {
+        Suggestion {
+            source: source.to_string(),
+            message: format!("{} message", source),
+            start,
+            end,
+            replacements: replacements.iter().map(|x| x.to_string()).collect(),
+            sentence_span: (0, 21),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merges_adjacent_suggestions() {
+        let text = "He go to there house.";
+        let suggestions = vec![
+            suggestion("A", 3, 5, &["goes", "went"]),
+            suggestion("B", 9, 14, &["their"]),
+            suggestion("C", 0, 2, &["She"]),
+        ];
+
+        let merged = merge_suggestions(text, &suggestions, MergeStrategy::Adjacent);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].suggestion.source, "C+A");
+        assert_eq!(
+            merged[0].suggestion.replacements,
+            vec!["She goes", "She went"]
+        );
+        assert_eq!(merged[0].suggestion.message, "C message A message");
+        assert_eq!(merged[1].parts.len(), 1);
+
+        let merged = merge_suggestions(text, &suggestions, MergeStrategy::MaxGap(4));
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].suggestion.replacements[0], "She goes to their");
+    }
+
+    #[test]
+    fn merges_nested_suggestions() {
+        let text = "He go to there house.";
+        let suggestions = vec![
+            suggestion("OUTER", 0, 5, &["She goes"]),
+            suggestion("INNER", 3, 5, &["goes"]),
+        ];
+
+        let merged = merge_suggestions(text, &suggestions, MergeStrategy::MaxGap(0));
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            (merged[0].suggestion.start, merged[0].suggestion.end),
+            (0, 5)
+        );
+        assert_eq!(merged[0].suggestion.replacements, vec!["She goes"]);
+    }
+}
@@ -0,0 +1,173 @@
+//! Checking of documents which contain text in more than one language.
+
+use crate::{types::*, utils::parallelism::MaybeParallelIterator, Rules, Tokenizer};
+
+/// Detects the language of a paragraph. Returns `None` if the language is not known.
+pub type LanguageDetector = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// A suggestion together with the language of the checker it is from.
+#[derive(Debug, Clone)]
+pub struct TaggedSuggestion {
+    /// The language code of the checker which produced the suggestion, as passed to [MultiChecker::add].
+    pub language: String,
+    /// The suggestion. Its indices refer to the whole document.
+    pub suggestion: Suggestion,
+}
+
+/// Holds checkers for several languages and checks each paragraph of a document with the checker for its language.
+/// Paragraphs are separated by blank lines.
+///
+/// ```no_run
+/// use nlprule::{multi::MultiChecker, Rules, Tokenizer};
+///
+/// let checker = MultiChecker::new()
+///     .add("en", Rules::new("path/to/en_rules.bin")?, Tokenizer::new("path/to/en_tokenizer.bin")?)
+///     .add("de", Rules::new("path/to/de_rules.bin")?, Tokenizer::new("path/to/de_tokenizer.bin")?)
+///     .detector(|paragraph| {
+///         // plug in a language detector here
+///         Some(if paragraph.contains(" und ") { "de" } else { "en" }.to_string())
+///     });
+///
+/// for tagged in checker.suggest("She was not been here.\n\nEr sind hier.\n") {
+///     println!("{}: {}", tagged.language, tagged.suggestion.message);
+/// }
+/// # Ok::<(), nlprule::Error>(())
+/// ```
+#[derive(Default)]
+pub struct MultiChecker {
+    checkers: Vec<(String, Rules, Tokenizer)>,
+    detector: Option<Box<LanguageDetector>>,
+}
+
+impl MultiChecker {
+    /// Creates a new checker without any languages.
+    pub fn new() -> Self {
+        MultiChecker::default()
+    }
+
+    /// Adds the rules and tokenizer for a language. The first language added is the default,
+    /// it is used if no detector is set or the detector does not return a known language.
+    pub fn add<S: Into<String>>(mut self, language: S, rules: Rules, tokenizer: Tokenizer) -> Self {
+        self.checkers.push((language.into(), rules, tokenizer));
+        self
+    }
+
+    /// Sets the function used to detect the language of each paragraph.
+    pub fn detector<F: Fn(&str) -> Option<String> + Send + Sync + 'static>(
+        mut self,
+        detector: F,
+    ) -> Self {
+        self.detector = Some(Box::new(detector));
+        self
+    }
+
+    /// Gets the languages of this checker in the order they were added.
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.checkers
+            .iter()
+            .map(|(language, _, _)| language.as_str())
+    }
+
+    /// Gets the index of the checker to use for a paragraph.
+    fn checker_index(&self, paragraph: &str) -> Option<usize> {
+        let detected = self
+            .detector
+            .as_ref()
+            .and_then(|detector| detector(paragraph));
+
+        match detected
+            .and_then(|language| self.checkers.iter().position(|(x, _, _)| *x == language))
+        {
+            Some(index) => Some(index),
+            None if self.checkers.is_empty() => None,
+            None => Some(0),
+        }
+    }
+
+    /// Computes the suggestions for a document, sorted by their start index.
+    /// Returns no suggestions if no language has been added.
+    pub fn suggest(&self, text: &str) -> Vec<TaggedSuggestion> {
+        let suggestions: Vec<Vec<TaggedSuggestion>> = paragraphs(text)
+            .into_maybe_par_iter()
+            .map(|(start, paragraph)| {
+                let (language, rules, tokenizer) = match self.checker_index(paragraph) {
+                    Some(index) => &self.checkers[index],
+                    None => return Vec::new(),
+                };
+
+                rules
+                    .suggest(paragraph, tokenizer)
+                    .into_iter()
+                    .map(|mut suggestion| {
+                        suggestion
+                            .shift(start, paragraph.as_ptr() as usize - text.as_ptr() as usize);
+                        TaggedSuggestion {
+                            language: language.clone(),
+                            suggestion,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut suggestions: Vec<_> = suggestions.into_iter().flatten().collect();
+        suggestions.sort_by_key(|x| x.suggestion.start);
+        suggestions
+    }
+}
+
+/// Splits a text into paragraphs separated by blank lines.
+/// Returns the character index of the start of each paragraph and its text.
+fn paragraphs(text: &str) -> Vec<(usize, &str)> {
+    let mut paragraphs = Vec::new();
+    let mut start = (0, 0);
+    let mut char_index = 0;
+    let mut byte_index = 0;
+
+    for line in text.split_inclusive('\n') {
+        char_index += line.chars().count();
+        byte_index += line.len();
+
+        if line.trim().is_empty() {
+            paragraphs.push((start.0, &text[start.1..byte_index]));
+            start = (char_index, byte_index);
+        }
+    }
+
+    if start.1 < text.len() {
+        paragraphs.push((start.0, &text[start.1..]));
+    }
+
+    paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_paragraphs() {
+        assert_eq!(
+            paragraphs("Ä first.\nStill first.\n\nZweiter."),
+            vec![(0, "Ä first.\nStill first.\n\n"), (23, "Zweiter.")]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_first_language() {
+        let checker = MultiChecker::new()
+            .add("en", Rules::default(), Tokenizer::default())
+            .add("de", Rules::default(), Tokenizer::default())
+            .detector(|paragraph| {
+                if paragraph.contains(" und ") {
+                    Some("de".to_string())
+                } else {
+                    None
+                }
+            });
+
+        assert_eq!(checker.checker_index("Ich und du."), Some(1));
+        assert_eq!(checker.checker_index("Me and you."), Some(0));
+        assert_eq!(MultiChecker::new().checker_index("Me and you."), None);
+    }
+}
@@ -0,0 +1,225 @@
+//! Rendering of suggestions with their context as HTML or Markdown for display in frontends.
+
+use crate::{types::*, Rules};
+
+/// Shown in place of the empty span of an insertion.
+const INSERTION_MARK: &str = "‸";
+
+/// The markup a suggestion is rendered to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// HTML with `nlprule-*` classes for styling. The highlighted span is wrapped in `<mark>`.
+    Html,
+    /// Markdown. The highlighted span is bold.
+    Markdown,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+/// Escapes inline Markdown. Newlines are replaced with spaces so the text stays in one block.
+fn escape_markdown(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\n' {
+            output.push(' ');
+            continue;
+        }
+        if "\\`*_[]<>#|~".contains(c) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// Splits the sentence of a suggestion into the text before, in and after its span.
+/// The spans are clamped to the text and the sentence is extended to contain the span, so a suggestion
+/// which does not fit the text is rendered with less context instead of panicking.
+fn context<'a>(text: &'a str, suggestion: &Suggestion) -> (&'a str, &'a str, &'a str) {
+    let converter = SpanConverter::new(text);
+    let clamp = |index: usize| index.min(converter.char_len());
+
+    let start = clamp(suggestion.start);
+    let end = clamp(suggestion.end).max(start);
+    let (sentence_start, sentence_end) = converter.byte_span((
+        clamp(suggestion.sentence_span.0).min(start),
+        clamp(suggestion.sentence_span.1).max(end),
+    ));
+    let (start, end) = converter.byte_span((start, end));
+
+    (
+        &text[sentence_start..start],
+        &text[start..end],
+        &text[end..sentence_end],
+    )
+}
+
+/// Renders a suggestion with the sentence it is in, the span highlighted, its message, replacements and the ID of the rule.
+/// `text` must be the text the indices of the suggestion refer to. `url` is linked from the rule ID if set.
+pub fn render(text: &str, suggestion: &Suggestion, url: Option<&str>, format: Format) -> String {
+    let (before, span, after) = context(text, suggestion);
+    let span = if span.is_empty() {
+        INSERTION_MARK
+    } else {
+        span
+    };
+
+    match format {
+        Format::Html => {
+            let mut output = String::from("<div class=\"nlprule-suggestion\">\n");
+            if let Some(short_message) = &suggestion.short_message {
+                output.push_str(&format!(
+                    "<p class=\"nlprule-short-message\">{}</p>\n",
+                    escape_html(short_message)
+                ));
+            }
+            output.push_str(&format!(
+                "<p class=\"nlprule-message\">{}</p>\n<p class=\"nlprule-context\">{}<mark>{}</mark>{}</p>\n",
+                escape_html(&suggestion.message),
+                escape_html(before),
+                escape_html(span),
+                escape_html(after)
+            ));
+            if !suggestion.replacements.is_empty() {
+                output.push_str("<ul class=\"nlprule-replacements\">\n");
+                for replacement in &suggestion.replacements {
+                    output.push_str(&format!("<li>{}</li>\n", escape_html(replacement)));
+                }
+                output.push_str("</ul>\n");
+            }
+            match url {
+                Some(url) => output.push_str(&format!(
+                    "<a class=\"nlprule-rule\" href=\"{}\">{}</a>\n",
+                    escape_html(url),
+                    escape_html(&suggestion.source)
+                )),
+                None => output.push_str(&format!(
+                    "<span class=\"nlprule-rule\">{}</span>\n",
+                    escape_html(&suggestion.source)
+                )),
+            }
+            output.push_str("</div>\n");
+            output
+        }
+        Format::Markdown => {
+            let mut output = String::new();
+            if let Some(short_message) = &suggestion.short_message {
+                output.push_str(&format!("**{}**\n\n", escape_markdown(short_message)));
+            }
+            output.push_str(&format!(
+                "{}\n\n> {}**{}**{}\n\n",
+                escape_markdown(&suggestion.message),
+                escape_markdown(before.trim_start()),
+                escape_markdown(span),
+                escape_markdown(after.trim_end())
+            ));
+            if !suggestion.replacements.is_empty() {
+                let replacements: Vec<_> = suggestion
+                    .replacements
+                    .iter()
+                    .map(|x| escape_markdown(x))
+                    .collect();
+                output.push_str(&format!("Suggestions: {}\n\n", replacements.join(", ")));
+            }
+            match url {
+                Some(url) => output.push_str(&format!(
+                    "Rule: [{}](<{}>)\n",
+                    escape_markdown(&suggestion.source),
+                    url.replace('>', "%3E")
+                )),
+                None => {
+                    output.push_str(&format!("Rule: {}\n", escape_markdown(&suggestion.source)))
+                }
+            }
+            output
+        }
+    }
+}
+
+/// Renders a suggestion like [render], linking the URL of the rule the suggestion is from if it has one.
+pub fn render_with_rules(
+    text: &str,
+    suggestion: &Suggestion,
+    rules: &Rules,
+    format: Format,
+) -> String {
+    let url = rules.rule(&suggestion.source).and_then(|rule| rule.url());
+    render(text, suggestion, url, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_context() {
+        let text = "Thiss <is> a test.";
+        let suggestion = Suggestion {
+            source: "TYPO".into(),
+            message: "Possible typo.".into(),
+            start: 0,
+            end: 5,
+            replacements: vec!["This".into()],
+            sentence_span: (0, 18),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render(text, &suggestion, Some("https://example.com"), Format::Html),
+            "<div class=\"nlprule-suggestion\">\n\
+             <p class=\"nlprule-message\">Possible typo.</p>\n\
+             <p class=\"nlprule-context\"><mark>Thiss</mark> &lt;is&gt; a test.</p>\n\
+             <ul class=\"nlprule-replacements\">\n<li>This</li>\n</ul>\n\
+             <a class=\"nlprule-rule\" href=\"https://example.com\">TYPO</a>\n\
+             </div>\n"
+        );
+        assert_eq!(
+            render(text, &suggestion, None, Format::Markdown),
+            "Possible typo.\n\n> **Thiss** \\<is\\> a test.\n\nSuggestions: This\n\nRule: TYPO\n"
+        );
+    }
+
+    #[test]
+    fn clamps_context() {
+        let text = "Thé tëst.";
+        let suggestion = |start, end, sentence_span| Suggestion {
+            start,
+            end,
+            sentence_span,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context(text, &suggestion(4, 8, (0, 9))),
+            ("Thé ", "tëst", ".")
+        );
+        // the sentence is extended to contain the span
+        assert_eq!(
+            context(text, &suggestion(4, 8, (0, 0))),
+            ("Thé ", "tëst", "")
+        );
+        assert_eq!(context(text, &suggestion(4, 8, (5, 6))), ("", "tëst", ""));
+        // spans beyond the text are clamped to it
+        assert_eq!(
+            context(text, &suggestion(8, 20, (0, 30))),
+            ("Thé tëst", ".", "")
+        );
+        assert_eq!(
+            context(text, &suggestion(20, 4, (0, 9))),
+            ("Thé tëst.", "", "")
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! A small seeded pseudo random number generator so sampling is reproducible without extra dependencies.
+//! APIs which sample (e. g. [TestSelection::Sample][crate::rules::TestSelection::Sample] or
+//! [CorpusStats::add_lines_sampled][crate::corpus::CorpusStats::add_lines_sampled]) take a seed or an [Rng]
+//! so results are the same across runs, e. g. in CI.
+
+/// SplitMix64, see <https://prng.di.unimi.it/splitmix64.c>.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator. The same seed always gives the same numbers on all platforms.
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Gets the next pseudo random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`. `n` must not be zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns `true` with probability `p`. Always `false` if `p <= 0` and always `true` if `p >= 1`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        // the upper 53 bits give a uniform float in [0, 1)
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// Samples `n` distinct indices in `0..len` (all if `n >= len`) and returns them in ascending order.
+    pub fn sample_indices(&mut self, len: usize, n: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..len).collect();
+        let n = std::cmp::min(n, len);
+
+        // partial Fisher-Yates shuffle
+        for i in 0..n {
+            let j = i + self.below(len - i);
+            indices.swap(i, j);
+        }
+
+        indices.truncate(n);
+        indices.sort_unstable();
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling_is_reproducible() {
+        let a = Rng::new(42).sample_indices(100, 10);
+        let b = Rng::new(42).sample_indices(100, 10);
+
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 10);
+        assert!(a.windows(2).all(|x| x[0] < x[1]));
+        assert_eq!(Rng::new(1).sample_indices(3, 10), vec![0, 1, 2]);
+
+        let mut rng = Rng::new(7);
+        assert!((0..100).all(|_| rng.chance(1.0) && !rng.chance(0.0)));
+        let n_hits = (0..1000).filter(|_| rng.chance(0.5)).count();
+        assert!(n_hits > 400 && n_hits < 600);
+    }
+}
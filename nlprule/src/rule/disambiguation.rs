@@ -1,4 +1,10 @@
-use crate::types::*;
+use crate::{
+    tokenizer::{
+        morph::{Features, TagParser},
+        tag::Tagger,
+    },
+    types::*,
+};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +16,25 @@ pub struct POSFilter {
 }
 
 impl POSFilter {
+    /// Creates a filter matching the readings whose tag has (at least) the `required` features.
+    /// Since the features depend only on the tag, they are parsed once for every tag of the tagger.
+    pub fn from_features(required: &Features, parser: TagParser, tagger: &Tagger) -> Self {
+        let mut mask = vec![false; tagger.tag_store().len()];
+
+        for (tag, id) in tagger.tag_store().iter() {
+            mask[*id as usize] = parser.parse(tag).satisfies(required);
+        }
+
+        POSFilter {
+            matcher: PosMatcher { mask },
+        }
+    }
+
+    /// Whether the reading matches this filter.
+    pub fn is_match(&self, data: &WordData) -> bool {
+        self.is_word_data_match(data)
+    }
+
     fn is_word_data_match(&self, data: &WordData) -> bool {
         self.matcher.is_match(&data.pos)
     }
@@ -22,7 +47,7 @@ impl POSFilter {
         data.tags.retain(|x| !self.is_word_data_match(x))
     }
 
-    fn and(filters: &[&Self], data: &Word) -> bool {
+    pub(crate) fn and(filters: &[&Self], data: &Word) -> bool {
         data.tags
             .iter()
             .any(|x| filters.iter().all(|filter| filter.is_word_data_match(x)))
@@ -47,7 +72,35 @@ pub enum Disambiguation {
     Nop,
 }
 
+/// What a disambiguation rule does to the tokens it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisambiguationKind {
+    /// Removes readings.
+    Remove,
+    /// Adds readings.
+    Add,
+    /// Replaces all readings.
+    Replace,
+    /// Keeps only matching readings.
+    Filter,
+    /// Keeps only readings which agree in features across tokens.
+    Unify,
+    /// Does not change the tokens, e. g. an immunization.
+    Nop,
+}
+
 impl Disambiguation {
+    pub fn kind(&self) -> DisambiguationKind {
+        match self {
+            Disambiguation::Remove(_) => DisambiguationKind::Remove,
+            Disambiguation::Add(_) => DisambiguationKind::Add,
+            Disambiguation::Replace(_) => DisambiguationKind::Replace,
+            Disambiguation::Filter(_) => DisambiguationKind::Filter,
+            Disambiguation::Unify(..) => DisambiguationKind::Unify,
+            Disambiguation::Nop => DisambiguationKind::Nop,
+        }
+    }
+
     pub fn apply<'t>(&'t self, groups: Vec<Vec<&mut IncompleteToken<'t>>>, retain_last: bool) {
         match self {
             Disambiguation::Remove(data_or_filters) => {
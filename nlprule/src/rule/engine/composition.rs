@@ -101,6 +101,11 @@ impl PosMatcher {
     pub fn is_match(&self, pos: &PosId) -> bool {
         self.mask[*pos.id() as usize]
     }
+
+    /// Whether this matcher was built for a tagset with `n_tags` tags and matches at least one of them.
+    pub(crate) fn matches_any(&self, n_tags: usize) -> bool {
+        self.mask.len() == n_tags && self.mask.iter().any(|x| *x)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -163,6 +168,23 @@ pub enum Atom {
     OffsetAtom,
 }
 
+impl Atom {
+    /// Whether this atom can match a token tagged with a tagset of `n_tags` tags. POS matchers which match no tag
+    /// can not match. Negated atoms are assumed to be able to match.
+    pub(crate) fn can_match_tagset(&self, n_tags: usize) -> bool {
+        match self {
+            Atom::WordDataAtom(atom) => match &atom.matcher.pos_matcher {
+                Some(matcher) => matcher.matches_any(n_tags),
+                None => true,
+            },
+            Atom::AndAtom(atom) => atom.atoms.iter().all(|x| x.can_match_tagset(n_tags)),
+            Atom::OrAtom(atom) => atom.atoms.iter().any(|x| x.can_match_tagset(n_tags)),
+            Atom::OffsetAtom(atom) => atom.atom.can_match_tagset(n_tags),
+            _ => true,
+        }
+    }
+}
+
 pub mod concrete {
     use super::{Atomable, MatchGraph, Matcher, TextMatcher, Token, WordDataMatcher};
     use serde::{Deserialize, Serialize};
@@ -317,15 +339,14 @@ impl Group {
             .collect()
     }
 
-    pub fn text<'a>(&self, text: &'a str) -> &'a str {
+    /// Gets the text of this group. `converter` must be built from `text`, it is shared by all groups of a sentence.
+    pub fn text<'a>(&self, text: &'a str, converter: &SpanConverter) -> &'a str {
         if self.char_span.0 >= self.char_span.1 {
             return "";
         }
 
-        let mut char_indices: Vec<_> = text.char_indices().map(|(i, _)| i).collect();
-        char_indices.push(text.len());
-
-        &text[char_indices[self.char_span.0]..char_indices[self.char_span.1]]
+        let (start, end) = converter.byte_span(self.char_span);
+        &text[start..end]
     }
 }
 
@@ -350,6 +371,51 @@ impl<'t> Default for MatchGraph<'t> {
     }
 }
 
+/// Reusable group buffers for [MatchGraph]s, so candidate matches which fail and matches which are
+/// only inspected (e. g. of antipatterns) do not allocate. A pool lives for one scan of a rule over a sentence.
+#[derive(Debug, Default)]
+pub struct GraphPool {
+    buffers: Vec<Vec<Group>>,
+}
+
+impl GraphPool {
+    /// Gets a buffer of `len` empty groups.
+    fn take(&mut self, len: usize) -> Vec<Group> {
+        let mut buffer = self.buffers.pop().unwrap_or_default();
+        buffer.clear();
+        buffer.resize(len, Group::default());
+        buffer
+    }
+
+    /// Returns the groups of a graph which is not needed anymore to the pool.
+    pub fn recycle(&mut self, graph: MatchGraph) {
+        self.buffers.push(graph.groups);
+    }
+}
+
+/// Bounds the steps of matching a composition at one position, see
+/// [Limits::max_match_steps][crate::rules::Limits::max_match_steps].
+#[derive(Debug, Clone, Copy)]
+pub struct StepLimit {
+    max_steps: usize,
+    exceeded: usize,
+}
+
+impl StepLimit {
+    /// Creates a limit of `max_steps` steps, `None` for no limit.
+    pub fn new(max_steps: Option<usize>) -> Self {
+        StepLimit {
+            max_steps: max_steps.unwrap_or(usize::MAX),
+            exceeded: 0,
+        }
+    }
+
+    /// How often matching was stopped because it exceeded the limit.
+    pub fn exceeded(&self) -> usize {
+        self.exceeded
+    }
+}
+
 impl<'t> MatchGraph<'t> {
     pub fn new(
         groups: Vec<Group>,
@@ -451,6 +517,13 @@ pub struct Composition {
 }
 
 impl Composition {
+    /// Whether every part which must match at least once can match a token tagged with a tagset of `n_tags` tags.
+    pub(crate) fn can_match_tagset(&self, n_tags: usize) -> bool {
+        self.parts
+            .iter()
+            .all(|x| x.quantifier.min == 0 || x.atom.can_match_tagset(n_tags))
+    }
+
     fn next_can_match<'t>(
         &self,
         tokens: &'t [&'t Token<'t>],
@@ -475,10 +548,15 @@ impl Composition {
             .any(|x| x.atom.is_match(tokens, graph, position))
     }
 
+    /// Matches this composition at `start`. The groups of the graph are taken from `pool`
+    /// and returned to it if there is no match. Matching is stopped without a match once it takes more
+    /// steps than allowed by `limit`, one step checks one token against the current part.
     pub fn apply<'t>(
         &'t self,
         tokens: &'t [&'t Token<'t>],
         start: usize,
+        pool: &mut GraphPool,
+        limit: &mut StepLimit,
     ) -> Option<MatchGraph<'t>> {
         // this path is extremely hot so more optimizations are done
 
@@ -499,16 +577,24 @@ impl Composition {
         let mut cur_atom_idx = 0;
 
         let mut graph = MatchGraph::new(
-            vec![Group::default(); self.parts.len() + 1],
+            pool.take(self.parts.len() + 1),
             &self.group_ids_to_idx,
             tokens,
         );
 
+        let mut steps = 0;
         let mut is_match = loop {
             if cur_atom_idx >= self.parts.len() {
                 break true;
             }
 
+            steps += 1;
+            if steps > limit.max_steps {
+                limit.exceeded += 1;
+                pool.recycle(graph);
+                return None;
+            }
+
             let part = &self.parts[cur_atom_idx];
 
             if cur_count >= part.quantifier.max {
@@ -553,7 +639,66 @@ impl Composition {
             graph.fill_empty();
             Some(graph)
         } else {
+            pool.recycle(graph);
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gets_group_texts() {
+        let text = "Grüße aus Köln.";
+        let converter = SpanConverter::new(text);
+
+        assert_eq!(Group::new((0, 5)).text(text, &converter), "Grüße");
+        assert_eq!(Group::new((10, 14)).text(text, &converter), "Köln");
+        assert_eq!(Group::new((6, 6)).text(text, &converter), "");
+    }
+
+    #[test]
+    fn detects_pos_matchers_without_tags() {
+        let pos_atom = |mask: Vec<bool>| -> Atom {
+            concrete::WordDataAtom {
+                matcher: WordDataMatcher {
+                    pos_matcher: Some(PosMatcher { mask }),
+                    inflect_matcher: None,
+                },
+                case_sensitive: false,
+            }
+            .into()
+        };
+
+        assert!(pos_atom(vec![false, true]).can_match_tagset(2));
+        assert!(!pos_atom(vec![false, false]).can_match_tagset(2));
+        // built for another tagset
+        assert!(!pos_atom(vec![false, true]).can_match_tagset(3));
+
+        let or = OrAtom {
+            atoms: vec![pos_atom(vec![false, false]), pos_atom(vec![true, false])],
+        };
+        assert!(Atom::from(or).can_match_tagset(2));
+        let not = NotAtom {
+            atom: Box::new(pos_atom(vec![false, false])),
+        };
+        assert!(Atom::from(not).can_match_tagset(2));
+    }
+
+    #[test]
+    fn reuses_graph_buffers() {
+        let mut pool = GraphPool::default();
+        let mut groups = pool.take(2);
+        groups[1].char_span = (3, 5);
+        pool.recycle(MatchGraph::new(groups, &EMPTY_MAP, &[]));
+
+        let groups = pool.take(3);
+        assert_eq!(
+            groups.iter().map(|x| x.char_span).collect::<Vec<_>>(),
+            vec![(0, 0); 3]
+        );
+        assert!(pool.buffers.is_empty());
+    }
+}
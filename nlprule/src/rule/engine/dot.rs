@@ -0,0 +1,232 @@
+//! Rendering of engines as [Graphviz DOT](https://graphviz.org/doc/info/lang.html) to inspect how rules were compiled.
+
+use super::{
+    composition::{Atom, Composition, Matcher, PosMatcher, Quantifier},
+    Engine,
+};
+use crate::tokenizer::tag::Tagger;
+
+/// How many matching tags are listed for a part-of-speech matcher.
+const MAX_TAGS: usize = 4;
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn matcher_label(matcher: &Matcher) -> String {
+    let mut label = match &matcher.matcher {
+        either::Left(either::Left(string)) => format!("\"{}\"", string),
+        either::Left(either::Right(id)) => format!("\\{}", id),
+        either::Right(regex) => format!("/{}/", regex.as_str()),
+    };
+
+    if matcher.negate {
+        label.insert(0, '!');
+    }
+    if !matcher.case_sensitive {
+        label.push_str(" (i)");
+    }
+    label
+}
+
+fn pos_label(matcher: &PosMatcher, tagger: &Tagger) -> String {
+    let mut tags: Vec<_> = tagger
+        .tag_store()
+        .iter()
+        .filter(|(_, id)| matcher.mask.get(**id as usize).copied().unwrap_or(false))
+        .map(|(tag, _)| tag.as_str())
+        .collect();
+    tags.sort_unstable();
+
+    if tags.len() == matcher.mask.len() {
+        return "any POS".to_string();
+    }
+
+    let mut label = format!("POS {}", tags[..tags.len().min(MAX_TAGS)].join("|"));
+    if tags.len() > MAX_TAGS {
+        label.push_str(&format!("|... ({} tags)", tags.len()));
+    }
+    label
+}
+
+fn atom_label(atom: &Atom, tagger: &Tagger) -> String {
+    match atom {
+        Atom::TextAtom(atom) => format!("text {}", matcher_label(&atom.matcher.matcher)),
+        Atom::ChunkAtom(atom) => format!("chunk {}", matcher_label(&atom.matcher)),
+        Atom::SpaceBeforeAtom(atom) => if atom.value {
+            "space before"
+        } else {
+            "no space before"
+        }
+        .to_string(),
+        Atom::WordDataAtom(atom) => {
+            let mut parts = Vec::new();
+            if let Some(matcher) = &atom.matcher.pos_matcher {
+                parts.push(pos_label(matcher, tagger));
+            }
+            if let Some(matcher) = &atom.matcher.inflect_matcher {
+                parts.push(format!("lemma {}", matcher_label(&matcher.matcher)));
+            }
+            parts.join(" & ")
+        }
+        Atom::TrueAtom(_) => "any".to_string(),
+        Atom::FalseAtom(_) => "none".to_string(),
+        Atom::AndAtom(atom) => combined_label(&atom.atoms, " &\n", "any", tagger),
+        Atom::OrAtom(atom) => combined_label(&atom.atoms, " |\n", "none", tagger),
+        Atom::NotAtom(atom) => format!("not ({})", atom_label(&atom.atom, tagger)),
+        Atom::OffsetAtom(atom) => {
+            format!("at {:+}: {}", atom.offset, atom_label(&atom.atom, tagger))
+        }
+    }
+}
+
+/// Joins the labels of atoms, leaving out atoms which do not change the result (e. g. `any` in a conjunction).
+fn combined_label(atoms: &[Atom], separator: &str, neutral: &str, tagger: &Tagger) -> String {
+    let labels: Vec<_> = atoms
+        .iter()
+        .map(|x| atom_label(x, tagger))
+        .filter(|x| x != neutral)
+        .collect();
+
+    match labels.len() {
+        0 => neutral.to_string(),
+        1 => labels[0].clone(),
+        _ => format!("({})", labels.join(separator)),
+    }
+}
+
+fn quantifier_label(quantifier: &Quantifier) -> Option<String> {
+    match (quantifier.min, quantifier.max) {
+        (1, 1) => None,
+        (0, 1) => Some("?".to_string()),
+        (min, max) => Some(format!("{{{},{}}}", min, max)),
+    }
+}
+
+/// Writes the parts of a composition as a chain of nodes in a cluster. Parts in `marker` (group IDs) are filled.
+fn write_composition(
+    output: &mut String,
+    name: &str,
+    label: &str,
+    composition: &Composition,
+    marker: Option<(usize, usize)>,
+    tagger: &Tagger,
+) {
+    let mut ids: Vec<_> = composition.group_ids_to_idx.iter().collect();
+    ids.sort_unstable();
+
+    output.push_str(&format!(
+        "  subgraph cluster_{} {{\n    label=\"{}\";\n",
+        name,
+        escape(label)
+    ));
+
+    for (i, part) in composition.parts.iter().enumerate() {
+        // group 0 spans the whole match, group `i + 1` the tokens of part `i`
+        let part_ids: Vec<_> = ids
+            .iter()
+            .filter(|(_, idx)| **idx == i + 1)
+            .map(|(id, _)| **id)
+            .collect();
+
+        let mut node_label = String::new();
+        if !part_ids.is_empty() {
+            let part_ids: Vec<_> = part_ids.iter().map(|x| x.to_string()).collect();
+            node_label.push_str(&format!("[{}] ", part_ids.join(",")));
+        }
+        node_label.push_str(&atom_label(&part.atom, tagger));
+        if let Some(quantifier) = quantifier_label(&part.quantifier) {
+            node_label.push_str(&format!("\n{}", quantifier));
+        }
+
+        let mut styles = Vec::new();
+        if !part.visible {
+            styles.push("dashed");
+        }
+        if let Some((start, end)) = marker {
+            if part_ids.iter().any(|id| (start..end).contains(id)) {
+                styles.push("filled");
+            }
+        }
+
+        output.push_str(&format!(
+            "    {}_{} [label=\"{}\", style=\"{}\"];\n",
+            name,
+            i,
+            escape(&node_label),
+            styles.join(",")
+        ));
+        if i > 0 {
+            output.push_str(&format!("    {}_{} -> {}_{};\n", name, i - 1, name, i));
+        }
+    }
+
+    output.push_str("  }\n");
+}
+
+impl Engine {
+    /// Renders the pattern and antipatterns of this engine as a DOT graph. The parts of the pattern
+    /// between the group IDs in `marker` are filled.
+    pub(crate) fn to_dot(
+        &self,
+        id: &str,
+        marker: Option<(usize, usize)>,
+        tagger: &Tagger,
+    ) -> String {
+        let mut output = format!("digraph \"{}\" {{\n  node [shape=box];\n", escape(id));
+
+        match self {
+            Engine::Token(engine) => {
+                write_composition(
+                    &mut output,
+                    "pattern",
+                    "pattern",
+                    &engine.composition,
+                    marker,
+                    tagger,
+                );
+                for (i, antipattern) in engine.antipatterns.iter().enumerate() {
+                    write_composition(
+                        &mut output,
+                        &format!("antipattern_{}", i),
+                        &format!("antipattern {}", i),
+                        antipattern,
+                        None,
+                        tagger,
+                    );
+                }
+            }
+            Engine::Text(regex, _) => {
+                output.push_str(&format!(
+                    "  regex [label=\"{}\"];\n",
+                    escape(&format!("/{}/", regex.as_str()))
+                ));
+            }
+        }
+
+        output.push_str("}\n");
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_quantifiers() {
+        assert_eq!(quantifier_label(&Quantifier { min: 1, max: 1 }), None);
+        assert_eq!(
+            quantifier_label(&Quantifier { min: 0, max: 1 }),
+            Some("?".to_string())
+        );
+        assert_eq!(
+            quantifier_label(&Quantifier { min: 0, max: 3 }),
+            Some("{0,3}".to_string())
+        );
+        assert_eq!(escape("a \"b\"\nc"), "a \\\"b\\\"\\nc");
+    }
+}
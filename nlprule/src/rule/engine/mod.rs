@@ -1,29 +1,86 @@
-use crate::{types::*, utils::regex::SerializeRegex};
+use super::disambiguation::POSFilter;
+use crate::{
+    types::*,
+    utils::{parallelism::MaybeParallelIterator, regex::SerializeRegex},
+};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 pub mod composition;
+mod dot;
 
-use composition::{Composition, Group, MatchGraph};
+use composition::{Composition, GraphPool, Group, MatchGraph, StepLimit};
+
+/// The number of start positions one thread scans for matches of a token pattern. Sentences with more tokens
+/// are scanned in parallel chunks, so one long sentence does not keep a rule on one thread.
+const SCAN_CHUNK_SIZE: usize = 64;
+
+/// Requires the tokens of some groups of a match to agree in features e. g. in number, see `<unify>` in the XML.
+#[derive(Serialize, Deserialize)]
+pub struct Unification {
+    /// The filters of each feature. The tokens agree if there is one filter of each feature matching all of them.
+    pub(crate) filters: Vec<Vec<POSFilter>>,
+    /// The IDs of the groups whose tokens have to agree.
+    pub(crate) ids: Vec<usize>,
+}
+
+impl Unification {
+    fn is_match(&self, graph: &MatchGraph) -> bool {
+        let tokens: Vec<_> = self
+            .ids
+            .iter()
+            .filter_map(|id| graph.by_id(*id))
+            .flat_map(|group| group.tokens(graph.tokens()))
+            .collect();
+
+        self.filters.iter().multi_cartesian_product().any(|filter| {
+            tokens
+                .iter()
+                .all(|token| POSFilter::and(&filter, &token.word))
+        })
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct TokenEngine {
     pub(crate) composition: Composition,
     pub(crate) antipatterns: Vec<Composition>,
+    pub(crate) unifications: Vec<Unification>,
 }
 
 impl TokenEngine {
-    fn get_match<'t>(&'t self, tokens: &'t [&'t Token], i: usize) -> Option<MatchGraph<'t>> {
-        if let Some(graph) = self.composition.apply(tokens, i) {
+    fn get_match<'t>(
+        &'t self,
+        tokens: &'t [&'t Token],
+        i: usize,
+        pool: &mut GraphPool,
+        limit: &mut StepLimit,
+    ) -> Option<MatchGraph<'t>> {
+        if let Some(graph) = self.composition.apply(tokens, i, pool, limit) {
+            if !self.unifications.iter().all(|x| x.is_match(&graph)) {
+                pool.recycle(graph);
+                return None;
+            }
+
             let mut blocked = false;
 
             // TODO: cache / move to outer loop
             for i in 0..tokens.len() {
                 for antipattern in &self.antipatterns {
-                    if let Some(anti_graph) = antipattern.apply(tokens, i) {
+                    // an antipattern which runs out of steps might have matched, so it blocks the match
+                    let exceeded = limit.exceeded();
+                    let anti_graph = antipattern.apply(tokens, i, pool, limit);
+                    if limit.exceeded() > exceeded {
+                        blocked = true;
+                        break;
+                    }
+
+                    if let Some(anti_graph) = anti_graph {
                         let anti_start = anti_graph.by_index(0).char_span.0;
                         let anti_end = anti_graph
                             .by_index(anti_graph.groups().len() - 1)
                             .char_span
                             .1;
+                        pool.recycle(anti_graph);
 
                         let rule_start = graph.by_index(0).char_span.0;
                         let rule_end = graph.by_index(graph.groups().len() - 1).char_span.1;
@@ -42,6 +99,7 @@ impl TokenEngine {
             if !blocked {
                 return Some(graph);
             }
+            pool.recycle(graph);
         }
 
         None
@@ -55,75 +113,146 @@ pub enum Engine {
 }
 
 impl Engine {
+    /// Whether the pattern of this engine can match with a tagset of `n_tags` tags, see [Composition::can_match_tagset].
+    pub(crate) fn can_match_tagset(&self, n_tags: usize) -> bool {
+        match self {
+            Engine::Token(engine) => engine.composition.can_match_tagset(n_tags),
+            Engine::Text(_, _) => true,
+        }
+    }
+
+    /// Gets a map from the IDs of the groups this engine produces to their index in a match graph.
+    pub(crate) fn group_ids_to_idx(&self) -> &DefaultHashMap<usize, usize> {
+        match &self {
+            Engine::Token(engine) => &engine.composition.group_ids_to_idx,
+            Engine::Text(_, id_to_idx) => id_to_idx,
+        }
+    }
+
+    /// Gets the non-overlapping matches of this engine. Matching of token patterns at one position is stopped
+    /// after `max_steps` steps, see [Composition::apply]. Also returns how often this happened.
+    /// If `max_matches` is set, scanning stops as soon as this many matches are found.
     pub fn get_matches<'t>(
         &'t self,
         tokens: &'t [&'t Token],
         start: usize,
         end: usize,
-    ) -> Vec<MatchGraph<'t>> {
+        max_steps: Option<usize>,
+        max_matches: Option<usize>,
+    ) -> (Vec<MatchGraph<'t>>, usize) {
         let mut graphs = Vec::new();
+        let mut exceeded = 0;
 
         match &self {
             Engine::Token(engine) => {
-                let mut graph_info: Vec<_> = (0..tokens.len())
-                    .into_iter()
-                    .filter_map(|i| {
-                        if let Some(graph) = engine.get_match(&tokens, i) {
-                            let start_group = graph
-                                .by_id(start)
-                                .unwrap_or_else(|| panic!("group must exist in graph: {}", start));
-                            let end_group = graph.by_id(end - 1).unwrap_or_else(|| {
-                                panic!("group must exist in graph: {}", end - 1)
-                            });
-
-                            let start = start_group.char_span.0;
-                            let end = end_group.char_span.1;
-                            Some((graph, start, end))
-                        } else {
-                            None
+                let span = |graph: &MatchGraph| {
+                    let start_group = graph
+                        .by_id(start)
+                        .unwrap_or_else(|| panic!("group must exist in graph: {}", start));
+                    let end_group = graph
+                        .by_id(end - 1)
+                        .unwrap_or_else(|| panic!("group must exist in graph: {}", end - 1));
+
+                    (start_group.char_span.0, end_group.char_span.1)
+                };
+                let mut mask = SpanMask::new(tokens[0].text.chars().count());
+
+                if let Some(max_matches) = max_matches {
+                    // matches are accepted in the order of their positions, so scanning can stop early
+                    let mut pool = GraphPool::default();
+                    let mut limit = StepLimit::new(max_steps);
+
+                    for i in 0..tokens.len() {
+                        if graphs.len() >= max_matches {
+                            break;
                         }
-                    })
-                    .collect();
+
+                        if let Some(graph) = engine.get_match(tokens, i, &mut pool, &mut limit) {
+                            if mask.insert(span(&graph)) {
+                                graphs.push(graph);
+                            } else {
+                                pool.recycle(graph);
+                            }
+                        }
+                    }
+
+                    return (graphs, limit.exceeded());
+                }
+
+                // each scan has its own pool since chunks may be scanned on different threads
+                let scan = |positions: std::ops::Range<usize>| -> (Vec<_>, usize) {
+                    let mut pool = GraphPool::default();
+                    let mut limit = StepLimit::new(max_steps);
+                    let graph_info = positions
+                        .filter_map(|i| {
+                            let graph = engine.get_match(tokens, i, &mut pool, &mut limit)?;
+                            let (start, end) = span(&graph);
+                            Some((graph, start, end))
+                        })
+                        .collect();
+                    (graph_info, limit.exceeded())
+                };
+
+                let mut graph_info = Vec::new();
+                if tokens.len() > SCAN_CHUNK_SIZE {
+                    let chunks: Vec<_> = (0..tokens.len()).step_by(SCAN_CHUNK_SIZE).collect();
+                    let outputs: Vec<_> = chunks
+                        .into_maybe_par_iter()
+                        .map(|chunk_start| {
+                            scan(chunk_start..(chunk_start + SCAN_CHUNK_SIZE).min(tokens.len()))
+                        })
+                        .collect();
+                    for (chunk_info, chunk_exceeded) in outputs {
+                        graph_info.extend(chunk_info);
+                        exceeded += chunk_exceeded;
+                    }
+                } else {
+                    let (info, scan_exceeded) = scan(0..tokens.len());
+                    graph_info = info;
+                    exceeded = scan_exceeded;
+                }
 
                 graph_info.sort_by(|(_, start, _), (_, end, _)| start.cmp(end));
-                let mut mask = vec![false; tokens[0].text.chars().count()];
 
                 for (graph, start, end) in graph_info {
-                    if mask[start..end].iter().all(|x| !x) {
+                    if mask.insert((start, end)) {
                         graphs.push(graph);
-                        mask[start..end].iter_mut().for_each(|x| *x = true);
                     }
                 }
             }
             Engine::Text(regex, id_to_idx) => {
                 // this is the entire text, NOT the text of one token
                 let text = tokens[0].text;
+                if !regex.could_match(text) {
+                    return (graphs, exceeded);
+                }
 
-                let mut byte_to_char_idx: DefaultHashMap<usize, usize> = text
-                    .char_indices()
-                    .enumerate()
-                    .map(|(ci, (bi, _))| (bi, ci))
-                    .collect();
-                byte_to_char_idx.insert(text.len(), byte_to_char_idx.len());
-
-                graphs.extend(regex.captures_iter(text).map(|captures| {
-                    let mut groups = Vec::new();
-                    for group in captures.iter_pos() {
-                        if let Some(group) = group {
-                            let start = *byte_to_char_idx.get(&group.0).unwrap();
-                            let end = *byte_to_char_idx.get(&group.1).unwrap();
-
-                            groups.push(Group::new((start, end)));
-                        } else {
-                            groups.push(Group::new((0, 0)));
-                        }
-                    }
+                let converter = SpanConverter::new(text);
+
+                graphs.extend(
+                    regex
+                        .captures_iter(text)
+                        .take(max_matches.unwrap_or(usize::MAX))
+                        .map(|captures| {
+                            let mut groups = Vec::new();
+                            for group in captures.iter_pos() {
+                                if let Some(group) = group {
+                                    let char_span = converter
+                                        .char_span(group)
+                                        .expect("regex match must be on char boundaries");
+
+                                    groups.push(Group::new(char_span));
+                                } else {
+                                    groups.push(Group::new((0, 0)));
+                                }
+                            }
 
-                    MatchGraph::new(groups, id_to_idx, tokens)
-                }));
+                            MatchGraph::new(groups, id_to_idx, tokens)
+                        }),
+                );
             }
         }
 
-        graphs
+        (graphs, exceeded)
     }
 }
@@ -1,4 +1,4 @@
-use super::engine::composition::{MatchGraph, PosMatcher};
+use super::engine::composition::{Group, MatchGraph, PosMatcher};
 use crate::types::*;
 use crate::{
     tokenizer::Tokenizer,
@@ -6,16 +6,6 @@ use crate::{
 };
 use onig::Captures;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-
-impl std::cmp::PartialEq for Suggestion {
-    fn eq(&self, other: &Suggestion) -> bool {
-        let a: HashSet<&String> = self.replacements.iter().collect();
-        let b: HashSet<&String> = other.replacements.iter().collect();
-
-        a.intersection(&b).count() > 0 && other.start == self.start && other.end == self.end
-    }
-}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Conversion {
@@ -27,6 +17,10 @@ pub enum Conversion {
 }
 
 impl Conversion {
+    pub(crate) fn is_some(&self) -> bool {
+        !matches!(self, Conversion::Nop)
+    }
+
     fn convert(&self, input: &str) -> String {
         match &self {
             Conversion::Nop => input.to_string(),
@@ -59,6 +53,15 @@ impl Example {
     }
 }
 
+/// Why a suggestion or message could not be synthesized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SynthesisFailure {
+    /// The POS replacer found no word form with the requested POS tag.
+    MissingPosForm,
+    /// The regex replacement references a group which does not exist in the regex.
+    RegexReplace,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PosReplacer {
     pub(crate) matcher: PosMatcher,
@@ -116,19 +119,40 @@ pub struct Match {
 }
 
 impl Match {
-    fn apply(&self, graph: &MatchGraph, tokenizer: &Tokenizer) -> Option<String> {
-        let text = graph
+    /// If `clip` is set, only the part of the group inside this char span is used.
+    fn apply(
+        &self,
+        graph: &MatchGraph,
+        tokenizer: &Tokenizer,
+        converter: &SpanConverter,
+        clip: Option<(usize, usize)>,
+    ) -> Result<String, SynthesisFailure> {
+        let group = graph
             .by_id(self.id)
-            .unwrap_or_else(|| panic!("group must exist in graph: {}", self.id))
-            .text(graph.tokens()[0].text);
+            .unwrap_or_else(|| panic!("group must exist in graph: {}", self.id));
+        let text = if let Some((start, end)) = clip {
+            Group::new((
+                std::cmp::max(group.char_span.0, start),
+                std::cmp::min(group.char_span.1, end),
+            ))
+            .text(graph.tokens()[0].text, converter)
+        } else {
+            group.text(graph.tokens()[0].text, converter)
+        };
 
         let mut text = if let Some(replacer) = &self.pos_replacer {
-            replacer.apply(text, tokenizer)?
+            replacer
+                .apply(text, tokenizer)
+                .ok_or(SynthesisFailure::MissingPosForm)?
         } else {
             text.to_string()
         };
 
         text = if let Some((regex, replacement)) = &self.regex_replacer {
+            if utils::max_dollar_reference(replacement) > regex.captures_len() {
+                return Err(SynthesisFailure::RegexReplace);
+            }
+
             regex.replace_all(&text, |caps: &Captures| {
                 utils::dollar_replace(replacement.to_string(), caps)
             })
@@ -137,11 +161,11 @@ impl Match {
         };
 
         // TODO: maybe return a vector here and propagate accordingly
-        Some(self.conversion.convert(&text))
+        Ok(self.conversion.convert(&text))
     }
 
     fn has_conversion(&self) -> bool {
-        !matches!(self.conversion, Conversion::Nop)
+        self.conversion.is_some()
     }
 }
 
@@ -149,42 +173,218 @@ impl Match {
 pub enum SynthesizerPart {
     Text(String),
     Match(Match),
+    /// Parts inside a `<marker>`. Matches in a marker only insert the part of their group which is inside the marked span of the rule.
+    Marker(Vec<SynthesizerPart>),
+    /// Parts of a suggestion in a message whose case is converted as a whole, see [Synthesizer::conversion].
+    Converted(Conversion, Vec<SynthesizerPart>),
+    /// Whitespace which only separates the parts, e. g. a line break from the formatting of the XML.
+    /// Inserts one space unless the output already ends with whitespace, see [push_part].
+    Separator(String),
+}
+
+/// Appends a part to the output. Whitespace is collapsed at the boundary of the parts only: the leading whitespace
+/// of the part is dropped if the output ends with whitespace, so e. g. an empty match between two spaces does not
+/// leave a double space. Whitespace inside the parts (e. g. a non-breaking space in a suggestion) is kept as is.
+fn push_part(output: &mut Vec<MessagePart>, part: MessagePart) {
+    let ends_with_whitespace = output
+        .iter()
+        .rev()
+        .map(|x| x.text())
+        .find(|x| !x.is_empty())
+        .and_then(|x| x.chars().last())
+        .map(char::is_whitespace);
+
+    let part = match (ends_with_whitespace, part) {
+        (Some(true), MessagePart::Literal(text)) => {
+            MessagePart::Literal(text.trim_start().to_string())
+        }
+        (Some(true), MessagePart::Match(text)) => MessagePart::Match(text.trim_start().to_string()),
+        (_, part) => part,
+    };
+    output.push(part);
+}
+
+impl SynthesizerPart {
+    fn starts_with_conversion(parts: &[SynthesizerPart]) -> bool {
+        match parts {
+            [SynthesizerPart::Match(m), ..] => m.has_conversion(),
+            [SynthesizerPart::Marker(parts), ..] => SynthesizerPart::starts_with_conversion(parts),
+            [SynthesizerPart::Converted(..), ..] => true,
+            _ => false,
+        }
+    }
+
+    fn match_ids(parts: &[SynthesizerPart], ids: &mut Vec<usize>) {
+        for part in parts {
+            match part {
+                SynthesizerPart::Text(_) | SynthesizerPart::Separator(_) => {}
+                SynthesizerPart::Match(m) => ids.push(m.id),
+                SynthesizerPart::Marker(parts) | SynthesizerPart::Converted(_, parts) => {
+                    SynthesizerPart::match_ids(parts, ids)
+                }
+            }
+        }
+    }
+
+    fn apply(
+        parts: &[SynthesizerPart],
+        graph: &MatchGraph,
+        tokenizer: &Tokenizer,
+        converter: &SpanConverter,
+        clip: Option<(usize, usize)>,
+        marker_span: (usize, usize),
+        output: &mut Vec<MessagePart>,
+    ) -> Result<(), SynthesisFailure> {
+        for part in parts {
+            match part {
+                SynthesizerPart::Text(t) => push_part(output, MessagePart::Literal(t.clone())),
+                SynthesizerPart::Match(m) => {
+                    push_part(
+                        output,
+                        MessagePart::Match(m.apply(graph, tokenizer, converter, clip)?),
+                    );
+                }
+                SynthesizerPart::Separator(_) => {
+                    push_part(output, MessagePart::Literal(" ".to_string()))
+                }
+                SynthesizerPart::Marker(parts) => {
+                    SynthesizerPart::apply(
+                        parts,
+                        graph,
+                        tokenizer,
+                        converter,
+                        Some(marker_span),
+                        marker_span,
+                        output,
+                    )?;
+                }
+                SynthesizerPart::Converted(conversion, parts) => {
+                    let mut converted = Vec::new();
+                    SynthesizerPart::apply(
+                        parts,
+                        graph,
+                        tokenizer,
+                        converter,
+                        clip,
+                        marker_span,
+                        &mut converted,
+                    )?;
+
+                    // same as converting the joined text, `start*` conversions only change the first non-empty part
+                    let converts_all =
+                        matches!(conversion, Conversion::AllLower | Conversion::AllUpper);
+                    let mut first = true;
+                    for part in converted {
+                        let part = match part {
+                            _ if part.text().is_empty() || !(first || converts_all) => part,
+                            MessagePart::Literal(text) => {
+                                MessagePart::Literal(conversion.convert(&text))
+                            }
+                            MessagePart::Match(text) => {
+                                MessagePart::Match(conversion.convert(&text))
+                            }
+                        };
+                        first &= part.text().is_empty();
+                        push_part(output, part);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Synthesizer {
     pub(crate) use_titlecase_adjust: bool,
     pub(crate) parts: Vec<SynthesizerPart>,
+    /// Conversion applied to the whole output after joining the parts.
+    pub(crate) conversion: Conversion,
 }
 
 impl Synthesizer {
+    /// Gets the IDs of all groups referenced by matches in this synthesizer.
+    pub(crate) fn match_ids(&self) -> Vec<usize> {
+        let mut ids = Vec::new();
+        SynthesizerPart::match_ids(&self.parts, &mut ids);
+        ids
+    }
+
+    /// Synthesizes the output for a match. If `fragment` is set, the output is not made title case because it is
+    /// at the start of the text, see [RulesOptions::fragment][crate::rules::RulesOptions::fragment].
     pub fn apply(
         &self,
         graph: &MatchGraph,
         tokenizer: &Tokenizer,
+        converter: &SpanConverter,
         start: usize,
-        _end: usize,
-    ) -> Option<String> {
+        end: usize,
+        fragment: bool,
+    ) -> Result<String, SynthesisFailure> {
+        self.synthesize(graph, tokenizer, converter, start, end, fragment)
+            .map(|(output, _)| output)
+    }
+
+    /// Like [Synthesizer::apply], but also returns the output split into literal text and text from matches.
+    /// The parts are joined to the same string as returned by [Synthesizer::apply]. If they can not be aligned
+    /// with it (e. g. because of a case conversion of the whole output), the output is returned as one literal part.
+    pub fn apply_parts(
+        &self,
+        graph: &MatchGraph,
+        tokenizer: &Tokenizer,
+        converter: &SpanConverter,
+        start: usize,
+        end: usize,
+        fragment: bool,
+    ) -> Result<(String, Vec<MessagePart>), SynthesisFailure> {
+        let (output, raw_parts) =
+            self.synthesize(graph, tokenizer, converter, start, end, fragment)?;
+        let parts = align_parts(&output, raw_parts);
+        Ok((output, parts))
+    }
+
+    fn synthesize(
+        &self,
+        graph: &MatchGraph,
+        tokenizer: &Tokenizer,
+        converter: &SpanConverter,
+        start: usize,
+        end: usize,
+        fragment: bool,
+    ) -> Result<(String, Vec<MessagePart>), SynthesisFailure> {
         let mut output = Vec::new();
 
-        let starts_with_conversion = match &self.parts[..] {
-            [SynthesizerPart::Match(m), ..] => m.has_conversion(),
-            _ => false,
-        };
+        let starts_with_conversion =
+            self.conversion.is_some() || SynthesizerPart::starts_with_conversion(&self.parts);
+        let marker_span = (
+            graph
+                .by_id(start)
+                .unwrap_or_else(|| panic!("group must exist in graph: {}", start))
+                .char_span
+                .0,
+            graph
+                .by_id(end - 1)
+                .unwrap_or_else(|| panic!("group must exist in graph: {}", end - 1))
+                .char_span
+                .1,
+        );
 
-        for part in &self.parts {
-            match part {
-                SynthesizerPart::Text(t) => output.push(t.clone()),
-                SynthesizerPart::Match(m) => {
-                    output.push(m.apply(graph, tokenizer)?);
-                }
-            }
-        }
+        SynthesizerPart::apply(
+            &self.parts,
+            graph,
+            tokenizer,
+            converter,
+            None,
+            marker_span,
+            &mut output,
+        )?;
 
-        let suggestion = utils::normalize_whitespace(&output.join(""));
+        let joined: String = output.iter().map(|x| x.text()).collect();
+        let suggestion = self.conversion.convert(&joined);
 
         // if the suggestion does not start with a case conversion match, make it title case if:
-        // * at sentence start
+        // * at sentence start (unless checking a fragment)
         // * the replaced text is title case
         let make_uppercase = !starts_with_conversion
             && graph.groups()[graph.get_index(start).unwrap()..]
@@ -201,16 +401,152 @@ impl Synthesizer {
                             .next()
                             .expect("token must have at least one char")
                             .is_uppercase())
-                        || first_token.byte_span.0 == 0
+                        || (!fragment && first_token.byte_span.0 == 0)
                 })
                 .unwrap_or(false);
 
         if make_uppercase {
-            Some(utils::apply_to_first(&suggestion, |x| {
-                x.to_uppercase().collect()
-            }))
+            Ok((
+                utils::apply_to_first(&suggestion, |x| x.to_uppercase().collect()),
+                output,
+            ))
         } else {
-            Some(suggestion)
+            Ok((suggestion, output))
+        }
+    }
+}
+
+/// Aligns the raw parts of a synthesized output with the final output, see [Synthesizer::apply_parts].
+fn align_parts(output: &str, raw_parts: Vec<MessagePart>) -> Vec<MessagePart> {
+    // the whitespace is already collapsed at the boundaries of the parts, see [push_part]
+    let mut parts: Vec<MessagePart> = Vec::new();
+    for part in raw_parts {
+        let text = part.text().to_string();
+
+        match (parts.last_mut(), part) {
+            _ if text.is_empty() => {}
+            (Some(MessagePart::Literal(prev)), MessagePart::Literal(_)) => prev.push_str(&text),
+            (_, MessagePart::Literal(_)) => parts.push(MessagePart::Literal(text)),
+            (_, MessagePart::Match(_)) => parts.push(MessagePart::Match(text)),
         }
     }
+
+    // the first character is possibly made uppercase
+    if let Some(MessagePart::Literal(text)) | Some(MessagePart::Match(text)) = parts.first_mut() {
+        if !output.starts_with(text.as_str()) {
+            *text = utils::apply_to_first(text, |x| x.to_uppercase().collect());
+        }
+    }
+
+    let joined: String = parts.iter().map(|x| x.text()).collect();
+    if joined != output {
+        parts = vec![MessagePart::Literal(output.to_string())];
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_message_parts() {
+        let literal = |x: &str| MessagePart::Literal(x.to_string());
+        let matched = |x: &str| MessagePart::Match(x.to_string());
+
+        assert_eq!(
+            align_parts(
+                "Did you mean \"has\"?",
+                vec![
+                    literal("Did you mean "),
+                    literal("\""),
+                    matched("has"),
+                    literal("\"?")
+                ]
+            ),
+            vec![literal("Did you mean \""), matched("has"), literal("\"?")]
+        );
+        assert_eq!(
+            align_parts(
+                "Use has.",
+                vec![literal("Use "), matched("has"), literal(".")]
+            ),
+            vec![literal("Use "), matched("has"), literal(".")]
+        );
+        assert_eq!(
+            align_parts("Was it?", vec![matched("was"), literal(" it?")]),
+            vec![matched("Was"), literal(" it?")]
+        );
+        assert_eq!(
+            align_parts("WAS IT?", vec![matched("was"), literal(" it?")]),
+            vec![literal("WAS IT?")]
+        );
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn clips_matches_in_markers() {
+        use crate::compile::testing;
+        use crate::rules::RulesOptions;
+
+        let tokenizer = testing::tokenizer(&[]);
+        // group 1 is "in teh", the marked span is group 2 "teh"
+        let xml = r#"<rule id="TEH" name="teh">
+            <regexp mark="2">(in (teh))</regexp>
+            <message>Did you mean "<marker><match no="1" regexp_match="teh" regexp_replace="the"/></marker>" in "<match no="1"/>"?</message>
+            <suggestion><marker><match no="1" regexp_match="teh" regexp_replace="the"/></marker></suggestion>
+            <example correction="the">Sit in <marker>teh</marker> car.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let suggestions = rules.suggest("Sit in teh car.", &tokenizer);
+        assert_eq!(
+            suggestions
+                .iter()
+                .map(|x| (x.start, x.end, x.message.as_str(), x.replacements.clone()))
+                .collect::<Vec<_>>(),
+            vec![(
+                7,
+                10,
+                "Did you mean \"the\" in \"in teh\"?",
+                vec!["the".to_string()]
+            )]
+        );
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn converts_case_of_whole_suggestions() {
+        use crate::compile::testing;
+        use crate::rules::RulesOptions;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="NASA" name="nasa">
+            <pattern><token>nasa</token><token>rocket</token></pattern>
+            <message>Did you mean <suggestion case_conversion="allupper"><match no="1"/> <match no="2"/></suggestion>?</message>
+            <example correction="NASA ROCKET">A <marker>nasa rocket</marker>.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let suggestions = rules.suggest("A nasa rocket.", &tokenizer);
+        assert_eq!(suggestions[0].replacements, vec!["NASA ROCKET"]);
+        assert_eq!(suggestions[0].message, "Did you mean NASA ROCKET?");
+    }
+
+    #[test]
+    fn collapses_whitespace_at_part_boundaries() {
+        let mut output = Vec::new();
+        push_part(
+            &mut output,
+            MessagePart::Literal("a\u{a0}b  c ".to_string()),
+        );
+        push_part(&mut output, MessagePart::Match(String::new()));
+        push_part(&mut output, MessagePart::Literal(" ".to_string()));
+        push_part(&mut output, MessagePart::Match(" d".to_string()));
+        push_part(&mut output, MessagePart::Literal(" ".to_string()));
+
+        let joined: String = output.iter().map(|x| x.text()).collect();
+        assert_eq!(joined, "a\u{a0}b  c d ");
+    }
 }
@@ -2,20 +2,31 @@
 
 use crate::types::*;
 use crate::{
+    events::{self, RuleEvent},
     filter::{Filter, Filterable},
+    rules::{Limits, RulesOptions},
     tokenizer::{finalize, Tokenizer},
+    trace::{Decision, Trace, TraceEntry},
     utils,
 };
-use log::{error, info, warn};
+use log::info;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    hash::Hasher,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 pub(crate) mod disambiguation;
 pub(crate) mod engine;
 pub(crate) mod grammar;
 
 use engine::Engine;
+use grammar::SynthesisFailure;
 
+pub use disambiguation::{
+    DisambiguationChange, DisambiguationExample, DisambiguationKind, POSFilter,
+};
 pub(crate) use engine::composition::MatchGraph;
 pub use grammar::Example;
 
@@ -46,6 +57,7 @@ pub struct DisambiguationRule {
     pub(crate) start: usize,
     pub(crate) end: usize,
     pub(crate) examples: Vec<disambiguation::DisambiguationExample>,
+    pub(crate) source_location: Option<SourceLocation>,
 }
 
 #[derive(Default)]
@@ -57,13 +69,54 @@ impl Changes {
     }
 }
 
+/// Formats a rule ID together with its location in the XML (if known) for messages.
+pub(crate) fn located(id: &str, source_location: &Option<SourceLocation>) -> String {
+    match source_location {
+        Some(location) => format!("{} ({})", id, location),
+        None => id.to_string(),
+    }
+}
+
 impl DisambiguationRule {
     /// Get a unique identifier of this rule.
     pub fn id(&self) -> &str {
         self.id.as_str()
     }
 
+    /// Gets where this rule is defined in the XML. `None` if the location was stripped from the binary.
+    pub fn source_location(&self) -> Option<&SourceLocation> {
+        self.source_location.as_ref()
+    }
+
+    /// Renders how this rule was compiled as a [Graphviz DOT](https://graphviz.org/) graph: the parts of the pattern
+    /// with their group IDs and quantifiers, and the antipatterns. Skipped tokens are dashed, the marked parts filled.
+    /// `tokenizer` must be the tokenizer the rule was compiled for, it is used to list the tags a POS matcher matches.
+    pub fn to_dot(&self, tokenizer: &Tokenizer) -> String {
+        self.engine
+            .to_dot(&self.id, Some((self.start, self.end)), tokenizer.tagger())
+    }
+
+    /// Gets what this rule does to the tokens it matches.
+    pub fn kind(&self) -> DisambiguationKind {
+        self.disambiguations.kind()
+    }
+
+    /// Gets the examples associated with this rule. They are run by [DisambiguationRule::test].
+    pub fn examples(&self) -> &[DisambiguationExample] {
+        &self.examples
+    }
+
     pub(crate) fn apply<'t>(&'t self, tokens: &[Token<'t>], tokenizer: &Tokenizer) -> Changes {
+        self.apply_with_trace(tokens, tokenizer, None)
+    }
+
+    /// Same as `apply` but additionally records the matches (and optionally the filter decisions) in the trace.
+    pub(crate) fn apply_with_trace<'t>(
+        &'t self,
+        tokens: &[Token<'t>],
+        tokenizer: &Tokenizer,
+        mut trace: Option<&mut Trace>,
+    ) -> Changes {
         if matches!(self.disambiguations, disambiguation::Disambiguation::Nop) {
             return Changes::default();
         }
@@ -72,13 +125,40 @@ impl DisambiguationRule {
 
         let mut all_byte_spans = Vec::new();
 
-        for graph in self.engine.get_matches(&refs, self.start, self.end) {
-            if let Some(filter) = &self.filter {
-                if !filter.keep(&graph, tokenizer) {
-                    continue;
+        for graph in self
+            .engine
+            .get_matches(&refs, self.start, self.end, None, None)
+            .0
+        {
+            let kept = match &self.filter {
+                Some(filter) => filter.keep(&graph, tokenizer),
+                None => true,
+            };
+
+            if let Some(trace) = trace.as_mut() {
+                if kept || trace.records_filtered() {
+                    let decision = match (&self.filter, kept) {
+                        (Some(filter), false) => Decision::Filtered {
+                            filter: filter.name().to_string(),
+                            reason: filter.reason(&graph, tokenizer),
+                        },
+                        _ => Decision::Kept,
+                    };
+
+                    trace.push(TraceEntry {
+                        rule_id: self.id.clone(),
+                        source_location: self.source_location.clone(),
+                        start: graph.by_id(self.start).map_or(0, |x| x.char_span.0),
+                        end: graph.by_id(self.end - 1).map_or(0, |x| x.char_span.1),
+                        decision,
+                    });
                 }
             }
 
+            if !kept {
+                continue;
+            }
+
             let mut byte_spans = Vec::new();
 
             for group_idx in self.start..self.end {
@@ -107,7 +187,7 @@ impl DisambiguationRule {
         tokenizer: &Tokenizer,
         changes: Changes,
     ) {
-        log::info!("applying {}", self.id);
+        events::emit(RuleEvent::DisambiguationApplied { rule_id: &self.id });
 
         for byte_spans in changes.0 {
             let mut groups = Vec::new();
@@ -188,23 +268,22 @@ impl DisambiguationRule {
             };
 
             if !pass {
-                let error_str = format!(
+                let message = format!(
                     "Rule {}: Test \"{:#?}\" failed. Before: {:#?}. After: {:#?}.",
-                    self.id,
+                    located(&self.id, &self.source_location),
                     test,
                     tokens_before.into_iter().collect::<Vec<_>>(),
                     tokens_after.into_iter().collect::<Vec<_>>(),
                 );
 
-                if tokenizer
-                    .options()
-                    .known_failures
-                    .contains(&format!("{}:{}", self.id, i))
-                {
-                    warn!("{}", error_str)
-                } else {
-                    error!("{}", error_str)
-                }
+                events::emit(RuleEvent::TestFailed {
+                    rule_id: &self.id,
+                    known: tokenizer
+                        .options()
+                        .known_failures
+                        .contains(&format!("{}:{}", self.id, i)),
+                    message,
+                });
             }
 
             passes.push(pass);
@@ -214,6 +293,70 @@ impl DisambiguationRule {
     }
 }
 
+/// The result of applying a [Rule] once.
+#[derive(Debug, Default)]
+pub(crate) struct RuleOutput {
+    pub(crate) suggestions: Vec<Suggestion>,
+    /// Whether matches were skipped because of the limits.
+    pub(crate) truncated: bool,
+    /// How many suggestions and messages failed to synthesize.
+    pub(crate) synthesis_failures: usize,
+    /// At how many positions matching was stopped by the step limit.
+    pub(crate) step_limit_exceeded: usize,
+}
+
+/// Counts of synthesis failures of a [Rule] by reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SynthesisFailures {
+    /// How often a POS replacer found no word form with the requested POS tag.
+    pub missing_pos_form: usize,
+    /// How often a regex replacement referenced a group which does not exist.
+    pub regex_replace: usize,
+}
+
+impl SynthesisFailures {
+    /// The total number of failures.
+    pub fn total(&self) -> usize {
+        self.missing_pos_form + self.regex_replace
+    }
+}
+
+/// Statistics collected while applying a rule.
+/// Atomic so rules can be applied in parallel.
+#[derive(Debug, Default)]
+pub(crate) struct RuleStats {
+    missing_pos_form: AtomicUsize,
+    regex_replace: AtomicUsize,
+    step_limit_exceeded: AtomicUsize,
+}
+
+impl RuleStats {
+    fn record(&self, failure: SynthesisFailure) {
+        let counter = match failure {
+            SynthesisFailure::MissingPosForm => &self.missing_pos_form,
+            SynthesisFailure::RegexReplace => &self.regex_replace,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn synthesis_failures(&self) -> SynthesisFailures {
+        SynthesisFailures {
+            missing_pos_form: self.missing_pos_form.load(Ordering::Relaxed),
+            regex_replace: self.regex_replace.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record_step_limit_exceeded(&self, count: usize) {
+        self.step_limit_exceeded.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.missing_pos_form.store(0, Ordering::Relaxed);
+        self.regex_replace.store(0, Ordering::Relaxed);
+        self.step_limit_exceeded.store(0, Ordering::Relaxed);
+    }
+}
+
 /// A grammar rule.
 /// Returns a [Suggestion][crate::types::Suggestion] for change if it matches.
 /// Sourced from LanguageTool. An example of how a simple rule might look in the original XML format:
@@ -236,15 +379,24 @@ pub struct Rule {
     pub(crate) examples: Vec<Example>,
     pub(crate) suggesters: Vec<grammar::Synthesizer>,
     pub(crate) message: grammar::Synthesizer,
+    pub(crate) localized_messages: DefaultHashMap<String, grammar::Synthesizer>,
     pub(crate) start: usize,
     pub(crate) end: usize,
     pub(crate) on: bool,
+    pub(crate) default_on: bool,
+    pub(crate) temp_off: bool,
+    pub(crate) priority: i32,
     pub(crate) url: Option<String>,
     pub(crate) short: Option<String>,
     pub(crate) name: String,
     pub(crate) category_id: String,
     pub(crate) category_name: String,
     pub(crate) category_type: Option<String>,
+    pub(crate) source_hash: u64,
+    pub(crate) safe: bool,
+    pub(crate) source_location: Option<SourceLocation>,
+    #[serde(skip)]
+    pub(crate) stats: RuleStats,
 }
 
 impl Rule {
@@ -254,10 +406,26 @@ impl Rule {
     }
 
     /// Get whether this rule is "turned on" i. e. whether it should be used by the rule set.
+    /// This is the current state which may have been changed at runtime, see [Rule::default_on].
     pub fn on(&self) -> bool {
         self.on
     }
 
+    /// Gets whether this rule is on in the rule set as it was built, i. e. according to the XML.
+    pub fn default_on(&self) -> bool {
+        self.default_on
+    }
+
+    /// Gets whether this rule was turned on or off at runtime, so it is not in its [default state][Rule::default_on].
+    pub fn modified(&self) -> bool {
+        self.on != self.default_on
+    }
+
+    /// Turns this rule on or off according to its [default state][Rule::default_on].
+    pub fn reset_to_default(&mut self) {
+        self.on = self.default_on;
+    }
+
     /// Gets a short text describing this rule e.g. "Possible typo" if there is one.
     pub fn short(&self) -> Option<&str> {
         self.short.as_deref()
@@ -268,16 +436,119 @@ impl Rule {
         self.url.as_deref()
     }
 
+    /// Gets a hash of the content of this rule i. e. the XML it was compiled from and its attributes.
+    /// The hash is the same across Rust versions and platforms, so rule sets of different builds can be compared.
+    pub fn content_hash(&self) -> u64 {
+        let content = (
+            self.source_hash,
+            &self.id,
+            &self.name,
+            self.default_on,
+            self.temp_off,
+            self.priority,
+            &self.category_id,
+        );
+        let mut hasher = utils::StableHasher::default();
+        hasher.write(&bincode::serialize(&content).expect("rule content must be serializable"));
+        hasher.finish()
+    }
+
+    /// Gets the locales this rule has a translated message for.
+    pub fn message_locales(&self) -> impl Iterator<Item = &str> {
+        self.localized_messages.keys().map(|x| x.as_str())
+    }
+
+    /// Gets how often the suggestions or the message of this rule failed to synthesize since the rule was loaded.
+    /// A failed suggestion is silently dropped so this helps spotting broken suggesters.
+    pub fn synthesis_failures(&self) -> SynthesisFailures {
+        self.stats.synthesis_failures()
+    }
+
+    /// Gets how often matching this rule at one position was stopped by [Limits::max_match_steps] since the rule
+    /// was loaded. Rules with a high count have patterns which are expensive to match.
+    pub fn step_limit_exceeded(&self) -> usize {
+        self.stats.step_limit_exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Resets the counts returned by [Rule::synthesis_failures] and [Rule::step_limit_exceeded].
+    pub fn reset_synthesis_failures(&self) {
+        self.stats.reset();
+    }
+
     /// Gets the examples associated with this rule.
     pub fn examples(&self) -> &[Example] {
         &self.examples
     }
 
-    /// Turn this rule on.
+    /// Gets whether this rule is marked as temporarily disabled (`default="temp_off"`) in LanguageTool, e. g. while it is
+    /// being worked on. Such rules are off by default but can be turned on with [Rule::set_on].
+    pub fn temp_off(&self) -> bool {
+        self.temp_off
+    }
+
+    /// Gets the priority of this rule from the `priority` attribute in the XML (of the rule, its group or its
+    /// category), 0 if there is none. Of overlapping suggestions, the ones of rules with a higher priority win,
+    /// see [Rules::set_priority][crate::Rules::set_priority].
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Turn this rule on or off. Does not change the [default state][Rule::default_on].
     pub fn set_on(&mut self, on: bool) {
         self.on = on;
     }
 
+    /// Gets whether the suggestions of this rule are safe to apply without review, see [Rules::autocorrect][crate::Rules::autocorrect].
+    pub fn safe(&self) -> bool {
+        self.safe
+    }
+
+    /// Sets whether the suggestions of this rule are safe to apply without review.
+    pub fn set_safe(&mut self, safe: bool) {
+        self.safe = safe;
+    }
+
+    /// Gets where this rule is defined in the XML. `None` if the location was stripped from the binary.
+    pub fn source_location(&self) -> Option<&SourceLocation> {
+        self.source_location.as_ref()
+    }
+
+    /// Renders how this rule was compiled as a [Graphviz DOT](https://graphviz.org/) graph: the parts of the pattern
+    /// with their group IDs and quantifiers, and the antipatterns. Skipped tokens are dashed, the marked parts filled.
+    /// `tokenizer` must be the tokenizer the rule was compiled for, it is used to list the tags a POS matcher matches.
+    pub fn to_dot(&self, tokenizer: &Tokenizer) -> String {
+        self.engine
+            .to_dot(&self.id, Some((self.start, self.end)), tokenizer.tagger())
+    }
+
+    /// Gets the IDs of groups this rule refers to (by `start` / `end` or in a match) which are not produced by its engine.
+    /// Applying a rule with missing groups would panic, so this must be empty for every rule in a valid rule set.
+    pub(crate) fn missing_groups(&self) -> Vec<usize> {
+        let group_ids = self.engine.group_ids_to_idx();
+
+        let mut ids = vec![self.start];
+        ids.extend(self.end.checked_sub(1));
+        for synthesizer in self
+            .suggesters
+            .iter()
+            .chain(std::iter::once(&self.message))
+            .chain(self.localized_messages.values())
+        {
+            ids.extend(synthesizer.match_ids());
+        }
+
+        let mut missing: Vec<_> = ids
+            .into_iter()
+            .filter(|id| !group_ids.contains_key(id))
+            .collect();
+        if self.end <= self.start && !missing.contains(&self.start) {
+            missing.push(self.start);
+        }
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
     /// Gets a human-readable name of this rule.
     pub fn name(&self) -> &str {
         &self.name
@@ -298,11 +569,59 @@ impl Rule {
         self.category_type.as_deref()
     }
 
-    pub(crate) fn apply(&self, tokens: &[Token], tokenizer: &Tokenizer) -> Vec<Suggestion> {
+    /// Gets whether this rule is in the category with the given ID or of the given type.
+    pub fn in_category(&self, category: &str) -> bool {
+        self.category_id == category || self.category_type() == Some(category)
+    }
+
+    pub(crate) fn apply(
+        &self,
+        tokens: &[Token],
+        tokenizer: &Tokenizer,
+        options: &RulesOptions,
+    ) -> Vec<Suggestion> {
+        let converter = SpanConverter::new(tokens[0].text);
+        self.apply_limited(tokens, tokenizer, &converter, options, &Limits::default())
+            .suggestions
+    }
+
+    /// Same as `apply` but only computes suggestions for the first [max_matches_per_rule][Limits::max_matches_per_rule]
+    /// matches and stops matching after [max_match_steps][Limits::max_match_steps].
+    /// Additionally returns whether matches were skipped. `converter` must be built from the text of the sentence.
+    pub(crate) fn apply_limited(
+        &self,
+        tokens: &[Token],
+        tokenizer: &Tokenizer,
+        converter: &SpanConverter,
+        options: &RulesOptions,
+        limits: &Limits,
+    ) -> RuleOutput {
         let refs: Vec<&Token> = tokens.iter().collect();
         let mut suggestions = Vec::new();
+        let mut synthesis_failures = 0;
+        // sentence case headings are checked like sentences
+        let title_case_heading = options.heading && utils::is_title_case_heading(tokens[0].text);
+
+        // one more match than the limit tells whether matches were skipped
+        let (graphs, exceeded) = self.engine.get_matches(
+            &refs,
+            self.start,
+            self.end,
+            limits.max_match_steps,
+            limits.max_matches_per_rule.map(|x| x.saturating_add(1)),
+        );
+        if exceeded > 0 {
+            self.stats.record_step_limit_exceeded(exceeded);
+            events::emit(RuleEvent::StepLimitExceeded {
+                rule_id: &self.id,
+                count: exceeded,
+            });
+        }
+
+        let max_matches = limits.max_matches_per_rule.unwrap_or(usize::MAX);
+        let truncated = graphs.len() > max_matches || exceeded > 0;
 
-        for graph in self.engine.get_matches(&refs, self.start, self.end) {
+        for graph in graphs.into_iter().take(max_matches) {
             let start_group = graph
                 .by_id(self.start)
                 .unwrap_or_else(|| panic!("{} group must exist in graph: {}", self.id, self.start));
@@ -310,15 +629,33 @@ impl Rule {
                 panic!("{} group must exist in graph: {}", self.id, self.end - 1)
             });
 
-            let replacements: Vec<String> = self
-                .suggesters
-                .iter()
-                .filter_map(|x| x.apply(&graph, tokenizer, self.start, self.end))
-                .collect();
+            let mut replacements: Vec<String> = Vec::new();
+            let mut failed = false;
+
+            for suggester in &self.suggesters {
+                match suggester.apply(
+                    &graph,
+                    tokenizer,
+                    converter,
+                    self.start,
+                    self.end,
+                    options.fragment,
+                ) {
+                    Ok(replacement) => replacements.push(replacement),
+                    Err(failure) => {
+                        self.stats.record(failure);
+                        events::emit(RuleEvent::SynthesisFailed { rule_id: &self.id });
+                        synthesis_failures += 1;
+                        failed = true;
+                    }
+                }
+            }
 
-            let start = if replacements
-                .iter()
-                .all(|x| utils::no_space_chars().chars().any(|c| x.starts_with(c)))
+            // a message-only suggestion has no replacements and keeps the span of the match
+            let mut start = if !replacements.is_empty()
+                && replacements
+                    .iter()
+                    .all(|x| utils::no_space_chars().chars().any(|c| x.starts_with(c)))
             {
                 let first_token = graph.groups()[graph.get_index(self.start).unwrap()..]
                     .iter()
@@ -339,29 +676,141 @@ impl Rule {
             } else {
                 start_group.char_span.0
             };
-            let end = end_group.char_span.1;
+            let mut end = end_group.char_span.1;
+
+            let (byte_start, byte_end) = converter.byte_span((start, end));
+            let original = &tokens[0].text[byte_start..byte_end];
 
-            // fix e. g. "Super , dass"
-            let replacements: Vec<String> = replacements
+            // fix e. g. "Super , dass", then remove duplicates and candidates which would not change the text
+            let mut replacements: Vec<String> = replacements
                 .into_iter()
                 .map(|x| utils::fix_nospace_chars(&x))
-                .collect();
+                .map(|x| {
+                    // in a title case heading, every word of the replacement is capitalized, not only the first
+                    if title_case_heading {
+                        utils::title_case_words(&x)
+                    } else {
+                        x
+                    }
+                })
+                .filter(|x| options.keep_noop_suggestions || x != original)
+                .fold(Vec::new(), |mut acc, x| {
+                    if !acc.contains(&x) {
+                        acc.push(x);
+                    }
+                    acc
+                });
+
+            if let Some(ranking) = &options.ranking {
+                ranking.rank(original, &mut replacements, tokenizer);
+            }
+
+            if options.trim_suggestions {
+                let (prefix, suffix) = utils::common_word_affixes(original, &replacements);
+                start += prefix;
+                end -= suffix;
+
+                for replacement in replacements.iter_mut() {
+                    let n_chars = replacement.chars().count();
+                    *replacement = replacement
+                        .chars()
+                        .skip(prefix)
+                        .take(n_chars - prefix - suffix)
+                        .collect();
+                }
+            }
+
+            let message_only = replacements.is_empty() && failed && options.message_only_fallback;
+
+            if !replacements.is_empty() || message_only {
+                let message = options
+                    .message_locale
+                    .as_ref()
+                    .and_then(|locale| self.localized_messages.get(locale))
+                    .unwrap_or(&self.message);
+
+                // the message can fail to synthesize if it contains a suggestion which failed
+                let (message, message_parts) = message
+                    .apply_parts(
+                        &graph,
+                        tokenizer,
+                        converter,
+                        self.start,
+                        self.end,
+                        options.fragment,
+                    )
+                    .unwrap_or_else(|failure| {
+                        self.stats.record(failure);
+                        events::emit(RuleEvent::SynthesisFailed { rule_id: &self.id });
+                        synthesis_failures += 1;
+                        let message = self.short.clone().unwrap_or_else(|| self.name.clone());
+                        (message.clone(), vec![MessagePart::Literal(message)])
+                    });
+
+                let groups = if options.include_groups {
+                    self.matched_groups(&graph, tokens[0].text, converter)
+                } else {
+                    Vec::new()
+                };
 
-            if !replacements.is_empty() {
                 suggestions.push(Suggestion {
-                    message: self
-                        .message
-                        .apply(&graph, tokenizer, self.start, self.end)
-                        .expect("Rules must have a message."),
+                    message,
+                    message_parts,
+                    short_message: self.short.clone(),
                     source: self.id.to_string(),
                     start,
                     end,
+                    byte_span: converter.byte_span((start, end)),
                     replacements,
+                    sentence_span: (0, converter.char_len()),
+                    severity: options.severity(
+                        std::iter::once(self.category_id.as_str())
+                            .chain(self.category_type.as_deref()),
+                    ),
+                    groups,
+                    ..Default::default()
                 });
             }
         }
 
-        suggestions
+        RuleOutput {
+            suggestions,
+            truncated,
+            synthesis_failures,
+            step_limit_exceeded: exceeded,
+        }
+    }
+
+    /// Gets an owned snapshot of the groups of a match, see [Suggestion::groups].
+    fn matched_groups(
+        &self,
+        graph: &MatchGraph,
+        text: &str,
+        converter: &SpanConverter,
+    ) -> Vec<MatchedGroup> {
+        let replacement_ids: DefaultHashSet<_> =
+            self.suggesters.iter().flat_map(|x| x.match_ids()).collect();
+        let mut ids: Vec<_> = self.engine.group_ids_to_idx().keys().copied().collect();
+        ids.sort_unstable();
+
+        ids.into_iter()
+            .filter_map(|id| {
+                let group = graph.by_id(id)?;
+
+                Some(MatchedGroup {
+                    id,
+                    char_span: group.char_span,
+                    text: group.text(text, converter).to_string(),
+                    tokens: group
+                        .tokens(graph.tokens())
+                        .iter()
+                        .map(|x| x.word.text.as_ref().to_string())
+                        .collect(),
+                    marked: id >= self.start && id < self.end,
+                    in_replacement: replacement_ids.contains(&id),
+                })
+            })
+            .collect()
     }
 
     /// Grammar rules always have at least one example associated with them.
@@ -372,27 +821,33 @@ impl Rule {
         for test in self.examples.iter() {
             let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(&test.text())));
             info!("Tokens: {:#?}", tokens);
-            let suggestions = self.apply(&tokens, tokenizer);
+            let suggestions = self.apply(&tokens, tokenizer, &RulesOptions::default());
 
             let pass = if suggestions.len() > 1 {
                 false
             } else {
                 match test.suggestion() {
                     Some(correct_suggestion) => {
-                        suggestions.len() == 1 && correct_suggestion == &suggestions[0]
+                        suggestions.len() == 1
+                            && correct_suggestion.same_correction(&suggestions[0])
                     }
                     None => suggestions.is_empty(),
                 }
             };
 
             if !pass {
-                warn!(
-                    "Rule {}: test \"{}\" failed. Expected: {:#?}. Found: {:#?}.",
-                    self.id,
-                    test.text(),
-                    test.suggestion(),
-                    suggestions
-                );
+                // failures of grammar rule tests are tolerated, there are no known failures for them
+                events::emit(RuleEvent::TestFailed {
+                    rule_id: &self.id,
+                    known: true,
+                    message: format!(
+                        "Rule {}: test \"{}\" failed. Expected: {:#?}. Found: {:#?}.",
+                        located(&self.id, &self.source_location),
+                        test.text(),
+                        test.suggestion(),
+                        suggestions
+                    ),
+                });
             }
 
             passes.push(pass);
@@ -1,15 +1,27 @@
 //! Sets of grammatical error correction rules.
 
-use crate::tokenizer::Tokenizer;
+use crate::checks::NativeChecks;
+use crate::tokenizer::{sentence::Sentence, Tokenizer};
 use crate::types::*;
-use crate::utils::parallelism::MaybeParallelRefIterator;
-use crate::{rule::Rule, tokenizer::finalize};
+use crate::utils::{self, lru::LruCache, parallelism::MaybeParallelRefIterator};
+use crate::{
+    events::{self, RuleEvent},
+    rng::Rng,
+    rule::{Rule, RuleOutput},
+    selector::RuleSelector,
+    stream::StreamChecker,
+    tokenizer::{finalize, quote_depths},
+};
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     fs::File,
+    hash::{Hash, Hasher},
     io::{BufReader, Read},
     path::Path,
+    sync::{Arc, Mutex},
 };
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Options for a rule set.
 #[derive(Serialize, Deserialize, Clone)]
@@ -22,6 +34,151 @@ pub struct RulesOptions {
     /// Grammar Rule IDs to ignore in this set.
     #[serde(default)]
     pub ignore_ids: Vec<String>,
+    /// Rule category IDs (e. g. "TYPOS") or category types (e. g. "style") to check. If not empty, only rules in
+    /// one of these categories are applied, see [Rule::in_category].
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Rule category IDs or category types whose rules are not applied. Takes precedence over `categories`.
+    #[serde(default)]
+    pub ignore_categories: Vec<String>,
+    /// Grammar Rule IDs which are safe to apply without review, see [Rules::autocorrect].
+    /// By default rules with exactly one suggestion whose examples have one correction are safe.
+    #[serde(default)]
+    pub safe_ids: Vec<String>,
+    /// Grammar Rule IDs which are not safe to apply without review even though they have exactly one suggestion.
+    #[serde(default)]
+    pub unsafe_ids: Vec<String>,
+    /// How to rank the replacements of a suggestion.
+    /// If `None`, replacements are kept in the order they are defined in the rule.
+    #[serde(default)]
+    pub ranking: Option<Ranking>,
+    /// Whether to keep replacements which are identical to the text they replace.
+    /// Such suggestions do not change the text so they are dropped by default, keeping them can be useful for debugging.
+    #[serde(default)]
+    pub keep_noop_suggestions: bool,
+    /// Whether to trim the words at the start and end of a suggestion which are the same in the matched text and all replacements,
+    /// e. g. to show "was" -> "has" instead of "She was not been" -> "She has not been" in an editor.
+    #[serde(default)]
+    pub trim_suggestions: bool,
+    /// The locale of the messages e. g. "de" to show German messages for rules checking English text.
+    /// Falls back to the original message of a rule if it has no message in this locale.
+    #[serde(default)]
+    pub message_locale: Option<String>,
+    /// Whether to still return a suggestion without replacements if all suggestions of a rule failed to synthesize.
+    /// See [Rule::synthesis_failures][crate::rule::Rule::synthesis_failures].
+    #[serde(default)]
+    pub message_only_fallback: bool,
+    /// Checks implemented natively which run in addition to the rules.
+    #[serde(default)]
+    pub native_checks: NativeChecks,
+    /// Maps rule category IDs (e. g. "TYPOS"), category types (e. g. "style") or IDs of native checks to severities.
+    /// Category IDs take precedence over category types.
+    #[serde(default)]
+    pub severities: DefaultHashMap<String, Severity>,
+    /// The severity of suggestions which are not mapped in `severities`.
+    #[serde(default)]
+    pub default_severity: Option<Severity>,
+    /// Whether the texts are fragments such as UI strings, list items or commit titles instead of full sentences.
+    /// If set, rules anchored at the sentence start (`SENT_START`) do not match at the start of the text and replacements
+    /// at the start of the text are not made title case. Disambiguation in the tokenizer is not affected.
+    #[serde(default)]
+    pub fragment: bool,
+    /// Whether the texts are headings which may be in Title Case. If set, rules in the capitalization categories
+    /// (see [CAPITALIZATION_CATEGORIES]) are not used. If the heading is in Title Case, replacements capitalize every word
+    /// except minor words such as "of" or "the".
+    #[serde(default)]
+    pub heading: bool,
+    /// Rule category IDs (e. g. "TYPOGRAPHY") or category types (e. g. "style") whose rules are not applied to text
+    /// inside quotation marks such as reported speech or cited titles, see [Token::quote_depth].
+    #[serde(default)]
+    pub skip_in_quotes: Vec<String>,
+    /// Rule category IDs or category types whose rules are not applied to ALL CAPS spans such as headlines,
+    /// see [TokenizerOptions::min_all_caps_words][crate::tokenizer::TokenizerOptions::min_all_caps_words].
+    #[serde(default)]
+    pub skip_in_all_caps: Vec<String>,
+    /// Whether to attach the covered tokens to each suggestion, see [Suggestion::tokens].
+    /// Lets consumers inspect e. g. the POS tags without tokenizing the text again.
+    #[serde(default)]
+    pub include_tokens: bool,
+    /// Whether to attach [ContextFeatures] (e. g. the neighboring tokens and the rule category) to each suggestion,
+    /// see [Suggestion::features].
+    #[serde(default)]
+    pub include_features: bool,
+    /// Whether to attach the groups of the match of a rule to its suggestions, see [Suggestion::groups].
+    /// Useful for debugging rules and highlighting, off by default since it allocates for every suggestion.
+    #[serde(default)]
+    pub include_groups: bool,
+    /// The maximum number of tokens the rules are applied to at once. Matching gets slow for very long sentences,
+    /// e. g. from minified text or tables. Longer sentences are handled according to `long_sentences`. `None` for no limit.
+    #[serde(default)]
+    pub max_sentence_tokens: Option<usize>,
+    /// How to check sentences with more than `max_sentence_tokens` tokens.
+    #[serde(default)]
+    pub long_sentences: LongSentences,
+    /// Whether to catch panics of single rules, e. g. from a bug in a rule, so the other rules are still applied.
+    /// The rule which panicked has no suggestions and a [RuleError::Panicked] in [LimitedSuggestions::errors].
+    /// The panic is still reported by the panic hook.
+    #[serde(default)]
+    pub catch_rule_panics: bool,
+    /// Which suggestions to keep if they overlap.
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+}
+
+/// How the rules check sentences which are longer than [RulesOptions::max_sentence_tokens].
+/// Native checks always check the whole sentence.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LongSentences {
+    /// Check overlapping windows of at most `max_sentence_tokens` tokens. Matches spanning more than `overlap` tokens
+    /// across a window boundary are missed. Rules anchored at the sentence start only match in the first window.
+    Windows { overlap: usize },
+    /// Only check the first `max_sentence_tokens` tokens.
+    Truncate,
+    /// Do not apply the rules.
+    Skip,
+}
+
+impl Default for LongSentences {
+    fn default() -> Self {
+        LongSentences::Windows { overlap: 16 }
+    }
+}
+
+/// Which suggestions [Rules::apply] keeps if they overlap, see [RulesOptions::overlap_policy].
+/// Except for `KeepAll`, the suggestions are visited in an order and a suggestion is kept if it does not overlap
+/// any suggestion kept before.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Keep all suggestions, e. g. to show all candidate issues. The suggestions can overlap, so they should not be
+    /// applied with [apply_suggestions] as is; it skips suggestions overlapping a previous one. A [Reranker] also
+    /// gets the overlapping suggestions.
+    KeepAll,
+    /// Prefer the suggestions covering more chars, then the ones with higher [priority][Suggestion::priority].
+    PreferLonger,
+    /// Prefer the suggestions with higher [priority][Suggestion::priority], see [Suggestion::cmp_priority].
+    #[default]
+    PreferHigherPriority,
+    /// Prefer the suggestions which start first, then the ones with higher priority. Insertions come before
+    /// other suggestions starting at the same position.
+    Leftmost,
+}
+
+/// IDs of the categories of rules which check capitalization, these are not used for headings, see [RulesOptions::heading].
+pub const CAPITALIZATION_CATEGORIES: [&str; 1] = ["CASING"];
+
+impl RulesOptions {
+    /// Whether the rule is in the categories to check, see [RulesOptions::categories].
+    pub(crate) fn includes_category(&self, rule: &Rule) -> bool {
+        (self.categories.is_empty() || self.categories.iter().any(|x| rule.in_category(x)))
+            && !self.ignore_categories.iter().any(|x| rule.in_category(x))
+    }
+
+    /// Resolves the severity by trying the keys in order.
+    pub(crate) fn severity<'a>(&self, keys: impl IntoIterator<Item = &'a str>) -> Option<Severity> {
+        keys.into_iter()
+            .find_map(|key| self.severities.get(key).copied())
+            .or(self.default_severity)
+    }
 }
 
 impl Default for RulesOptions {
@@ -30,108 +187,2804 @@ impl Default for RulesOptions {
             allow_errors: true,
             ids: Vec::new(),
             ignore_ids: Vec::new(),
+            categories: Vec::new(),
+            ignore_categories: Vec::new(),
+            safe_ids: Vec::new(),
+            unsafe_ids: Vec::new(),
+            ranking: None,
+            keep_noop_suggestions: false,
+            trim_suggestions: false,
+            message_locale: None,
+            message_only_fallback: false,
+            native_checks: NativeChecks::default(),
+            severities: DefaultHashMap::default(),
+            default_severity: None,
+            fragment: false,
+            heading: false,
+            skip_in_quotes: Vec::new(),
+            skip_in_all_caps: Vec::new(),
+            include_tokens: false,
+            include_features: false,
+            include_groups: false,
+            max_sentence_tokens: None,
+            long_sentences: LongSentences::default(),
+            catch_rule_panics: false,
+            overlap_policy: OverlapPolicy::default(),
+        }
+    }
+}
+
+/// Weights of the scoring function used to rank the replacements of a suggestion.
+/// Each partial score is in the range [0, 1], the score of a replacement is the weighted sum of the partial scores.
+/// Replacements with a higher score come first, ties keep the order in which they are defined in the rule.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Ranking {
+    /// Weight of the fraction of words in the replacement which are known to the tagger. The tagger has no word
+    /// frequencies, so this is a proxy for how common the replacement is: it does not distinguish rare known words
+    /// from frequent ones.
+    pub known_words: f32,
+    /// Weight of the similarity (one minus the normalized edit distance) of the replacement to the original text.
+    pub similarity: f32,
+    /// Weight of whether the replacement preserves the case of the first character of the original text.
+    pub capitalization: f32,
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Ranking {
+            known_words: 1.,
+            similarity: 1.,
+            capitalization: 0.5,
+        }
+    }
+}
+
+impl Ranking {
+    /// Computes the score of replacing `original` with `replacement`.
+    pub fn score(&self, original: &str, replacement: &str, tokenizer: &Tokenizer) -> f32 {
+        let words: Vec<_> = replacement.split_whitespace().collect();
+        let known_words = if words.is_empty() {
+            0.
+        } else {
+            words
+                .iter()
+                .filter(|word| {
+                    !tokenizer
+                        .tagger()
+                        .get_tags(
+                            word,
+                            tokenizer.options().always_add_lower_tags,
+                            tokenizer.options().use_compound_split_heuristic,
+                        )
+                        .is_empty()
+                })
+                .count() as f32
+                / words.len() as f32
+        };
+
+        let max_len = std::cmp::max(original.chars().count(), replacement.chars().count());
+        let similarity = if max_len == 0 {
+            1.
+        } else {
+            1. - utils::levenshtein(original, replacement) as f32 / max_len as f32
+        };
+
+        let is_upper = |x: &str| matches!(x.chars().next(), Some(c) if c.is_uppercase());
+        let capitalization = (is_upper(original) == is_upper(replacement)) as u8 as f32;
+
+        self.known_words * known_words
+            + self.similarity * similarity
+            + self.capitalization * capitalization
+    }
+
+    /// Sorts the replacements by descending score. The sort is stable.
+    pub(crate) fn rank(
+        &self,
+        original: &str,
+        replacements: &mut Vec<String>,
+        tokenizer: &Tokenizer,
+    ) {
+        let mut scored: Vec<_> = replacements
+            .drain(..)
+            .map(|x| (self.score(original, &x, tokenizer), x))
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+        replacements.extend(scored.into_iter().map(|(_, x)| x));
+    }
+}
+
+/// A function to customize the message of a suggestion at runtime.
+/// Receives the ID of the rule which produced the suggestion and the formatted message and returns the new message.
+pub type MessageHook = dyn Fn(&str, String) -> String + Send + Sync;
+
+/// Reorders or drops the suggestions of a sentence before they are returned, e. g. with a language model or
+/// a heuristic specific to the application. Set with [Rules::set_reranker].
+pub trait Reranker: Send + Sync {
+    /// Gets the suggestions to return for the sentence. `tokens` are the tokens of the sentence, `tokens[0].text`
+    /// is the sentence text. The suggestions are sorted by position and do not overlap unless the
+    /// [overlap policy][RulesOptions::overlap_policy] is [OverlapPolicy::KeepAll]. The returned suggestions must
+    /// not overlap either (with the same exception) and must only contain suggestions from `suggestions`, but
+    /// their replacements may be reordered or removed.
+    fn rerank(&self, tokens: &[Token], suggestions: Vec<Suggestion>) -> Vec<Suggestion>;
+}
+
+/// A match passed to the sampling hook, see [Rules::set_sampler].
+#[derive(Debug, Clone, Copy)]
+pub struct SampledMatch<'a> {
+    /// The suggestion of the match. Its [source][Suggestion::source] is the ID of the rule or native check and
+    /// its indices are relative to the start of `sentence`.
+    pub suggestion: &'a Suggestion,
+    /// The text of the sentence the match is in.
+    pub sentence: &'a str,
+}
+
+/// A function which receives sampled matches, see [Rules::set_sampler].
+pub type SampleHook = dyn Fn(&SampledMatch) + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct Sampler {
+    rate: f64,
+    seed: u64,
+    hook: Arc<SampleHook>,
+}
+
+impl Sampler {
+    /// Whether the match of the rule at `start` in the sentence is sampled. Only depends on the seed and the
+    /// arguments (hashed with the [StableHasher][utils::StableHasher]), so each match is sampled with probability
+    /// `rate` independently of the other rules and the order of the checks.
+    fn samples(&self, rule_id: &str, sentence: &str, start: usize) -> bool {
+        let mut hasher = utils::StableHasher::default();
+        (rule_id, sentence, start).hash(&mut hasher);
+        let hash = hasher.finish();
+
+        Rng::new(self.seed ^ hash).chance(self.rate)
+    }
+}
+
+/// The result of [Rules::autocorrect].
+#[derive(Debug, Clone, Default)]
+pub struct Autocorrection {
+    /// The text with the safe suggestions applied.
+    pub text: String,
+    /// The suggestions which need review, with indices relative to the corrected `text`.
+    pub remaining: Vec<Suggestion>,
+}
+
+/// Limits to bound the time and output of a check on pathological inputs, see [Rules::apply_with_limits].
+/// `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limits {
+    /// The maximum number of suggestions of one rule. The suggestions at the lowest positions are kept.
+    #[serde(default)]
+    pub max_suggestions_per_rule: Option<usize>,
+    /// The maximum number of suggestions in total. The suggestions at the lowest positions are kept.
+    #[serde(default)]
+    pub max_suggestions: Option<usize>,
+    /// The maximum number of matches of one rule to compute suggestions for. Matches after this are skipped
+    /// without synthesizing their replacements and messages.
+    #[serde(default)]
+    pub max_matches_per_rule: Option<usize>,
+    /// The maximum number of steps to match a token pattern at one position, see [Rule::step_limit_exceeded].
+    /// Bounds the time spent on patterns with many optional or repeated tokens. Antipatterns share the limit
+    /// of the match they check; an antipattern which exceeds it blocks the match.
+    #[serde(default)]
+    pub max_match_steps: Option<usize>,
+}
+
+/// The result of [Rules::apply_with_limits].
+#[derive(Debug, Clone, Default)]
+pub struct LimitedSuggestions {
+    /// The suggestions within the limits.
+    pub suggestions: Vec<Suggestion>,
+    /// Whether any limit was reached, i. e. whether suggestions may be missing.
+    pub truncated: bool,
+    /// The IDs of the rules whose matches or suggestions were truncated, sorted.
+    pub truncated_rules: Vec<String>,
+    /// The problems of single rules, see [RuleError].
+    pub errors: Vec<RuleError>,
+}
+
+/// A problem of a single rule while applying it. The other rules are not affected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleError {
+    /// Suggestions or the message of the rule failed to synthesize, see [Rule::synthesis_failures].
+    SynthesisFailed {
+        /// The ID of the rule.
+        rule_id: String,
+        /// How many suggestions and messages failed.
+        count: usize,
+    },
+    /// Matching the rule was stopped by [Limits::max_match_steps].
+    StepLimitExceeded {
+        /// The ID of the rule.
+        rule_id: String,
+        /// At how many positions matching was stopped.
+        count: usize,
+    },
+    /// Applying the rule panicked, see [RulesOptions::catch_rule_panics]. The rule has no suggestions.
+    Panicked {
+        /// The ID of the rule.
+        rule_id: String,
+        /// The panic message.
+        message: String,
+    },
+}
+
+impl RuleError {
+    /// Gets the ID of the rule with the problem.
+    pub fn rule_id(&self) -> &str {
+        match self {
+            RuleError::SynthesisFailed { rule_id, .. }
+            | RuleError::StepLimitExceeded { rule_id, .. }
+            | RuleError::Panicked { rule_id, .. } => rule_id,
+        }
+    }
+}
+
+/// The result of [Rules::suggest_partial]: the suggestions of all rules which worked and the problems of the others.
+#[derive(Debug, Clone, Default)]
+pub struct PartialResult {
+    /// The suggestions.
+    pub suggestions: Vec<Suggestion>,
+    /// The problems of single rules, in the order of the rules.
+    pub errors: Vec<RuleError>,
+}
+
+/// A sentence with its tokens and suggestions, see [Rules::check_sentences].
+#[derive(Debug, Clone)]
+pub struct CheckedSentence<'t> {
+    /// The character span of the sentence in the text.
+    pub text_range: (usize, usize),
+    /// The tokens of the sentence. Their spans are relative to the start of the sentence.
+    pub tokens: Vec<Token<'t>>,
+    /// The suggestions for the sentence. Their indices are relative to the start of the text like the ones of
+    /// [Rules::suggest].
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Clone)]
+struct SentenceWord {
+    text: String,
+    char_span: (usize, usize),
+    byte_span: (usize, usize),
+    has_space_before: bool,
+}
+
+/// The words of a sentence, enough to compute the context of the sentences around it without tokenizing it again,
+/// see [Rules::contexts]. The spans are relative to the start of the sentence.
+#[derive(Debug, Clone)]
+pub(crate) struct SentenceWords(Vec<SentenceWord>);
+
+impl SentenceWords {
+    pub fn new(tokens: &[IncompleteToken]) -> Self {
+        SentenceWords(
+            tokens
+                .iter()
+                .map(|x| SentenceWord {
+                    text: x.word.text.as_ref().to_string(),
+                    char_span: x.char_span,
+                    byte_span: x.byte_span,
+                    has_space_before: x.has_space_before,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A sentence with its words and its tokens if it was tokenized already, see [Rules::contexts].
+pub(crate) type SentenceWithWords<'t> = (
+    Sentence<'t>,
+    SentenceWords,
+    Option<Vec<IncompleteToken<'t>>>,
+);
+
+/// A sentence of a text with the context needed to check it on its own, see [Rules::contexts].
+pub(crate) struct SentenceContext<'t> {
+    pub sentence: Sentence<'t>,
+    // the quote depth of each token of the sentence, computed over the whole text
+    quote_depths: Vec<usize>,
+    // suggestions of the document scoped native checks in this sentence, relative to the start of the sentence
+    native_suggestions: Vec<Suggestion>,
+    // the tokens if the sentence was tokenized to compute the context
+    tokens: Option<Vec<IncompleteToken<'t>>>,
+}
+
+impl<'t> SentenceContext<'t> {
+    /// Hashes the sentence and the context it depends on e. g. to cache its suggestions.
+    pub fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::default();
+        self.sentence.text.hash(&mut hasher);
+        self.quote_depths.hash(&mut hasher);
+        for suggestion in &self.native_suggestions {
+            (suggestion.start, suggestion.end).hash(&mut hasher);
+            suggestion.source.hash(&mut hasher);
+            suggestion.message.hash(&mut hasher);
+            suggestion.replacements.hash(&mut hasher);
         }
+        hasher.finish()
+    }
+}
+
+/// Which rules to run the tests for, see [Rules::test].
+#[derive(Debug, Clone)]
+pub enum TestSelection {
+    /// Test all rules.
+    All,
+    /// Test the rules with the given IDs.
+    Ids(Vec<String>),
+    /// Test `n` rules chosen at random. The same seed always chooses the same rules.
+    Sample { n: usize, seed: u64 },
+}
+
+/// The outcome of running the tests of a rule set, see [Rules::test].
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    /// IDs of the rules whose tests passed.
+    pub passed: Vec<String>,
+    /// IDs of the rules whose tests failed.
+    pub failed: Vec<String>,
+    /// Why the rules could not be compiled e. g. because the XML is malformed. No tests are run in this case.
+    pub error: Option<String>,
+}
+
+impl TestReport {
+    /// Whether the rules could be compiled and all tested rules passed.
+    pub fn success(&self) -> bool {
+        self.failed.is_empty() && self.error.is_none()
+    }
+}
+
+/// The differences between two rule sets, see [Rules::diff].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RulesDiff {
+    /// IDs of rules which are only in the new rule set.
+    pub added: Vec<String>,
+    /// IDs of rules which are only in the old rule set.
+    pub removed: Vec<String>,
+    /// IDs of rules which are in both rule sets but have different content.
+    pub changed: Vec<String>,
+}
+
+impl RulesDiff {
+    /// Whether the rule sets are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
     }
 }
 
+/// Which rules of a rule set are enabled and safe, plus its options, see [Rules::export_settings].
+/// Lets applications persist user preferences and share them between instances of the same rule set.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RulesSettings {
+    /// Category IDs whose rules are all off.
+    #[serde(default)]
+    pub disabled_categories: Vec<String>,
+    /// IDs of rules which are off, except for rules in `disabled_categories`. All other rules are on.
+    #[serde(default)]
+    pub disabled: Vec<String>,
+    /// IDs of rules whose suggestions are safe to apply without review, see [Rules::autocorrect]. All other rules are not safe.
+    #[serde(default)]
+    pub safe: Vec<String>,
+    /// The options of the rule set.
+    pub options: RulesOptions,
+}
+
 /// A set of grammatical error correction rules.
 #[derive(Serialize, Deserialize, Default)]
 pub struct Rules {
     pub(crate) rules: Vec<Rule>,
+    pub(crate) options: RulesOptions,
+    pub(crate) metadata: Metadata,
+    #[serde(skip)]
+    pub(crate) message_hook: Option<Arc<MessageHook>>,
+    #[serde(skip)]
+    pub(crate) reranker: Option<Arc<dyn Reranker>>,
+    #[serde(skip)]
+    pub(crate) sampler: Option<Sampler>,
+    #[serde(skip)]
+    pub(crate) sentence_cache: Option<Mutex<SentenceCache>>,
 }
 
+/// Suggestions of sentences keyed by the hash of the sentence and the fingerprints of the [rules][Rules::fingerprint]
+/// and the [tokenizer][Tokenizer::fingerprint] they were computed with.
+/// The indices of the suggestions are relative to the start of the sentence.
+pub(crate) type SentenceCache = LruCache<(u64, u64, u64), Vec<Suggestion>>;
+
 impl Rules {
     /// Creates a new rules set from a file.
-    pub fn new<P: AsRef<Path>>(p: P) -> bincode::Result<Self> {
-        let reader = BufReader::new(File::open(p).unwrap());
-        bincode::deserialize_from(reader)
+    /// Fails if the file can not be read or deserialized, was built with an incompatible version of nlprule
+    /// or if a rule refers to groups which do not exist.
+    pub fn new<P: AsRef<Path>>(p: P) -> Result<Self, crate::Error> {
+        let reader = BufReader::new(File::open(p)?);
+        Rules::new_from(reader)
     }
 
-    /// Creates a new rules set from a reader.
-    pub fn new_from<R: Read>(reader: R) -> bincode::Result<Self> {
-        bincode::deserialize_from(reader)
+    /// Creates a new rules set from a reader. Fails like [Rules::new].
+    pub fn new_from<R: Read>(reader: R) -> Result<Self, crate::Error> {
+        let rules: Rules = bincode::deserialize_from(reader)?;
+        rules.metadata.check_version()?;
+        rules.validate_groups()?;
+        Ok(rules)
     }
 
-    /// All rules ordered by priority.
-    pub fn rules(&self) -> &Vec<Rule> {
-        &self.rules
+    /// Checks that all groups referenced by the rules exist, so a corrupted or hand-edited binary
+    /// leads to an error when loading instead of a panic when applying the rules.
+    fn validate_groups(&self) -> Result<(), crate::Error> {
+        let offending: Vec<_> = self
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                let missing = rule.missing_groups();
+                if missing.is_empty() {
+                    None
+                } else {
+                    Some(format!("{} (groups {:?})", rule.id(), missing))
+                }
+            })
+            .collect();
+
+        if offending.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::CorruptBinary(format!(
+                "rules refer to nonexistent groups: {}",
+                offending.join(", ")
+            )))
+        }
     }
 
-    /// Finds a rule by ID.
-    pub fn rule(&self, id: &str) -> Option<&Rule> {
-        self.rules.iter().find(|x| x.id() == id)
+    /// Gets the options of this rule set.
+    pub fn options(&self) -> &RulesOptions {
+        &self.options
     }
 
-    /// Compute the suggestions for the given tokens by checking all rules.
-    pub fn apply(&self, tokens: &[Token], tokenizer: &Tokenizer) -> Vec<Suggestion> {
-        if tokens.is_empty() {
-            return Vec::new();
+    /// Gets the options of this rule set mutably e. g. to change the ranking at runtime.
+    pub fn options_mut(&mut self) -> &mut RulesOptions {
+        &mut self.options
+    }
+
+    /// Gets information about how this rule set was built.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Removes the [source locations][crate::rule::Rule::source_location] of all rules e. g. to not ship local paths.
+    pub fn strip_source_locations(&mut self) {
+        for rule in self.rules.iter_mut() {
+            rule.source_location = None;
         }
+    }
 
-        let mut output: Vec<(usize, Suggestion)> = self
-            .rules
-            .maybe_par_iter()
-            .enumerate()
-            .filter(|(_, x)| x.on())
-            .map(|(i, rule)| {
-                let mut output = Vec::new();
-
-                for suggestion in rule.apply(tokens, tokenizer) {
-                    output.push((i, suggestion));
-                }
+    /// Sets a hook to customize suggestion messages e. g. to append localized explanations or links to a style guide.
+    /// The hook is not serialized.
+    pub fn set_message_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(&str, String) -> String + Send + Sync + 'static,
+    {
+        self.message_hook = Some(Arc::new(hook));
+        self.clear_sentence_cache();
+    }
+
+    /// Removes the message hook, if any.
+    pub fn clear_message_hook(&mut self) {
+        self.message_hook = None;
+        self.clear_sentence_cache();
+    }
+
+    /// Sets a [Reranker] which is called with the suggestions of each sentence before they are returned.
+    /// The reranker is not serialized.
+    pub fn set_reranker<R: Reranker + 'static>(&mut self, reranker: R) {
+        self.reranker = Some(Arc::new(reranker));
+        self.clear_sentence_cache();
+    }
+
+    /// Removes the reranker, if any.
+    pub fn clear_reranker(&mut self) {
+        self.reranker = None;
+        self.clear_sentence_cache();
+    }
+
+    /// Sets a hook which is called with a sample of the matches found by [Rules::apply], e. g. to monitor the
+    /// quality of rules in production without storing the checked documents. Each match is passed to the hook
+    /// with probability `rate` (e. g. `0.01` for 1% of the matches of each rule) together with its sentence.
+    /// Which matches are sampled is deterministic given the `seed`. Sentences whose suggestions come from the
+    /// [sentence cache][Rules::set_sentence_cache] are not sampled again. The hook is not serialized.
+    pub fn set_sampler<F>(&mut self, rate: f64, seed: u64, hook: F)
+    where
+        F: Fn(&SampledMatch) + Send + Sync + 'static,
+    {
+        self.sampler = Some(Sampler {
+            rate,
+            seed,
+            hook: Arc::new(hook),
+        });
+    }
+
+    /// Removes the sampling hook, if any.
+    pub fn clear_sampler(&mut self) {
+        self.sampler = None;
+    }
+
+    /// Enables caching the suggestions of up to `capacity` sentences, or disables the cache if `capacity` is `None`.
+    /// With a cache, [Rules::suggest] checks each sentence on its own and reuses the suggestions of sentences
+    /// it has seen before with the same options, e. g. when a document is rechecked after a small edit.
+    /// Rules and native checks can then not match across sentence boundaries. The cache is not serialized.
+    pub fn set_sentence_cache(&mut self, capacity: Option<usize>) {
+        self.sentence_cache = capacity.map(|capacity| Mutex::new(LruCache::new(capacity)));
+    }
+
+    /// Removes all entries from the sentence cache, if there is one.
+    pub fn clear_sentence_cache(&self) {
+        if let Some(cache) = &self.sentence_cache {
+            cache
+                .lock()
+                .expect("sentence cache lock must not be poisoned")
+                .clear();
+        }
+    }
+
+    /// Computes a fingerprint of everything which influences the suggestions at runtime: the rule set,
+    /// which rules are enabled, their priorities and the [options][Rules::options]. The fingerprint is stable
+    /// across processes running the same nlprule version, so it can be stored with cached results to invalidate
+    /// them when the settings change. The tokens also influence the suggestions, see [Tokenizer::fingerprint].
+    /// Hooks and the reranker can not be hashed, only whether a reranker is set is part of the fingerprint.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = utils::StableHasher::default();
+
+        self.metadata.source_hash.hash(&mut hasher);
+        self.metadata.nlprule_version.hash(&mut hasher);
+        for rule in self.rules.iter().filter(|x| x.on()) {
+            rule.id().hash(&mut hasher);
+            rule.priority().hash(&mut hasher);
+        }
+        self.reranker.is_some().hash(&mut hasher);
+
+        // the severities are a hash map, so they are hashed in sorted order
+        let mut options = self.options.clone();
+        let mut severities: Vec<_> = options.severities.drain().collect();
+        severities.sort_by(|a, b| a.0.cmp(&b.0));
+        bincode::serialize(&(options, severities))
+            .expect("options must be serializable")
+            .hash(&mut hasher);
+
+        hasher.finish()
+    }
 
-                output
+    /// Splits the text into sentences which can be checked one by one with [Rules::check_in_context], see
+    /// [Rules::contexts].
+    pub(crate) fn sentences_in_context<'t>(
+        &self,
+        text: &'t str,
+        tokenizer: &'t Tokenizer,
+    ) -> Vec<SentenceContext<'t>> {
+        let sentences = tokenizer
+            .sentencize(text)
+            .into_iter()
+            .map(|sentence| {
+                let tokens = tokenizer.tokenize(sentence.text);
+                (sentence, SentenceWords::new(&tokens), Some(tokens))
             })
-            .flatten()
             .collect();
 
-        output.sort_by(|(ia, a), (ib, b)| a.start.cmp(&b.start).then_with(|| ib.cmp(ia)));
+        self.contexts(text, tokenizer, sentences)
+    }
 
-        let mut mask = vec![false; tokens[0].text.chars().count()];
+    /// Computes the context of the sentences of a text from their words. Quotations and the
+    /// [document scoped][crate::checks::NativeCheck::document_scoped] native checks see the whole text.
+    /// The tokens of a sentence are optional, sentences without them are tokenized when they are checked.
+    pub(crate) fn contexts<'t>(
+        &self,
+        text: &'t str,
+        tokenizer: &'t Tokenizer,
+        sentences: Vec<SentenceWithWords<'t>>,
+    ) -> Vec<SentenceContext<'t>> {
+        let words: Vec<_> = sentences
+            .iter()
+            .flat_map(|(_, words, _)| words.0.iter().map(|x| x.text.as_str()))
+            .collect();
+        let mut quote_depths = quote_depths(&words).into_iter();
 
-        output
+        let checks: Vec<_> = self
+            .options
+            .native_checks
+            .enabled()
             .into_iter()
-            .filter_map(|(_, suggestion)| {
-                if mask[suggestion.start..suggestion.end].iter().all(|x| !x) {
-                    mask[suggestion.start..suggestion.end]
-                        .iter_mut()
-                        .for_each(|x| *x = true);
-                    Some(suggestion)
-                } else {
-                    None
+            .filter(|x| x.document_scoped())
+            .collect();
+        let mut native_suggestions = Vec::new();
+        if !checks.is_empty() && !words.is_empty() {
+            // untagged tokens of the whole text, with spans relative to the start of the text
+            let tokens: Vec<_> = sentences
+                .iter()
+                .flat_map(|(sentence, words, _)| {
+                    words.0.iter().map(move |word| IncompleteToken {
+                        word: Word::new_with_tags(
+                            tokenizer.tagger().id_word(word.text.as_str().into()),
+                            Vec::new(),
+                        ),
+                        char_span: (
+                            word.char_span.0 + sentence.char_span.0,
+                            word.char_span.1 + sentence.char_span.0,
+                        ),
+                        byte_span: (
+                            word.byte_span.0 + sentence.byte_span.0,
+                            word.byte_span.1 + sentence.byte_span.0,
+                        ),
+                        is_sentence_end: false,
+                        has_space_before: word.has_space_before,
+                        chunks: Vec::new(),
+                        quote_depth: 0,
+                        text,
+                        tagger: tokenizer.tagger(),
+                    })
+                })
+                .collect();
+            let tokens = finalize(tokens);
+
+            for check in &checks {
+                native_suggestions.extend(check.check_tokens(&tokens));
+            }
+        }
+
+        sentences
+            .into_iter()
+            .map(|(sentence, words, tokens)| {
+                let (start, end) = sentence.char_span;
+                let native_suggestions = native_suggestions
+                    .iter()
+                    .filter(|x| x.start >= start && x.start < end && x.end <= end)
+                    .cloned()
+                    .map(|mut suggestion| {
+                        suggestion.start -= start;
+                        suggestion.end -= start;
+                        suggestion
+                    })
+                    .collect();
+
+                SentenceContext {
+                    sentence,
+                    quote_depths: quote_depths.by_ref().take(words.0.len()).collect(),
+                    native_suggestions,
+                    tokens,
                 }
             })
             .collect()
     }
 
-    /// Compute the suggestions for a text by checking all rules.
-    pub fn suggest(&self, text: &str, tokenizer: &Tokenizer) -> Vec<Suggestion> {
-        let tokens = tokenizer.disambiguate(tokenizer.tokenize(text));
-        self.apply(&finalize(tokens), tokenizer)
+    /// Computes the suggestions for a sentence, see [Rules::contexts]. The indices of the suggestions
+    /// are relative to the start of the sentence.
+    pub(crate) fn check_in_context<'t>(
+        &self,
+        context: SentenceContext<'t>,
+        tokenizer: &'t Tokenizer,
+        limits: &Limits,
+    ) -> (Vec<Token<'t>>, LimitedSuggestions) {
+        let sentence = context.sentence;
+        let mut tokens = context
+            .tokens
+            .unwrap_or_else(|| tokenizer.tokenize(sentence.text));
+        for (token, depth) in tokens.iter_mut().zip(context.quote_depths) {
+            token.quote_depth = depth;
+        }
+
+        let tokens = finalize(tokenizer.disambiguate(tokens));
+        let suggestions = self.apply_in_context(
+            &tokens,
+            tokenizer,
+            limits,
+            Some(&context.native_suggestions),
+        );
+        (tokens, suggestions)
     }
 
-    /// Correct a text by first tokenizing, then finding all suggestions and choosing the first replacement of each suggestion.
-    pub fn correct(&self, text: &str, tokenizer: &Tokenizer) -> String {
-        let suggestions = self.suggest(text, tokenizer);
-        apply_suggestions(text, &suggestions)
+    /// Computes the suggestions sentence by sentence, see [Rules::sentences_in_context]. If a
+    /// [sentence cache][Rules::set_sentence_cache] is set, cached suggestions are reused.
+    fn suggest_sentences(&self, text: &str, tokenizer: &Tokenizer) -> Vec<Suggestion> {
+        // the hooks and the reranker are not in the fingerprint, the cache is cleared when they change
+        let cache = self
+            .sentence_cache
+            .as_ref()
+            .map(|cache| (cache, self.fingerprint(), tokenizer.fingerprint()));
+        let mut suggestions = Vec::new();
+
+        for context in self.sentences_in_context(text, tokenizer) {
+            let sentence = context.sentence;
+
+            let sentence_suggestions = match cache {
+                Some((cache, fingerprint, tokenizer_fingerprint)) => {
+                    let key = (context.hash(), fingerprint, tokenizer_fingerprint);
+
+                    let cached = cache
+                        .lock()
+                        .expect("sentence cache lock must not be poisoned")
+                        .get(&key);
+                    match cached {
+                        Some(cached) => cached,
+                        None => {
+                            let computed = self
+                                .check_in_context(context, tokenizer, &Limits::default())
+                                .1
+                                .suggestions;
+                            cache
+                                .lock()
+                                .expect("sentence cache lock must not be poisoned")
+                                .insert(key, computed.clone());
+                            computed
+                        }
+                    }
+                }
+                None => {
+                    self.check_in_context(context, tokenizer, &Limits::default())
+                        .1
+                        .suggestions
+                }
+            };
+
+            suggestions.extend(sentence_suggestions.into_iter().map(|mut suggestion| {
+                suggestion.shift(sentence.char_span.0, sentence.byte_span.0);
+                suggestion
+            }));
+        }
+
+        suggestions
     }
-}
 
-/// Correct a text by applying suggestions to it.
-/// In the case of multiple possible replacements, always chooses the first one.
-pub fn apply_suggestions(text: &str, suggestions: &[Suggestion]) -> String {
-    let mut offset: isize = 0;
-    let mut chars: Vec<_> = text.chars().collect();
+    /// All rules ordered by priority.
+    pub fn rules(&self) -> &Vec<Rule> {
+        &self.rules
+    }
 
-    for suggestion in suggestions {
-        let replacement: Vec<_> = suggestion.replacements[0].chars().collect();
-        chars.splice(
-            (suggestion.start as isize + offset) as usize
-                ..(suggestion.end as isize + offset) as usize,
-            replacement.iter().cloned(),
-        );
-        offset = offset + replacement.len() as isize - (suggestion.end - suggestion.start) as isize;
+    /// Finds a rule by ID.
+    pub fn rule(&self, id: &str) -> Option<&Rule> {
+        self.rules.iter().find(|x| x.id() == id)
+    }
+
+    /// Finds a rule by ID to e. g. turn it on or off with [Rule::set_on].
+    pub fn rule_mut(&mut self, id: &str) -> Option<&mut Rule> {
+        self.rules.iter_mut().find(|x| x.id() == id)
+    }
+
+    /// Sets the [priority][Rule::priority] of the rule with this ID, e. g. so a rule specific to the application
+    /// wins over the LanguageTool rules. Returns whether the rule exists.
+    pub fn set_priority(&mut self, id: &str, priority: i32) -> bool {
+        match self.rule_mut(id) {
+            Some(rule) => rule.priority = priority,
+            None => return false,
+        }
+
+        self.sort_by_priority();
+        self.clear_sentence_cache();
+        true
+    }
+
+    /// Sorts the rules by priority, keeping the order of rules with the same priority. The priority of their
+    /// suggestions is computed from the position of the rule in this order, see [Suggestion::priority].
+    pub(crate) fn sort_by_priority(&mut self) {
+        self.rules.sort_by_key(|x| x.priority);
+    }
+
+    /// Turns off rules whose pattern requires POS tags which do not exist in the tagset of the tokenizer,
+    /// e. g. when rules and tokenizer are compiled from different LanguageTool versions. Such rules can never match.
+    /// Returns the IDs of the rules which were turned off.
+    pub fn check_tagset(&mut self, tokenizer: &Tokenizer) -> Vec<String> {
+        self.disable_unmatchable(tokenizer.tagger().tag_store().len())
+    }
+
+    pub(crate) fn disable_unmatchable(&mut self, n_tags: usize) -> Vec<String> {
+        let mut disabled = Vec::new();
+
+        for rule in self.rules.iter_mut() {
+            if rule.on() && !rule.engine.can_match_tagset(n_tags) {
+                rule.set_on(false);
+                disabled.push(rule.id().to_string());
+            }
+        }
+
+        disabled.sort();
+        disabled
+    }
+
+    /// Turns the rules selected by the selector on and all other rules off. Returns how many rules are on.
+    pub fn select_on(&mut self, selector: &RuleSelector) -> usize {
+        for rule in self.rules.iter_mut() {
+            rule.set_on(selector.matches(rule));
+        }
+        self.rules.iter().filter(|x| x.on()).count()
     }
 
-    chars.into_iter().collect()
+    /// Turns all rules on or off according to their [default state][Rule::default_on].
+    pub fn reset_to_defaults(&mut self) {
+        for rule in self.rules.iter_mut() {
+            rule.reset_to_default();
+        }
+    }
+
+    /// Gets which rules are enabled and safe, and the options. Categories whose rules are all off are stored
+    /// as one entry so the settings stay small.
+    pub fn export_settings(&self) -> RulesSettings {
+        let mut categories: DefaultHashMap<&str, bool> = DefaultHashMap::default();
+        for rule in &self.rules {
+            *categories.entry(rule.category_id()).or_insert(true) &= !rule.on();
+        }
+
+        let mut disabled_categories: Vec<_> = categories
+            .iter()
+            .filter(|(_, all_off)| **all_off)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        disabled_categories.sort();
+
+        RulesSettings {
+            disabled: self
+                .rules
+                .iter()
+                .filter(|x| !x.on() && !categories[x.category_id()])
+                .map(|x| x.id().to_string())
+                .collect(),
+            disabled_categories,
+            safe: self
+                .rules
+                .iter()
+                .filter(|x| x.safe())
+                .map(|x| x.id().to_string())
+                .collect(),
+            options: self.options.clone(),
+        }
+    }
+
+    /// Turns rules on or off, marks them as safe and sets the options according to settings from [Rules::export_settings].
+    /// Settings exported from another version of the rule set may mention rules and categories which do not exist
+    /// in this one, they are ignored and returned sorted.
+    pub fn import_settings(&mut self, settings: &RulesSettings) -> Vec<String> {
+        let disabled_categories: DefaultHashSet<_> = settings.disabled_categories.iter().collect();
+        let disabled: DefaultHashSet<_> = settings.disabled.iter().collect();
+        let safe: DefaultHashSet<_> = settings.safe.iter().collect();
+
+        let mut known = DefaultHashSet::default();
+        for rule in self.rules.iter_mut() {
+            let id = rule.id().to_string();
+            let category_id = rule.category_id().to_string();
+
+            rule.set_on(!disabled_categories.contains(&category_id) && !disabled.contains(&id));
+            rule.set_safe(safe.contains(&id));
+
+            known.insert(id);
+            known.insert(category_id);
+        }
+        self.options = settings.options.clone();
+
+        let mut unknown: Vec<_> = settings
+            .disabled_categories
+            .iter()
+            .chain(&settings.disabled)
+            .chain(&settings.safe)
+            .filter(|x| !known.contains(*x))
+            .cloned()
+            .collect();
+        unknown.sort();
+        unknown.dedup();
+        unknown
+    }
+
+    /// Gets the rules matching the selection, in priority order.
+    pub fn select(&self, selection: &TestSelection) -> Vec<&Rule> {
+        match selection {
+            TestSelection::All => self.rules.iter().collect(),
+            TestSelection::Ids(ids) => self
+                .rules
+                .iter()
+                .filter(|x| ids.iter().any(|id| id == x.id()))
+                .collect(),
+            TestSelection::Sample { n, seed } => Rng::new(*seed)
+                .sample_indices(self.rules.len(), *n)
+                .into_iter()
+                .map(|i| &self.rules[i])
+                .collect(),
+        }
+    }
+
+    /// Runs the tests of the selected rules, see [Rule::test].
+    /// Testing a sample of the rules is useful during development since testing all rules takes a while.
+    pub fn test(&self, tokenizer: &Tokenizer, selection: &TestSelection) -> TestReport {
+        let mut report = TestReport::default();
+
+        for rule in self.select(selection) {
+            if rule.test(tokenizer) {
+                report.passed.push(rule.id().to_string());
+            } else {
+                report.failed.push(rule.id().to_string());
+            }
+        }
+
+        report
+    }
+
+    /// Compares this (old) rule set with another (new) one by rule ID and [content hash][Rule::content_hash].
+    /// Useful to validate upgrades of the LanguageTool XML.
+    pub fn diff(&self, other: &Rules) -> RulesDiff {
+        let old: DefaultHashMap<_, _> = self
+            .rules
+            .iter()
+            .map(|x| (x.id(), x.content_hash()))
+            .collect();
+        let new: DefaultHashMap<_, _> = other
+            .rules
+            .iter()
+            .map(|x| (x.id(), x.content_hash()))
+            .collect();
+
+        let mut diff = RulesDiff::default();
+
+        for rule in &other.rules {
+            match old.get(rule.id()) {
+                None => diff.added.push(rule.id().to_string()),
+                Some(hash) if *hash != new[rule.id()] => diff.changed.push(rule.id().to_string()),
+                _ => {}
+            }
+        }
+
+        for rule in &self.rules {
+            if !new.contains_key(rule.id()) {
+                diff.removed.push(rule.id().to_string());
+            }
+        }
+
+        diff
+    }
+
+    /// Compute the suggestions for the given tokens by checking all rules. Of overlapping suggestions, the one with
+    /// the highest [priority][Suggestion::priority] is kept. The suggestions are sorted by [position][Suggestion::cmp_position].
+    pub fn apply(&self, tokens: &[Token], tokenizer: &Tokenizer) -> Vec<Suggestion> {
+        self.apply_with_limits(tokens, tokenizer, &Limits::default())
+            .suggestions
+    }
+
+    /// Compute the suggestions for the given tokens by checking all rules, stopping early once the limits are reached.
+    /// Whether suggestions were dropped because of the limits is noted in the result.
+    pub fn apply_with_limits(
+        &self,
+        tokens: &[Token],
+        tokenizer: &Tokenizer,
+        limits: &Limits,
+    ) -> LimitedSuggestions {
+        self.apply_in_context(tokens, tokenizer, limits, None)
+    }
+
+    /// Same as [Rules::apply_with_limits]. If `native_suggestions` is set, it holds the suggestions of the
+    /// [document scoped][crate::checks::NativeCheck::document_scoped] native checks for these tokens, which are
+    /// used instead of running the checks on the tokens.
+    fn apply_in_context(
+        &self,
+        tokens: &[Token],
+        tokenizer: &Tokenizer,
+        limits: &Limits,
+        native_suggestions: Option<&[Suggestion]>,
+    ) -> LimitedSuggestions {
+        if tokens.is_empty() {
+            return LimitedSuggestions::default();
+        }
+
+        // the special first token without the sentence start tag can not be matched by rules anchored at it
+        let fragment_tokens;
+        let tokens = if self.options.fragment {
+            let mut first = tokens[0].clone();
+            first.word.tags.retain(|x| x.pos.as_ref() != "SENT_START");
+            fragment_tokens = std::iter::once(first)
+                .chain(tokens[1..].iter().cloned())
+                .collect::<Vec<_>>();
+            &fragment_tokens
+        } else {
+            tokens
+        };
+
+        // windows after the first one do not start at the sentence start, like fragments
+        let mut first = tokens[0].clone();
+        first.word.tags.retain(|x| x.pos.as_ref() != "SENT_START");
+        let windows: Vec<Cow<[Token]>> = window_ranges(tokens.len(), &self.options)
+            .into_iter()
+            .map(|(start, end)| {
+                if start == 1 {
+                    Cow::Borrowed(&tokens[..end])
+                } else {
+                    Cow::Owned(
+                        std::iter::once(first.clone())
+                            .chain(tokens[start..end].iter().cloned())
+                            .collect(),
+                    )
+                }
+            })
+            .collect();
+
+        let all_caps = {
+            let words: Vec<_> = tokens[1..]
+                .iter()
+                .map(|x| {
+                    tokens[0]
+                        .text
+                        .get(x.byte_span.0..x.byte_span.1)
+                        .unwrap_or("")
+                })
+                .collect();
+            let min_words = tokenizer.options().min_all_caps_words;
+            std::iter::once(false)
+                .chain(crate::tokenizer::all_caps_words(&words, min_words))
+                .collect::<Vec<_>>()
+        };
+
+        let converter = SpanConverter::new(tokens[0].text);
+        let rule_outputs: Vec<_> =
+            self.rules
+                .maybe_par_iter()
+                .enumerate()
+                .filter(|(_, x)| {
+                    x.on()
+                        && !(self.options.heading
+                            && CAPITALIZATION_CATEGORIES.contains(&x.category_id()))
+                        && self.options.includes_category(x)
+                })
+                .map(|(i, rule)| {
+                    let apply = || {
+                        let mut output = RuleOutput::default();
+                        for window in &windows {
+                            let window_output = rule.apply_limited(
+                                window,
+                                tokenizer,
+                                &converter,
+                                &self.options,
+                                limits,
+                            );
+                            output.suggestions.extend(window_output.suggestions);
+                            output.truncated |= window_output.truncated;
+                            output.synthesis_failures += window_output.synthesis_failures;
+                            output.step_limit_exceeded += window_output.step_limit_exceeded;
+                        }
+                        output
+                    };
+
+                    let mut errors = Vec::new();
+                    let output = if self.options.catch_rule_panics {
+                        std::panic::catch_unwind(std::panic::AssertUnwindSafe(apply))
+                            .unwrap_or_else(|payload| {
+                                let message = match payload.downcast_ref::<&str>() {
+                                    Some(message) => message.to_string(),
+                                    None => payload
+                                        .downcast_ref::<String>()
+                                        .cloned()
+                                        .unwrap_or_default(),
+                                };
+                                errors.push(RuleError::Panicked {
+                                    rule_id: rule.id().to_string(),
+                                    message,
+                                });
+                                RuleOutput::default()
+                            })
+                    } else {
+                        apply()
+                    };
+                    if output.synthesis_failures > 0 {
+                        errors.push(RuleError::SynthesisFailed {
+                            rule_id: rule.id().to_string(),
+                            count: output.synthesis_failures,
+                        });
+                    }
+                    if output.step_limit_exceeded > 0 {
+                        errors.push(RuleError::StepLimitExceeded {
+                            rule_id: rule.id().to_string(),
+                            count: output.step_limit_exceeded,
+                        });
+                    }
+
+                    let (mut suggestions, mut truncated) = (output.suggestions, output.truncated);
+                    // matches in the overlap of two windows are found twice
+                    if windows.len() > 1 {
+                        suggestions.sort_by_key(|x| (x.start, x.end));
+                        suggestions.dedup_by(|a, b| a.start == b.start && a.end == b.end);
+                    }
+
+                    let skip_in_quotes = self
+                        .options
+                        .skip_in_quotes
+                        .iter()
+                        .any(|x| rule.in_category(x));
+                    if skip_in_quotes {
+                        suggestions.retain(|x| !in_quotes(tokens, x.start, x.end));
+                    }
+
+                    let skip_in_all_caps = self
+                        .options
+                        .skip_in_all_caps
+                        .iter()
+                        .any(|x| rule.in_category(x));
+                    if skip_in_all_caps {
+                        suggestions.retain(|x| !in_all_caps(tokens, &all_caps, x.start, x.end));
+                    } else if tokenizer.options().lowercase_all_caps {
+                        // the words were matched in lowercase, see [TokenizerOptions::lowercase_all_caps]
+                        for suggestion in suggestions
+                            .iter_mut()
+                            .filter(|x| in_all_caps(tokens, &all_caps, x.start, x.end))
+                        {
+                            for replacement in suggestion.replacements.iter_mut() {
+                                *replacement = replacement.to_uppercase();
+                            }
+                        }
+                    }
+
+                    if let Some(max_suggestions) = limits.max_suggestions_per_rule {
+                        if suggestions.len() > max_suggestions {
+                            suggestions.sort_by_key(|x| (x.start, x.end));
+                            suggestions.truncate(max_suggestions);
+                            truncated = true;
+                        }
+                    }
+
+                    (i, suggestions, truncated, errors)
+                })
+                .collect();
+
+        let mut truncated_rules = Vec::new();
+        let mut errors = Vec::new();
+        let mut output: Vec<(usize, Suggestion)> = Vec::new();
+        for (i, suggestions, truncated, rule_errors) in rule_outputs {
+            if truncated {
+                truncated_rules.push(self.rules[i].id().to_string());
+            }
+            output.extend(suggestions.into_iter().map(|x| (i, x)));
+            errors.extend(rule_errors);
+        }
+        truncated_rules.sort();
+
+        // native checks take precedence over rules starting at the same position
+        let mut suppressed = Vec::new();
+        for check in self.options.native_checks.enabled() {
+            let suggestions = match native_suggestions {
+                Some(native_suggestions) if check.document_scoped() => native_suggestions
+                    .iter()
+                    .filter(|x| x.source == check.id())
+                    .cloned()
+                    .collect(),
+                _ => check.check_tokens(tokens),
+            };
+            if check.suppresses_rules() {
+                suppressed.extend(suggestions.iter().map(|x| (x.start, x.end)));
+            }
+
+            output.extend(suggestions.into_iter().map(|mut suggestion| {
+                suggestion.severity = self.options.severity(Some(check.id()));
+                (usize::MAX, suggestion)
+            }));
+        }
+        if !suppressed.is_empty() {
+            output.retain(|(i, x)| {
+                *i == usize::MAX
+                    || !suppressed
+                        .iter()
+                        .any(|(start, end)| x.start < *end && *start < x.end)
+            });
+        }
+
+        let n_rules = self.rules.len();
+        for (i, suggestion) in output.iter_mut() {
+            suggestion.priority = if *i == usize::MAX {
+                1.
+            } else {
+                (*i + 1) as f32 / (n_rules + 1) as f32
+            };
+        }
+        let keep_all = self.options.overlap_policy == OverlapPolicy::KeepAll;
+        match self.options.overlap_policy {
+            OverlapPolicy::KeepAll => {}
+            OverlapPolicy::PreferLonger => output.sort_by(|(_, a), (_, b)| {
+                (b.end - b.start)
+                    .cmp(&(a.end - a.start))
+                    .then_with(|| a.cmp_priority(b))
+            }),
+            OverlapPolicy::PreferHigherPriority => {
+                output.sort_by(|(_, a), (_, b)| a.cmp_priority(b))
+            }
+            OverlapPolicy::Leftmost => output.sort_by(|(_, a), (_, b)| {
+                a.start
+                    .cmp(&b.start)
+                    .then_with(|| (a.start != a.end).cmp(&(b.start != b.end)))
+                    .then_with(|| a.cmp_priority(b))
+            }),
+        }
+
+        let char_len = converter.char_len();
+        let mut mask = SpanMask::new(char_len);
+
+        let mut output: Vec<_> = output
+            .into_iter()
+            .filter_map(|(i, mut suggestion)| {
+                if keep_all || mask.insert((suggestion.start, suggestion.end)) {
+                    if let Some(rule) = self.rules.get(i) {
+                        events::emit(RuleEvent::RuleApplied {
+                            rule_id: rule.id(),
+                            start: suggestion.start,
+                            end: suggestion.end,
+                        });
+                    }
+                    suggestion.sentence_span = (0, char_len);
+                    suggestion.set_byte_span(&converter);
+                    let (start, end) = token_span(tokens, suggestion.start, suggestion.end);
+                    suggestion.token_span = Some((start, end));
+                    if self.options.include_tokens {
+                        suggestion.tokens = tokens[start..end]
+                            .iter()
+                            .map(|x| x.to_owned_token())
+                            .collect();
+                    }
+                    if self.options.include_features {
+                        let category = self.rules.get(i).map(|x| x.category_id());
+                        suggestion.features =
+                            Some(ContextFeatures::new(tokens, (start, end), category));
+                    }
+                    Some(suggestion)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        output.sort_by(|a, b| a.cmp_position(b));
+
+        // checked before the hook and the reranker, which are not part of the rules
+        #[cfg(debug_assertions)]
+        if !keep_all {
+            crate::fuzz::check_suggestions(tokens[0].text, &output);
+        }
+
+        if let Some(hook) = &self.message_hook {
+            for suggestion in output.iter_mut() {
+                let message = hook(&suggestion.source, suggestion.message.clone());
+                // the parts can not be aligned with a message changed by the hook
+                if message != suggestion.message {
+                    suggestion.message_parts = vec![MessagePart::Literal(message.clone())];
+                }
+                suggestion.message = message;
+            }
+        }
+
+        if let Some(reranker) = &self.reranker {
+            output = reranker.rerank(tokens, output);
+        }
+
+        let mut truncated = !truncated_rules.is_empty();
+        if let Some(max_suggestions) = limits.max_suggestions {
+            if output.len() > max_suggestions {
+                output.truncate(max_suggestions);
+                truncated = true;
+            }
+        }
+
+        if let Some(sampler) = &self.sampler {
+            let sentence = tokens[0].text;
+            for suggestion in output
+                .iter()
+                .filter(|x| sampler.samples(&x.source, sentence, x.start))
+            {
+                (sampler.hook)(&SampledMatch {
+                    suggestion,
+                    sentence,
+                });
+            }
+        }
+
+        LimitedSuggestions {
+            suggestions: output,
+            truncated,
+            truncated_rules,
+            errors,
+        }
+    }
+
+    /// Compute the suggestions for a text analyzed before with [Tokenizer::analyze], e. g. in another process.
+    /// `tokenizer` must be the tokenizer the text was analyzed with.
+    ///
+    /// # Errors
+    /// If the analysis contains tags which are not known to the tokenizer.
+    pub fn apply_tokens(
+        &self,
+        analysis: &Analysis,
+        tokenizer: &Tokenizer,
+    ) -> Result<Vec<Suggestion>, crate::Error> {
+        let tokens = analysis.tokens(tokenizer.tagger())?;
+        Ok(self.apply(&tokens, tokenizer))
+    }
+
+    /// Compute the suggestions for a text by checking all rules. The text is checked sentence by sentence
+    /// (see [Tokenizer::sentencize]) so e. g. rules anchored at the sentence start match in every sentence.
+    /// Quotations and [document scoped][crate::checks::NativeCheck::document_scoped] native checks see the whole text.
+    /// The indices of the suggestions are relative to the start of the text.
+    /// If a [sentence cache][Rules::set_sentence_cache] is set, the suggestions of each sentence are cached.
+    pub fn suggest(&self, text: &str, tokenizer: &Tokenizer) -> Vec<Suggestion> {
+        self.suggest_sentences(text, tokenizer)
+    }
+
+    /// Compute the suggestions for a text sentence by sentence, e. g. to show annotations per sentence.
+    /// Sentences are split like in [Rules::suggest]; the [sentence cache][Rules::set_sentence_cache] is not used.
+    pub fn check_sentences<'t>(
+        &self,
+        text: &'t str,
+        tokenizer: &'t Tokenizer,
+    ) -> Vec<CheckedSentence<'t>> {
+        self.sentences_in_context(text, tokenizer)
+            .into_iter()
+            .map(|context| {
+                let sentence = context.sentence;
+                let (tokens, limited) =
+                    self.check_in_context(context, tokenizer, &Limits::default());
+                let mut suggestions = limited.suggestions;
+                for suggestion in suggestions.iter_mut() {
+                    suggestion.shift(sentence.char_span.0, sentence.byte_span.0);
+                }
+
+                CheckedSentence {
+                    text_range: sentence.char_span,
+                    tokens,
+                    suggestions,
+                }
+            })
+            .collect()
+    }
+
+    /// Compute the suggestions for a text like [Rules::suggest], stopping early once the limits are reached,
+    /// see [Rules::apply_with_limits]. The limits apply per sentence except for `max_suggestions`, which bounds
+    /// the suggestions for the whole text. The [sentence cache][Rules::set_sentence_cache] is not used.
+    pub fn suggest_with_limits(
+        &self,
+        text: &str,
+        tokenizer: &Tokenizer,
+        limits: &Limits,
+    ) -> LimitedSuggestions {
+        let mut output = LimitedSuggestions::default();
+
+        for context in self.sentences_in_context(text, tokenizer) {
+            let sentence = context.sentence;
+            let limited = self.check_in_context(context, tokenizer, limits).1;
+
+            output
+                .suggestions
+                .extend(limited.suggestions.into_iter().map(|mut suggestion| {
+                    suggestion.shift(sentence.char_span.0, sentence.byte_span.0);
+                    suggestion
+                }));
+            output.truncated |= limited.truncated;
+            output.truncated_rules.extend(limited.truncated_rules);
+            output.errors.extend(limited.errors);
+
+            if let Some(max_suggestions) = limits.max_suggestions {
+                if output.suggestions.len() > max_suggestions {
+                    output.suggestions.truncate(max_suggestions);
+                    output.truncated = true;
+                    break;
+                }
+            }
+        }
+
+        output.truncated_rules.sort();
+        output.truncated_rules.dedup();
+        output
+    }
+
+    /// Compute the suggestions for a text like [Rules::suggest] and collect the problems of single rules,
+    /// e. g. failed synthesis, so a service can report them while still returning the other suggestions.
+    /// Set [RulesOptions::catch_rule_panics] to also recover from panics of single rules.
+    /// The [sentence cache][Rules::set_sentence_cache] is not used.
+    pub fn suggest_partial(&self, text: &str, tokenizer: &Tokenizer) -> PartialResult {
+        let limited = self.suggest_with_limits(text, tokenizer, &Limits::default());
+
+        PartialResult {
+            suggestions: limited.suggestions,
+            errors: limited.errors,
+        }
+    }
+
+    /// Exercises the tokenizer, the disambiguator and the rules so allocations and lazily initialized state
+    /// do not slow down the first real check, e. g. before a service starts taking traffic.
+    /// If `sample_texts` is empty, the first example of every rule is used instead.
+    pub fn warm_up(&self, sample_texts: &[&str], tokenizer: &Tokenizer) {
+        if sample_texts.is_empty() {
+            for rule in &self.rules {
+                if let Some(example) = rule.examples().first() {
+                    self.suggest(example.text(), tokenizer);
+                }
+            }
+        } else {
+            for text in sample_texts {
+                self.suggest(text, tokenizer);
+            }
+        }
+    }
+
+    /// Correct a text by applying only the suggestions of [safe][Rule::safe] rules.
+    /// The other suggestions are returned for review.
+    pub fn autocorrect(&self, text: &str, tokenizer: &Tokenizer) -> Autocorrection {
+        let safe: DefaultHashSet<_> = self
+            .rules
+            .iter()
+            .filter(|x| x.safe())
+            .map(|x| x.id())
+            .collect();
+
+        let (applied, mut remaining): (Vec<_>, Vec<_>) = self
+            .suggest(text, tokenizer)
+            .into_iter()
+            .partition(|x| safe.contains(x.source.as_str()) && !x.replacements.is_empty());
+        let Correction { text, offsets } =
+            correct_with(text, &applied, |x| x.replacements.first().cloned());
+        let map = |index| offsets.map(index);
+        let converter = SpanConverter::new(&text);
+        for suggestion in remaining.iter_mut() {
+            suggestion.start = map(suggestion.start);
+            suggestion.end = map(suggestion.end);
+            suggestion.set_byte_span(&converter);
+            suggestion.sentence_span = (
+                map(suggestion.sentence_span.0),
+                map(suggestion.sentence_span.1),
+            );
+        }
+
+        Autocorrection { text, remaining }
+    }
+
+    /// Check a text from a reader paragraph by paragraph without reading it into memory at once.
+    /// The returned iterator yields suggestions with character indices relative to the start of the input.
+    pub fn suggest_stream<'a, R: Read>(
+        &'a self,
+        reader: R,
+        tokenizer: &'a Tokenizer,
+    ) -> StreamChecker<'a, R> {
+        StreamChecker::new(reader, self, tokenizer)
+    }
+
+    /// Correct a text by first tokenizing, then finding all suggestions and choosing the first replacement of each suggestion.
+    pub fn correct(&self, text: &str, tokenizer: &Tokenizer) -> String {
+        let suggestions = self.suggest(text, tokenizer);
+        apply_suggestions(text, &suggestions)
+    }
+}
+
+/// Gets the ranges of tokens the rules are applied to, see [RulesOptions::max_sentence_tokens].
+/// The special first token at index 0 is not part of the ranges, it is added to every window.
+fn window_ranges(n_tokens: usize, options: &RulesOptions) -> Vec<(usize, usize)> {
+    let max = match options.max_sentence_tokens {
+        Some(max) if n_tokens - 1 > max => max.max(1),
+        _ => return vec![(1, n_tokens)],
+    };
+
+    match options.long_sentences {
+        LongSentences::Skip => Vec::new(),
+        LongSentences::Truncate => vec![(1, 1 + max)],
+        LongSentences::Windows { overlap } => {
+            let step = max.saturating_sub(overlap).max(1);
+            let mut ranges = Vec::new();
+            let mut start = 1;
+
+            loop {
+                let end = (start + max).min(n_tokens);
+                ranges.push((start, end));
+                if end == n_tokens {
+                    break ranges;
+                }
+                start += step;
+            }
+        }
+    }
+}
+
+/// Whether all tokens overlapping the char span are inside quotation marks.
+fn in_quotes(tokens: &[Token], start: usize, end: usize) -> bool {
+    let mut overlapping = tokens
+        .iter()
+        .filter(|x| x.char_span.0 < end.max(start + 1) && x.char_span.1 > start)
+        .peekable();
+
+    overlapping.peek().is_some() && overlapping.all(|x| x.quote_depth > 0)
+}
+
+/// Whether all tokens overlapping the char span are in an ALL CAPS span. `all_caps` has a flag for each token.
+fn in_all_caps(tokens: &[Token], all_caps: &[bool], start: usize, end: usize) -> bool {
+    let mut overlapping = tokens
+        .iter()
+        .zip(all_caps)
+        .filter(|(x, _)| x.char_span.0 < end.max(start + 1) && x.char_span.1 > start)
+        .peekable();
+
+    overlapping.peek().is_some() && overlapping.all(|(_, flag)| *flag)
+}
+
+/// Gets the indices of the tokens overlapping the char span. For an insertion, gets an empty span
+/// before the first token starting at or after the insertion point.
+fn token_span(tokens: &[Token], start: usize, end: usize) -> (usize, usize) {
+    // the special sentence start token at index 0 is never covered
+    let first = (1..tokens.len())
+        .find(|i| {
+            let span = tokens[*i].char_span;
+            if start == end {
+                span.0 >= start
+            } else {
+                span.1 > start
+            }
+        })
+        .unwrap_or(tokens.len());
+    let n_covered = tokens[first..]
+        .iter()
+        .take_while(|x| x.char_span.0 < end)
+        .count();
+
+    (first, first + n_covered)
+}
+
+/// Moves the spans of suggestions outward to grapheme cluster boundaries so applying them does not split
+/// e. g. flag emoji or characters with combining marks. An insertion inside a cluster is moved before the cluster.
+/// The suggestions must be sorted and not overlap. If two suggestions overlap after moving, the later one is dropped.
+pub(crate) fn snap_to_graphemes(text: &str, suggestions: &[Suggestion]) -> Vec<Suggestion> {
+    let mut boundaries = vec![false; text.chars().count() + 1];
+    let mut char_index = 0;
+    for grapheme in text.graphemes(true) {
+        boundaries[char_index] = true;
+        char_index += grapheme.chars().count();
+    }
+    boundaries[char_index] = true;
+
+    let converter = SpanConverter::new(text);
+    let mut prev_end = 0;
+    let mut output = Vec::new();
+
+    for suggestion in suggestions {
+        let mut suggestion = suggestion.clone();
+        let is_insertion = suggestion.start == suggestion.end;
+
+        while !boundaries[suggestion.start] {
+            suggestion.start -= 1;
+        }
+        if is_insertion {
+            suggestion.end = suggestion.start;
+        }
+        while !boundaries[suggestion.end] {
+            suggestion.end += 1;
+        }
+
+        if suggestion.start >= prev_end {
+            prev_end = suggestion.end;
+            suggestion.set_byte_span(&converter);
+            output.push(suggestion);
+        }
+    }
+
+    output
+}
+
+/// An edit of a [correction][correct_with]: the replaced char span in the original text and the char span of the
+/// replacement in the corrected text.
+pub type Edit = ((usize, usize), (usize, usize));
+
+/// Maps char indices in a text to char indices in the text after a [correction][correct_with], e. g. to move a
+/// cursor or annotations of the text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OffsetMap {
+    // sorted by position
+    edits: Vec<Edit>,
+}
+
+impl OffsetMap {
+    /// Maps a char index in the original text to the corrected text. Indices after an insertion or at the end of a
+    /// replaced span are moved after the replacement, indices inside a replaced span to the end of the replacement.
+    pub fn map(&self, index: usize) -> usize {
+        let n_before = self.edits.partition_point(|(old, _)| old.1 <= index);
+
+        match self.edits.get(n_before) {
+            Some((old, new)) if old.0 < index => new.1,
+            _ => match n_before.checked_sub(1).map(|i| self.edits[i]) {
+                Some((old, new)) => index - old.1 + new.1,
+                None => index,
+            },
+        }
+    }
+
+    /// Gets the applied edits as char spans in the original and in the corrected text, sorted by position.
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+}
+
+/// The result of [correct_with].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Correction {
+    /// The corrected text.
+    pub text: String,
+    /// Maps char indices in the original text to the corrected text.
+    pub offsets: OffsetMap,
+}
+
+/// Corrects a text by applying suggestions to it with the replacement `chooser` returns for each suggestion, or
+/// skipping the suggestion if it returns `None`. `chooser` is called in the order of the suggestions and is not called
+/// for suggestions overlapping one applied before. A suggestion which starts or ends inside a grapheme cluster replaces
+/// the whole cluster, see [Suggestion::end] for how insertions are applied.
+///
+/// ```
+/// use nlprule::{rules::correct_with, types::Suggestion};
+///
+/// let suggestion = |start, end, replacement: &str| Suggestion {
+///     start,
+///     end,
+///     replacements: vec![replacement.to_string()],
+///     ..Default::default()
+/// };
+/// let suggestions = vec![suggestion(0, 3, "This"), suggestion(8, 12, "text")];
+///
+/// // skip the second suggestion
+/// let correction = correct_with("Thi is a txet.", &suggestions, |x| {
+///     Some(x.replacements[0].clone()).filter(|_| x.start == 0)
+/// });
+/// assert_eq!(correction.text, "This is a txet.");
+/// assert_eq!(correction.offsets.map(9), 10);
+/// ```
+pub fn correct_with<F>(text: &str, suggestions: &[Suggestion], mut chooser: F) -> Correction
+where
+    F: FnMut(&Suggestion) -> Option<String>,
+{
+    let mut mask = SpanMask::new(text.chars().count());
+    let mut chosen: Vec<_> = suggestions
+        .iter()
+        .filter_map(|x| {
+            if !mask.is_free((x.start, x.end)) {
+                return None;
+            }
+            let replacement = chooser(x)?;
+            mask.insert((x.start, x.end));
+
+            let mut suggestion = x.clone();
+            suggestion.replacements = vec![replacement];
+            Some(suggestion)
+        })
+        .collect();
+    chosen.sort_by(|a, b| a.cmp_position(b));
+
+    let chars: Vec<_> = text.chars().collect();
+    let mut output = String::new();
+    let mut edits = Vec::new();
+    let (mut prev_end, mut new_len) = (0, 0);
+
+    for suggestion in snap_to_graphemes(text, &chosen) {
+        output.extend(&chars[prev_end..suggestion.start]);
+        let new_start = new_len + suggestion.start - prev_end;
+        let replacement = &suggestion.replacements[0];
+        output.push_str(replacement);
+        new_len = new_start + replacement.chars().count();
+
+        edits.push(((suggestion.start, suggestion.end), (new_start, new_len)));
+        prev_end = suggestion.end;
+    }
+    output.extend(&chars[prev_end..]);
+
+    Correction {
+        text: output,
+        offsets: OffsetMap { edits },
+    }
+}
+
+/// Correct a text by applying suggestions to it.
+/// In the case of multiple possible replacements, always chooses the first one.
+/// Suggestions without replacements and suggestions overlapping a previous one are skipped. A suggestion which starts or ends inside a grapheme cluster
+/// replaces the whole cluster, see [Suggestion::end] for how insertions are applied. See [correct_with] to choose
+/// the replacements and map indices to the corrected text.
+pub fn apply_suggestions(text: &str, suggestions: &[Suggestion]) -> String {
+    correct_with(text, suggestions, |x| x.replacements.first().cloned()).text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_insertions() {
+        let suggestion = |start, end, replacement: &str| Suggestion {
+            start,
+            end,
+            replacements: vec![replacement.to_string()],
+            ..Default::default()
+        };
+
+        // the insertion at 5 is applied before the suggestion starting at 5
+        let suggestions = vec![
+            suggestion(0, 0, "Oh "),
+            suggestion(5, 5, ","),
+            suggestion(5, 11, " World"),
+            suggestion(12, 12, "!"),
+        ];
+
+        assert_eq!(
+            apply_suggestions("hello world.", &suggestions),
+            "Oh hello, World.!"
+        );
+    }
+
+    #[test]
+    fn skips_overlapping_suggestions() {
+        let suggestion = |start, end, replacement: &str| Suggestion {
+            start,
+            end,
+            replacements: vec![replacement.to_string()],
+            ..Default::default()
+        };
+
+        // e. g. from [OverlapPolicy::KeepAll]
+        let suggestions = vec![suggestion(0, 5, "Hello"), suggestion(2, 11, "y")];
+
+        assert_eq!(
+            apply_suggestions("hello world.", &suggestions),
+            "Hello world."
+        );
+    }
+
+    #[test]
+    fn imports_exported_settings() {
+        let mut rules = Rules::default();
+        rules.options_mut().trim_suggestions = true;
+
+        let settings = rules.export_settings();
+        let settings: RulesSettings =
+            bincode::deserialize(&bincode::serialize(&settings).unwrap()).unwrap();
+
+        let mut other = Rules::default();
+        assert!(other.import_settings(&settings).is_empty());
+        assert_eq!(rules.fingerprint(), other.fingerprint());
+
+        let settings = RulesSettings {
+            disabled: vec!["MISSING_RULE".to_string()],
+            ..settings
+        };
+        assert_eq!(
+            other.import_settings(&settings),
+            vec!["MISSING_RULE".to_string()]
+        );
+    }
+
+    #[test]
+    fn splits_long_sentences_into_windows() {
+        let mut options = RulesOptions {
+            max_sentence_tokens: Some(4),
+            long_sentences: LongSentences::Windows { overlap: 1 },
+            ..RulesOptions::default()
+        };
+        assert_eq!(window_ranges(5, &options), vec![(1, 5)]);
+        assert_eq!(window_ranges(11, &options), vec![(1, 5), (4, 8), (7, 11)]);
+
+        options.long_sentences = LongSentences::Truncate;
+        assert_eq!(window_ranges(11, &options), vec![(1, 5)]);
+        options.long_sentences = LongSentences::Skip;
+        assert!(window_ranges(11, &options).is_empty());
+    }
+
+    #[test]
+    fn fingerprints_options() {
+        let mut a = Rules::default();
+        let mut b = Rules::default();
+        for key in &["x", "y"] {
+            a.options_mut()
+                .severities
+                .insert(key.to_string(), Severity::Hint);
+        }
+        for key in &["y", "x"] {
+            b.options_mut()
+                .severities
+                .insert(key.to_string(), Severity::Hint);
+        }
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        b.options_mut().trim_suggestions = true;
+        assert_ne!(a.fingerprint(), b.fingerprint());
+
+        struct Keep;
+
+        impl Reranker for Keep {
+            fn rerank(&self, _tokens: &[Token], suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+                suggestions
+            }
+        }
+
+        let fingerprint = a.fingerprint();
+        a.set_reranker(Keep);
+        assert_ne!(a.fingerprint(), fingerprint);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn caches_sentences_per_tokenizer() {
+        use crate::compile::testing;
+
+        let mut tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        rules.set_sentence_cache(Some(8));
+
+        let text = "Read tëh book.";
+        assert!(rules.suggest(text, &tokenizer).is_empty());
+
+        // the cached suggestions must not be reused with different tokenizer options
+        let fingerprint = tokenizer.fingerprint();
+        tokenizer.set_options(crate::tokenizer::TokenizerOptions {
+            normalization: vec![('ë', "e".to_string())],
+            ..tokenizer.options().clone()
+        });
+        assert_ne!(tokenizer.fingerprint(), fingerprint);
+        assert_eq!(rules.suggest(text, &tokenizer).len(), 1);
+
+        // nor after the priorities changed
+        let fingerprint = rules.fingerprint();
+        rules.rule_mut("TEH").unwrap().priority = 1;
+        assert_ne!(rules.fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn applies_suggestions_to_grapheme_clusters() {
+        let suggestion = |start, end, replacement: &str| Suggestion {
+            start,
+            end,
+            replacements: vec![replacement.to_string()],
+            ..Default::default()
+        };
+
+        // replacing one regional indicator replaces the whole flag
+        assert_eq!(
+            apply_suggestions("🇩🇪 flag", &[suggestion(1, 2, "DE")]),
+            "DE flag"
+        );
+        // the combining accent belongs to the replaced word
+        assert_eq!(
+            apply_suggestions("cafe\u{301} ok", &[suggestion(0, 4, "coffee")]),
+            "coffee ok"
+        );
+        // insertions inside a cluster are moved before it
+        assert_eq!(
+            apply_suggestions("a 👍🏽 b", &[suggestion(3, 3, "!")]),
+            "a !👍🏽 b"
+        );
+        // clusters in between are kept intact
+        assert_eq!(
+            apply_suggestions(
+                "👨‍👩‍👧 teh 🇫🇷",
+                &[suggestion(6, 9, "the"), suggestion(10, 12, "France")]
+            ),
+            "👨‍👩‍👧 the France"
+        );
+    }
+
+    #[test]
+    fn maps_offsets() {
+        let suggestion = |start, end, replacement: &str| Suggestion {
+            start,
+            end,
+            replacements: vec![replacement.to_string()],
+            ..Default::default()
+        };
+
+        // unsorted, the overlapping (1, 3) is skipped since (0, 2) is chosen first
+        let suggestions = vec![
+            suggestion(6, 6, "!!"),
+            suggestion(0, 2, "x"),
+            suggestion(1, 3, "y"),
+            suggestion(3, 4, "z"),
+        ];
+        let mut calls = 0;
+        let correction = correct_with("abcdef", &suggestions, |x| {
+            calls += 1;
+            Some(x.replacements[0].clone()).filter(|_| x.start != 3)
+        });
+
+        assert_eq!(calls, 3);
+        assert_eq!(correction.text, "xcdef!!");
+        assert_eq!(
+            correction.offsets.edits(),
+            &[((0, 2), (0, 1)), ((6, 6), (5, 7))]
+        );
+        let mapped: Vec<_> = (0..=6).map(|i| correction.offsets.map(i)).collect();
+        assert_eq!(mapped, vec![0, 1, 1, 2, 3, 4, 7]);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn ranks_replacements() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[("house", "house", "NN")]);
+        let ranking = Ranking::default();
+
+        // known word, similar and same case > unknown, similar and same case > unknown and different case
+        assert!((ranking.score("hous", "house", &tokenizer) - 2.3).abs() < 1e-6);
+        assert!((ranking.score("hous", "mouse", &tokenizer) - 1.1).abs() < 1e-6);
+        let mut replacements = vec!["mouse".to_string(), "Hose".to_string(), "house".to_string()];
+        ranking.rank("hous", &mut replacements, &tokenizer);
+        assert_eq!(replacements, vec!["house", "mouse", "Hose"]);
+
+        let xml = r#"<rule id="HOUS" name="hous">
+            <pattern><token>hous</token></pattern>
+            <message>Did you mean <suggestion>mouse</suggestion> or <suggestion>house</suggestion>?</message>
+            <example correction="mouse|house">A <marker>hous</marker>.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        let replacements =
+            |rules: &Rules| rules.suggest("A hous.", &tokenizer)[0].replacements.clone();
+        assert_eq!(replacements(&rules), vec!["mouse", "house"]);
+
+        rules.options_mut().ranking = Some(ranking);
+        assert_eq!(replacements(&rules), vec!["house", "mouse"]);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn drops_noop_replacements() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="COLOR" name="color">
+            <pattern><token>color</token></pattern>
+            <message>Use <suggestion>color</suggestion> or <suggestion>colour</suggestion>.</message>
+            <example correction="color|colour">A <marker>color</marker>.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        let replacements = |rules: &Rules| {
+            rules
+                .suggest("A color.", &tokenizer)
+                .into_iter()
+                .flat_map(|x| x.replacements)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(replacements(&rules), vec!["colour"]);
+
+        rules.options_mut().keep_noop_suggestions = true;
+        assert_eq!(replacements(&rules), vec!["color", "colour"]);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn applies_message_hook() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        let message = |rules: &Rules| {
+            rules.suggest("Read teh book.", &tokenizer)[0]
+                .message
+                .clone()
+        };
+        assert_eq!(message(&rules), "Did you mean the?");
+
+        rules.set_message_hook(|id, message| format!("{} See {}.", message, id));
+        assert_eq!(message(&rules), "Did you mean the? See TEH.");
+
+        rules.clear_message_hook();
+        assert_eq!(message(&rules), "Did you mean the?");
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn selects_localized_messages() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rulegroup id="TEH" name="teh">
+            <rule>
+                <pattern><token>teh</token></pattern>
+                <message>Did you mean <suggestion>the</suggestion>?</message>
+                <example correction="the">Read <marker>teh</marker> book.</example>
+            </rule>
+        </rulegroup>
+        <rule id="HTE" name="hte">
+            <pattern><token>hte</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>hte</marker> book.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        // the message for the group ID is used for the rules in the group
+        let mut messages = DefaultHashMap::default();
+        messages.insert("TEH".to_string(), "Meinten Sie \"the\"?".to_string());
+        rules.add_localized_messages("de", &messages);
+        assert_eq!(
+            rules
+                .rules()
+                .iter()
+                .map(|x| x.message_locales().collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            vec![vec!["de"], vec![]]
+        );
+
+        let messages = |rules: &Rules| {
+            rules
+                .suggest("Read teh hte book.", &tokenizer)
+                .into_iter()
+                .map(|x| x.message)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(&rules),
+            vec!["Did you mean the?", "Did you mean the?"]
+        );
+
+        // rules without a message in the locale fall back to the original message
+        rules.options_mut().message_locale = Some("de".to_string());
+        assert_eq!(
+            messages(&rules),
+            vec!["Meinten Sie \"the\"?", "Did you mean the?"]
+        );
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn counts_synthesis_failures() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        // the replacement references a group which does not exist
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Possible typo.</message>
+            <suggestion><match no="1" regexp_match="t(eh)" regexp_replace="$5"/></suggestion>
+            <example>Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        let failures = |rules: &Rules| rules.rules()[0].synthesis_failures();
+
+        assert!(rules.suggest("Read teh teh book.", &tokenizer).is_empty());
+        assert_eq!(
+            failures(&rules),
+            crate::rule::SynthesisFailures {
+                missing_pos_form: 0,
+                regex_replace: 2,
+            }
+        );
+
+        rules.rules()[0].reset_synthesis_failures();
+        assert_eq!(failures(&rules).total(), 0);
+
+        rules.options_mut().message_only_fallback = true;
+        let suggestions = rules.suggest("Read teh book.", &tokenizer);
+        assert_eq!(
+            suggestions
+                .iter()
+                .map(|x| (x.start, x.end, x.message.as_str(), x.replacements.len()))
+                .collect::<Vec<_>>(),
+            vec![(5, 8, "Possible typo.", 0)]
+        );
+        assert_eq!(failures(&rules).regex_replace, 1);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn warms_up_with_examples() {
+        use crate::compile::testing;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+            <example>Read the book.</example>
+        </rule>
+        <rule id="HTE" name="hte">
+            <pattern><token>hte</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>hte</marker> book.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        rules.set_message_hook(move |_, message| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            message
+        });
+
+        // only the first example of each rule is checked
+        rules.warm_up(&[], &tokenizer);
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 2);
+
+        rules.warm_up(&["teh hte teh", "Fine."], &tokenizer);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn checks_suggestions_before_reranking() {
+        use crate::compile::testing;
+
+        struct Reverse;
+
+        impl Reranker for Reverse {
+            fn rerank(
+                &self,
+                _tokens: &[Token],
+                mut suggestions: Vec<Suggestion>,
+            ) -> Vec<Suggestion> {
+                suggestions.reverse();
+                suggestions
+            }
+        }
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        rules.set_reranker(Reverse);
+
+        // the returned suggestions do not have to be sorted, so this must not fail the debug assertions
+        let starts: Vec<_> = rules
+            .suggest("Read teh teh book.", &tokenizer)
+            .iter()
+            .map(|x| x.start)
+            .collect();
+        assert_eq!(starts, vec![9, 5]);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn reranks_overlapping_suggestions_with_keep_all() {
+        use crate::compile::testing;
+        use std::sync::Mutex;
+
+        struct Record(Arc<Mutex<Vec<(usize, usize)>>>);
+
+        impl Reranker for Record {
+            fn rerank(&self, _tokens: &[Token], suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .extend(suggestions.iter().map(|x| (x.start, x.end)));
+                suggestions
+            }
+        }
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>
+        <rule id="TEH_BOOK" name="teh book">
+            <pattern><token>teh</token><token>book</token></pattern>
+            <message>Did you mean <suggestion>the book</suggestion>?</message>
+            <example correction="the book">Read <marker>teh book</marker>.</example>
+        </rule>"#;
+        let options = RulesOptions {
+            overlap_policy: OverlapPolicy::KeepAll,
+            ..RulesOptions::default()
+        };
+        let mut rules = testing::rules(xml, &tokenizer, options);
+        let reranked = Arc::new(Mutex::new(Vec::new()));
+        rules.set_reranker(Record(reranked.clone()));
+
+        let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize("Read teh book.")));
+        let spans: Vec<_> = rules
+            .apply(&tokens, &tokenizer)
+            .iter()
+            .map(|x| (x.start, x.end))
+            .collect();
+        assert_eq!(spans, vec![(5, 8), (5, 13)]);
+        assert_eq!(*reranked.lock().unwrap(), spans);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn emits_applied_rules_after_masking() {
+        use crate::compile::testing;
+        use std::sync::Mutex;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="EVENT_SHORT" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>
+        <rule id="EVENT_LONG" name="teh book">
+            <pattern><token>teh</token><token>book</token></pattern>
+            <message>Did you mean <suggestion>the book</suggestion>?</message>
+            <example correction="the book">Read <marker>teh book</marker>.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let sink = applied.clone();
+        events::set_event_callback(move |event| {
+            if let RuleEvent::RuleApplied {
+                rule_id,
+                start,
+                end,
+            } = event
+            {
+                if rule_id.starts_with("EVENT_") {
+                    sink.lock()
+                        .unwrap()
+                        .push((rule_id.to_string(), *start, *end));
+                }
+            }
+        });
+        let suggestions = rules.suggest("Read teh book.", &tokenizer);
+        events::clear_event_callback();
+
+        // only the suggestion which survived overlap resolution is reported
+        let kept: Vec<_> = suggestions
+            .iter()
+            .map(|x| (x.source.clone(), x.start, x.end))
+            .collect();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(*applied.lock().unwrap(), kept);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn diffs_rule_sets() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let rule = |id: &str, word: &str| {
+            format!(
+                r#"<rule id="{id}" name="{word}">
+                    <pattern><token>{word}</token></pattern>
+                    <message>Did you mean <suggestion>the</suggestion>?</message>
+                    <example correction="the">Read <marker>{word}</marker> book.</example>
+                </rule>"#,
+                id = id,
+                word = word
+            )
+        };
+        let rules =
+            |xml: &[String]| testing::rules(&xml.concat(), &tokenizer, RulesOptions::default());
+
+        let old = rules(&[rule("TEH", "teh"), rule("HTE", "hte"), rule("TEE", "tee")]);
+        let new = rules(&[rule("TEH", "teh"), rule("HTE", "thh"), rule("ETH", "eth")]);
+
+        // rule sets compiled separately from the same XML are equal
+        assert!(old
+            .diff(&rules(&[
+                rule("TEH", "teh"),
+                rule("HTE", "hte"),
+                rule("TEE", "tee")
+            ]))
+            .is_empty());
+        assert_eq!(
+            old.diff(&new),
+            RulesDiff {
+                added: vec!["ETH".to_string()],
+                removed: vec!["TEE".to_string()],
+                changed: vec!["HTE".to_string()],
+            }
+        );
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn validates_groups_on_load() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        assert!(rules.rules[0].missing_groups().is_empty());
+        let loaded = Rules::new_from(&bincode::serialize(&rules).unwrap()[..]).unwrap();
+        assert_eq!(loaded.rules().len(), 1);
+
+        rules.rules[0].start = 42;
+        rules.rules[0].end = 43;
+        let error = Rules::new_from(&bincode::serialize(&rules).unwrap()[..])
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("TEH (groups [42])"), "{}", error);
+
+        rules.rules[0].end = rules.rules[0].start;
+        assert!(rules.rules[0].missing_groups().contains(&42));
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn filters_rules_by_category() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        // the rules are wrapped in the TEST category, so the second rule gets its own category
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>
+        </category>
+        <category id="WORDINESS" name="Wordiness" type="style">
+        <rule id="VERY_UNIQUE" name="very unique">
+            <pattern><token>very</token><token>unique</token></pattern>
+            <message>Did you mean <suggestion>unique</suggestion>?</message>
+            <example correction="unique">It is <marker>very unique</marker>.</example>
+        </rule>"#;
+        let mut rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let rule = &rules.rules()[1];
+        assert_eq!(rule.category_id(), "WORDINESS");
+        assert_eq!(rule.category_name(), "Wordiness");
+        assert_eq!(rule.category_type(), Some("style"));
+        assert!(rule.in_category("WORDINESS") && rule.in_category("style"));
+        assert!(!rule.in_category("TEST"));
+        assert_eq!(rules.rules()[0].category_type(), None);
+
+        let tokens =
+            finalize(tokenizer.disambiguate(tokenizer.tokenize("Teh cake is very unique.")));
+        let mut sources = |categories: &[&str], ignore_categories: &[&str]| {
+            let options = rules.options_mut();
+            options.categories = categories.iter().map(|x| x.to_string()).collect();
+            options.ignore_categories = ignore_categories.iter().map(|x| x.to_string()).collect();
+            rules
+                .apply(&tokens, &tokenizer)
+                .into_iter()
+                .map(|x| x.source)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sources(&[], &[]), vec!["TEH", "VERY_UNIQUE"]);
+        assert_eq!(sources(&["TEST"], &[]), vec!["TEH"]);
+        assert_eq!(sources(&["style"], &[]), vec!["VERY_UNIQUE"]);
+        assert_eq!(sources(&[], &["style"]), vec!["TEH"]);
+        // ignored categories take precedence
+        assert!(sources(&["TEST"], &["TEST"]).is_empty());
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn applies_rules_to_serialized_analysis() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[("read", "read", "VB")]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token postag="VB"/><marker><token>teh</token></marker></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">I read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let text = "I read teh book.";
+        let analysis = tokenizer.analyze(text);
+        let analysis: Analysis =
+            bincode::deserialize(&bincode::serialize(&analysis).unwrap()).unwrap();
+        let suggestions = rules.apply_tokens(&analysis, &tokenizer).unwrap();
+        assert_eq!(suggestions, rules.suggest(text, &tokenizer));
+        assert_eq!(suggestions.len(), 1);
+
+        // the tags are looked up again, so an analysis with unknown tags is rejected
+        let mut analysis = analysis;
+        for token in analysis.tokens.iter_mut() {
+            for data in token.word.tags.iter_mut() {
+                data.pos.0 = "NOT_A_TAG".to_string();
+            }
+        }
+        assert!(rules.apply_tokens(&analysis, &tokenizer).is_err());
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn scans_long_sentences_in_chunks() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH_TEH" name="teh teh">
+            <pattern><token>teh</token><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh teh</marker> book.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        // overlapping matches, some of them across the chunk boundaries
+        let text = (0..200)
+            .map(|i| if i % 7 == 3 { "a" } else { "teh" })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(&text)));
+        // longer than two chunks of the scan
+        assert!(tokens.len() > 128);
+        let refs: Vec<_> = tokens.iter().collect();
+        let rule = &rules.rules()[0];
+        let spans = |max_matches| {
+            rule.engine
+                .get_matches(&refs, rule.start, rule.end, None, max_matches)
+                .0
+                .iter()
+                .map(|graph| {
+                    (
+                        graph.by_id(rule.start).unwrap().char_span.0,
+                        graph.by_id(rule.end - 1).unwrap().char_span.1,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // a limit on the matches scans serially
+        let chunked = spans(None);
+        assert_eq!(chunked, spans(Some(usize::MAX)));
+        assert_eq!(chunked.len(), 85);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn stops_matching_at_the_limit() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+
+        let text = "teh ".repeat(1000);
+        let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(&text)));
+        let refs: Vec<_> = tokens.iter().collect();
+        let rule = &rules.rules()[0];
+        assert_eq!(
+            rule.engine
+                .get_matches(&refs, rule.start, rule.end, None, None)
+                .0
+                .len(),
+            1000
+        );
+        // scanning stops after the limit instead of finding all matches first
+        assert_eq!(
+            rule.engine
+                .get_matches(&refs, rule.start, rule.end, None, Some(3))
+                .0
+                .len(),
+            3
+        );
+
+        let limits = Limits {
+            max_matches_per_rule: Some(2),
+            ..Limits::default()
+        };
+        let output = rules.apply_with_limits(&tokens, &tokenizer, &limits);
+        let starts: Vec<_> = output.suggestions.iter().map(|x| x.start).collect();
+        assert_eq!(starts, vec![0, 4]);
+        assert!(output.truncated);
+        assert_eq!(output.truncated_rules, vec!["TEH".to_string()]);
+
+        let limits = Limits {
+            max_matches_per_rule: Some(1000),
+            ..Limits::default()
+        };
+        let output = rules.apply_with_limits(&tokens, &tokenizer, &limits);
+        assert_eq!(output.suggestions.len(), 1000);
+        assert!(!output.truncated);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn blocks_matches_if_antipatterns_exceed_the_step_limit() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <antipattern><token>read</token><token>teh</token><token>book</token></antipattern>
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Write <marker>teh</marker> book.</example>
+            <example>Read teh book.</example>
+        </rule>"#;
+        let rules = testing::rules(xml, &tokenizer, RulesOptions::default());
+        let apply = |text: &str, max_match_steps| {
+            let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(text)));
+            let limits = Limits {
+                max_match_steps,
+                ..Limits::default()
+            };
+            rules.apply_with_limits(&tokens, &tokenizer, &limits)
+        };
+
+        assert!(apply("Read teh book.", None).suggestions.is_empty());
+        assert_eq!(apply("Write teh book.", Some(3)).suggestions.len(), 1);
+
+        // the pattern fits into the limit, the antipattern does not
+        let output = apply("Read teh book.", Some(3));
+        assert!(output.suggestions.is_empty());
+        assert_eq!(
+            output.errors,
+            vec![RuleError::StepLimitExceeded {
+                rule_id: "TEH".to_string(),
+                count: 1
+            }]
+        );
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn collects_rule_errors() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>
+        <rule id="ADN" name="adn">
+            <pattern><token>adn</token></pattern>
+            <message>Possible typo.</message>
+            <suggestion><match no="1" regexp_match="a(dn)" regexp_replace="$5"/></suggestion>
+            <example>Read <marker>adn</marker> write.</example>
+        </rule>
+        <rule id="ALOT" name="alot">
+            <pattern><token>alot</token></pattern>
+            <message>Did you mean <suggestion>a lot</suggestion>?</message>
+            <example correction="a lot">I like it <marker>alot</marker>.</example>
+        </rule>"#;
+        let options = RulesOptions {
+            catch_rule_panics: true,
+            ..RulesOptions::default()
+        };
+        let mut rules = testing::rules(xml, &tokenizer, options);
+        // the marker references a group which does not exist, so applying the rule panics
+        let rule = rules.rules.iter_mut().find(|x| x.id == "TEH").unwrap();
+        rule.start = 10;
+        rule.end = 11;
+
+        let result = rules.suggest_partial("Read teh adn alot.", &tokenizer);
+        assert_eq!(
+            result
+                .suggestions
+                .iter()
+                .map(|x| (x.source.as_str(), x.start, x.end))
+                .collect::<Vec<_>>(),
+            vec![("ALOT", 13, 17)]
+        );
+
+        assert_eq!(result.errors.len(), 2);
+        match &result.errors[0] {
+            RuleError::Panicked { rule_id, message } => {
+                assert_eq!(rule_id, "TEH");
+                assert!(message.contains("group must exist in graph"));
+            }
+            error => panic!("unexpected error: {:?}", error),
+        }
+        assert_eq!(
+            result.errors[1],
+            RuleError::SynthesisFailed {
+                rule_id: "ADN".to_string(),
+                count: 1
+            }
+        );
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn autocorrects_with_safe_rules() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>
+        <rule id="ALOT" name="alot">
+            <pattern><token>alot</token></pattern>
+            <message>Did you mean <suggestion>a lot</suggestion>?</message>
+            <example correction="a lot">I like it <marker>alot</marker>.</example>
+        </rule>"#;
+        let options = RulesOptions {
+            safe_ids: vec!["ALOT".to_string()],
+            unsafe_ids: vec!["TEH".to_string()],
+            ..RulesOptions::default()
+        };
+        let rules = testing::rules(xml, &tokenizer, options);
+
+        let text = "Ünits alot teh book.";
+        let corrected = rules.autocorrect(text, &tokenizer);
+        assert_eq!(corrected.text, "Ünits a lot teh book.");
+
+        // the suggestion of the unsafe rule is moved behind the inserted char
+        assert_eq!(
+            corrected
+                .remaining
+                .iter()
+                .map(|x| (
+                    x.source.as_str(),
+                    x.start,
+                    x.end,
+                    x.byte_span,
+                    x.sentence_span
+                ))
+                .collect::<Vec<_>>(),
+            vec![("TEH", 12, 15, (13, 16), (0, 21))]
+        );
+        let remaining = &corrected.remaining[0];
+        assert_eq!(
+            &corrected.text[remaining.byte_span.0..remaining.byte_span.1],
+            "teh"
+        );
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn resolves_severities() {
+        use crate::checks::Brackets;
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>
+        </category>
+        <category id="STYLE" name="Style" type="style">
+        <rule id="ALOT" name="alot">
+            <pattern><token>alot</token></pattern>
+            <message>Did you mean <suggestion>a lot</suggestion>?</message>
+            <example correction="a lot">I like it <marker>alot</marker>.</example>
+        </rule>
+        </category>
+        <category id="WORDINESS" name="Wordiness" type="style">
+        <rule id="ADN" name="adn">
+            <pattern><token>adn</token></pattern>
+            <message>Did you mean <suggestion>and</suggestion>?</message>
+            <example correction="and">Read <marker>adn</marker> write.</example>
+        </rule>"#;
+        let severities = [
+            ("STYLE", Severity::Error),
+            ("style", Severity::Warning),
+            ("UNPAIRED_BRACKETS", Severity::Error),
+        ];
+        let options = RulesOptions {
+            severities: severities
+                .iter()
+                .map(|(key, severity)| (key.to_string(), *severity))
+                .collect(),
+            default_severity: Some(Severity::Hint),
+            native_checks: NativeChecks {
+                brackets: Some(Brackets::english()),
+                ..NativeChecks::default()
+            },
+            ..RulesOptions::default()
+        };
+        let mut rules = testing::rules(xml, &tokenizer, options);
+        let severities = |rules: &Rules| {
+            rules
+                .suggest("Read teh book alot adn (more.", &tokenizer)
+                .into_iter()
+                .map(|x| (x.source, x.severity))
+                .collect::<Vec<_>>()
+        };
+
+        // the category ID takes precedence over the category type
+        assert_eq!(
+            severities(&rules),
+            vec![
+                ("TEH".to_string(), Some(Severity::Hint)),
+                ("ALOT".to_string(), Some(Severity::Error)),
+                ("ADN".to_string(), Some(Severity::Warning)),
+                ("UNPAIRED_BRACKETS".to_string(), Some(Severity::Error)),
+            ]
+        );
+
+        rules.options_mut().default_severity = None;
+        assert_eq!(severities(&rules)[0], ("TEH".to_string(), None));
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn title_cases_only_title_case_headings() {
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="ALOT" name="alot">
+            <pattern><token>alot</token></pattern>
+            <message>Did you mean <suggestion>a lot</suggestion>?</message>
+            <example correction="a lot">I like it <marker>alot</marker>.</example>
+        </rule>"#;
+        let options = RulesOptions {
+            heading: true,
+            ..RulesOptions::default()
+        };
+        let rules = testing::rules(xml, &tokenizer, options);
+
+        let replacements = |text: &str| {
+            let tokens = finalize(tokenizer.disambiguate(tokenizer.tokenize(text)));
+            rules.apply(&tokens, &tokenizer)[0].replacements.clone()
+        };
+        assert_eq!(replacements("Having Alot of Fun"), vec!["A Lot"]);
+        // sentence case headings keep sentence case
+        assert_eq!(replacements("Having alot of fun"), vec!["a lot"]);
+        assert_eq!(replacements("Alot of fun"), vec!["A lot"]);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn checks_sentences_in_context() {
+        use crate::checks::{Brackets, Typography};
+        use crate::compile::testing;
+
+        let tokenizer = testing::tokenizer(&[]);
+        let xml = r#"<rule id="TEH" name="teh">
+            <pattern><token>teh</token></pattern>
+            <message>Did you mean <suggestion>the</suggestion>?</message>
+            <example correction="the">Read <marker>teh</marker> book.</example>
+        </rule>"#;
+        let options = RulesOptions {
+            skip_in_quotes: vec!["TEST".to_string()],
+            native_checks: NativeChecks {
+                brackets: Some(Brackets::english()),
+                typography: Some(Typography::english()),
+                ..NativeChecks::default()
+            },
+            ..RulesOptions::default()
+        };
+        let rules = testing::rules(xml, &tokenizer, options);
+        let spans = |text: &str| {
+            rules
+                .suggest(text, &tokenizer)
+                .into_iter()
+                .map(|x| (x.source, x.start, x.end))
+                .collect::<Vec<_>>()
+        };
+
+        // the quote and the space after the first sentence are not flagged in the sentences on their own
+        assert!(spans("This is one. He said \"Stop. Now.\"").is_empty());
+        // the quotation spans sentences
+        assert_eq!(
+            spans("He said \"Read teh book. Read teh book.\" Read teh book."),
+            vec![("TEH".to_string(), 45, 48)]
+        );
+        assert_eq!(
+            spans("This is one.  (Two. "),
+            vec![
+                ("TYPOGRAPHY".to_string(), 12, 14),
+                ("UNPAIRED_BRACKETS".to_string(), 14, 15),
+                ("TYPOGRAPHY".to_string(), 19, 20),
+            ]
+        );
+
+        let mut checker = crate::incremental::IncrementalChecker::new(
+            "He said \"Read teh book. Read teh book.",
+            &rules,
+            &tokenizer,
+        );
+        // the quote is unpaired so the quotation is ignored
+        assert_eq!(checker.suggestions().len(), 3);
+        // closing the quotation changes the context of the unchanged sentences
+        checker.edit((38, 38), "\"").unwrap();
+        assert!(checker.suggestions().is_empty());
+    }
+
+    #[test]
+    fn samples_deterministically() {
+        let sampler = |rate, seed| Sampler {
+            rate,
+            seed,
+            hook: Arc::new(|_: &SampledMatch| {}),
+        };
+        let sampled = |sampler: &Sampler| -> Vec<usize> {
+            (0..1000)
+                .filter(|&i| sampler.samples("RULE", "A sentence.", i))
+                .collect()
+        };
+
+        let a = sampled(&sampler(0.1, 42));
+        assert_eq!(a, sampled(&sampler(0.1, 42)));
+        assert_ne!(a, sampled(&sampler(0.1, 43)));
+        assert!(a.len() > 50 && a.len() < 150);
+        assert!(sampled(&sampler(0.0, 42)).is_empty());
+        assert_eq!(sampled(&sampler(1.0, 42)).len(), 1000);
+    }
 }
@@ -0,0 +1,228 @@
+//! Expressions which select rules, e. g. to turn on the rules of a category except some rules.
+
+use crate::{rule::Rule, Error};
+use std::{fmt, str::FromStr};
+
+/// Selects rules by ID, category and default state. Can be parsed from an expression like
+/// `category:STYLE AND NOT id:PASSIVE_VOICE` with the atoms
+/// - `id:<ID>` for the rule with this ID,
+/// - `category:<ID or type>` for the rules in a category, see [Rule::in_category],
+/// - `default` for the rules which are on by default, see [Rule::default_on],
+/// - `all` for all rules,
+///
+/// combined with `NOT`, `AND` and `OR` (in order of precedence, case-insensitive) and parentheses.
+///
+/// ```no_run
+/// use nlprule::{selector::RuleSelector, Rules};
+///
+/// let mut rules = Rules::new("path/to/en_rules.bin")?;
+/// let selector: RuleSelector = "default OR (category:STYLE AND NOT id:PASSIVE_VOICE)".parse()?;
+/// rules.select_on(&selector);
+/// # Ok::<(), nlprule::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleSelector {
+    /// Selects all rules.
+    All,
+    /// Selects the rules which are on by default.
+    Default,
+    /// Selects the rule with this ID.
+    Id(String),
+    /// Selects the rules in the category with this ID or of this type.
+    Category(String),
+    /// Selects the rules the inner selector does not select.
+    Not(Box<RuleSelector>),
+    /// Selects the rules both selectors select.
+    And(Box<RuleSelector>, Box<RuleSelector>),
+    /// Selects the rules either selector selects.
+    Or(Box<RuleSelector>, Box<RuleSelector>),
+}
+
+impl RuleSelector {
+    /// Whether the rule is selected.
+    pub fn matches(&self, rule: &Rule) -> bool {
+        match self {
+            RuleSelector::All => true,
+            RuleSelector::Default => rule.default_on(),
+            RuleSelector::Id(id) => rule.id() == id,
+            RuleSelector::Category(category) => rule.in_category(category),
+            RuleSelector::Not(inner) => !inner.matches(rule),
+            RuleSelector::And(a, b) => a.matches(rule) && b.matches(rule),
+            RuleSelector::Or(a, b) => a.matches(rule) || b.matches(rule),
+        }
+    }
+}
+
+impl fmt::Display for RuleSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleSelector::All => write!(f, "all"),
+            RuleSelector::Default => write!(f, "default"),
+            RuleSelector::Id(id) => write!(f, "id:{}", id),
+            RuleSelector::Category(category) => write!(f, "category:{}", category),
+            RuleSelector::Not(inner) => write!(f, "NOT {}", inner),
+            RuleSelector::And(a, b) => write!(f, "({} AND {})", a, b),
+            RuleSelector::Or(a, b) => write!(f, "({} OR {})", a, b),
+        }
+    }
+}
+
+/// Splits an expression into words and parentheses.
+fn lex(expression: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (i, c) in expression.char_indices() {
+        if c.is_whitespace() || c == '(' || c == ')' {
+            if let Some(start) = start.take() {
+                tokens.push(&expression[start..i]);
+            }
+            if !c.is_whitespace() {
+                tokens.push(&expression[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&expression[start..]);
+    }
+
+    tokens
+}
+
+/// A recursive descent parser with one function per precedence level.
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidOptions(format!(
+            "invalid rule selector at token {}: {}",
+            self.index, message
+        ))
+    }
+
+    fn next_is(&self, keyword: &str) -> bool {
+        match self.tokens.get(self.index) {
+            Some(token) => token.eq_ignore_ascii_case(keyword),
+            None => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<RuleSelector, Error> {
+        let mut selector = self.and()?;
+        while self.next_is("OR") {
+            self.index += 1;
+            selector = RuleSelector::Or(Box::new(selector), Box::new(self.and()?));
+        }
+        Ok(selector)
+    }
+
+    fn and(&mut self) -> Result<RuleSelector, Error> {
+        let mut selector = self.not()?;
+        while self.next_is("AND") {
+            self.index += 1;
+            selector = RuleSelector::And(Box::new(selector), Box::new(self.not()?));
+        }
+        Ok(selector)
+    }
+
+    fn not(&mut self) -> Result<RuleSelector, Error> {
+        if self.next_is("NOT") {
+            self.index += 1;
+            return Ok(RuleSelector::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<RuleSelector, Error> {
+        let token = *self
+            .tokens
+            .get(self.index)
+            .ok_or_else(|| self.error("unexpected end"))?;
+        self.index += 1;
+
+        if token == "(" {
+            let selector = self.or()?;
+            if !self.next_is(")") {
+                return Err(self.error("expected \")\""));
+            }
+            self.index += 1;
+            return Ok(selector);
+        }
+
+        if token.eq_ignore_ascii_case("all") {
+            Ok(RuleSelector::All)
+        } else if token.eq_ignore_ascii_case("default") {
+            Ok(RuleSelector::Default)
+        } else if let Some(id) = token.strip_prefix("id:").filter(|x| !x.is_empty()) {
+            Ok(RuleSelector::Id(id.to_string()))
+        } else if let Some(category) = token.strip_prefix("category:").filter(|x| !x.is_empty()) {
+            Ok(RuleSelector::Category(category.to_string()))
+        } else {
+            self.index -= 1;
+            Err(self.error(&format!("unexpected {:?}", token)))
+        }
+    }
+}
+
+impl FromStr for RuleSelector {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: lex(expression),
+            index: 0,
+        };
+
+        let selector = parser.or()?;
+        if parser.index < parser.tokens.len() {
+            let token = parser.tokens[parser.index];
+            return Err(parser.error(&format!("unexpected {:?}", token)));
+        }
+        Ok(selector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_expressions() {
+        let id = |x: &str| Box::new(RuleSelector::Id(x.to_string()));
+        let category = |x: &str| Box::new(RuleSelector::Category(x.to_string()));
+
+        assert_eq!(
+            "category:STYLE AND NOT id:PASSIVE_VOICE"
+                .parse::<RuleSelector>()
+                .unwrap(),
+            RuleSelector::And(
+                category("STYLE"),
+                Box::new(RuleSelector::Not(id("PASSIVE_VOICE")))
+            )
+        );
+        assert_eq!(
+            "id:A or id:B and (default OR all)"
+                .parse::<RuleSelector>()
+                .unwrap(),
+            RuleSelector::Or(
+                id("A"),
+                Box::new(RuleSelector::And(
+                    id("B"),
+                    Box::new(RuleSelector::Or(
+                        Box::new(RuleSelector::Default),
+                        Box::new(RuleSelector::All)
+                    ))
+                ))
+            )
+        );
+
+        for invalid in &["", "id:", "id:A AND", "(id:A", "id:A)", "name:A"] {
+            assert!(invalid.parse::<RuleSelector>().is_err());
+        }
+    }
+}
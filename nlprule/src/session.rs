@@ -0,0 +1,154 @@
+//! Interactive correction of a text, suggestion by suggestion.
+
+use crate::{rules::snap_to_graphemes, types::*, Rules, Tokenizer};
+use std::collections::VecDeque;
+
+/// Holds a text and its suggestions and lets the caller accept, edit or skip the suggestions one by one.
+/// Accepted edits change the text, the indices of the following suggestions are updated accordingly.
+///
+/// ```no_run
+/// use nlprule::{session::CorrectionSession, Rules, Tokenizer};
+///
+/// let tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+/// let rules = Rules::new("path/to/en_rules.bin")?;
+///
+/// let mut session = CorrectionSession::new("She was not been here since Monday.", &rules, &tokenizer);
+/// while let Some(suggestion) = session.current() {
+///     println!("{}", suggestion.message);
+///     session.accept(0);
+/// }
+///
+/// assert_eq!(session.text(), "She was not here since Monday.");
+/// # Ok::<(), nlprule::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CorrectionSession {
+    text: String,
+    pending: VecDeque<Suggestion>,
+}
+
+impl CorrectionSession {
+    /// Creates a new session by computing the suggestions for the text.
+    pub fn new(text: &str, rules: &Rules, tokenizer: &Tokenizer) -> Self {
+        CorrectionSession::from_suggestions(text, rules.suggest(text, tokenizer))
+    }
+
+    /// Creates a new session from a text and its suggestions.
+    /// The suggestions must not overlap, like the suggestions returned by [Rules::suggest].
+    pub fn from_suggestions(text: &str, mut suggestions: Vec<Suggestion>) -> Self {
+        suggestions.sort_by_key(|x| x.start);
+
+        CorrectionSession {
+            text: text.to_string(),
+            pending: suggestions.into(),
+        }
+    }
+
+    /// Gets the current text with all accepted edits applied.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Consumes the session and returns the current text.
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    /// Gets the current suggestion. Its indices refer to the current text.
+    /// Returns `None` if all suggestions have been handled.
+    pub fn current(&self) -> Option<&Suggestion> {
+        self.pending.front()
+    }
+
+    /// Gets the suggestions which have not been handled yet, including the current one.
+    pub fn remaining(&self) -> impl Iterator<Item = &Suggestion> {
+        self.pending.iter()
+    }
+
+    /// Accepts the replacement at `index` of the current suggestion and moves to the next suggestion.
+    ///
+    /// # Panics
+    /// If there is no current suggestion or it has no replacement at `index`.
+    pub fn accept(&mut self, index: usize) {
+        let replacement = self
+            .current()
+            .expect("session must have a current suggestion")
+            .replacements[index]
+            .clone();
+        self.edit(&replacement);
+    }
+
+    /// Replaces the text of the current suggestion with a custom `replacement` and moves to the next suggestion.
+    ///
+    /// # Panics
+    /// If there is no current suggestion.
+    pub fn edit(&mut self, replacement: &str) {
+        let suggestion = self
+            .pending
+            .pop_front()
+            .expect("session must have a current suggestion");
+        // the span only changes if it starts or ends inside a grapheme cluster
+        let suggestion = snap_to_graphemes(&self.text, &[suggestion]).remove(0);
+
+        let (byte_start, byte_end) =
+            SpanConverter::new(&self.text).byte_span((suggestion.start, suggestion.end));
+        self.text.replace_range(byte_start..byte_end, replacement);
+
+        let delta =
+            replacement.chars().count() as isize - (suggestion.end - suggestion.start) as isize;
+        let map = |index: usize| {
+            if index >= suggestion.end {
+                (index as isize + delta) as usize
+            } else {
+                index
+            }
+        };
+
+        let converter = SpanConverter::new(&self.text);
+        for pending in self.pending.iter_mut() {
+            pending.start = map(pending.start);
+            pending.end = map(pending.end);
+            pending.set_byte_span(&converter);
+            pending.sentence_span = (map(pending.sentence_span.0), map(pending.sentence_span.1));
+        }
+    }
+
+    /// Skips the current suggestion without changing the text.
+    pub fn skip(&mut self) {
+        self.pending.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_following_suggestions() {
+        let suggestion = |start, end, replacement: &str| Suggestion {
+            start,
+            end,
+            replacements: vec![replacement.to_string()],
+            ..Default::default()
+        };
+
+        let mut session = CorrectionSession::from_suggestions(
+            "Thiss is ä tset of teh session.",
+            vec![
+                suggestion(0, 5, "This"),
+                suggestion(11, 15, "test"),
+                suggestion(19, 22, "the"),
+            ],
+        );
+
+        session.accept(0);
+        assert_eq!(session.current().map(|x| (x.start, x.end)), Some((10, 14)));
+        // "ä" has two bytes
+        assert_eq!(session.current().map(|x| x.byte_span), Some((11, 15)));
+        session.skip();
+        session.edit("THE");
+
+        assert!(session.current().is_none());
+        assert_eq!(session.text(), "This is ä tset of THE session.");
+    }
+}
@@ -0,0 +1,105 @@
+//! Golden tests for rule output. A snapshot is a canonical text representation of the suggestions for every
+//! line of a corpus. Comparing it against a checked-in golden file makes behavior changes visible as diffs.
+//!
+//! Set the `NLPRULE_BLESS` environment variable to write the golden file instead of comparing against it.
+
+use crate::{types::*, Rules, Tokenizer};
+use std::{fs, path::Path};
+
+/// Formats the suggestions for one text. Suggestions are sorted by position and source so the output is stable.
+pub fn format_suggestions(text: &str, suggestions: &[Suggestion]) -> String {
+    let mut suggestions: Vec<_> = suggestions.iter().collect();
+    suggestions.sort_by(|a, b| (a.start, a.end, &a.source).cmp(&(b.start, b.end, &b.source)));
+
+    let mut output = format!("> {}\n", text);
+    for suggestion in suggestions {
+        output.push_str(&format!(
+            "{}..{} {} [{}] {}\n",
+            suggestion.start,
+            suggestion.end,
+            suggestion.source,
+            suggestion.replacements.join(" | "),
+            suggestion.message
+        ));
+    }
+    output
+}
+
+/// Computes the snapshot of a corpus with one text per line. Empty lines are skipped.
+pub fn snapshot(rules: &Rules, tokenizer: &Tokenizer, corpus: &str) -> String {
+    corpus
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| format_suggestions(line, &rules.suggest(line, tokenizer)))
+        .collect()
+}
+
+/// Compares the snapshot of the corpus at `corpus_path` with the golden file at `golden_path`.
+///
+/// # Panics
+/// If the snapshot differs from the golden file, showing the first differing line.
+pub fn assert_snapshot<P: AsRef<Path>, Q: AsRef<Path>>(
+    rules: &Rules,
+    tokenizer: &Tokenizer,
+    corpus_path: P,
+    golden_path: Q,
+) {
+    let corpus = fs::read_to_string(corpus_path).expect("corpus must be readable");
+    let actual = snapshot(rules, tokenizer, &corpus);
+
+    if std::env::var_os("NLPRULE_BLESS").is_some() {
+        fs::write(golden_path, actual).expect("golden file must be writable");
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden_path).unwrap_or_default();
+    if actual != expected {
+        let (i, (expected_line, actual_line)) = expected
+            .lines()
+            .chain(std::iter::repeat(""))
+            .zip(actual.lines().chain(std::iter::repeat("")))
+            .enumerate()
+            .find(|(_, (a, b))| a != b)
+            .expect("snapshots differ so some line must differ");
+
+        panic!(
+            "snapshot differs from {} at line {}:\n- {}\n+ {}\nRun with NLPRULE_BLESS=1 to update the golden file.",
+            golden_path.as_ref().display(),
+            i + 1,
+            expected_line,
+            actual_line
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_sorted_suggestions() {
+        let suggestions = vec![
+            Suggestion {
+                source: "B".into(),
+                message: "Second.".into(),
+                start: 4,
+                end: 7,
+                replacements: vec!["x".into(), "y".into()],
+                ..Default::default()
+            },
+            Suggestion {
+                source: "A".into(),
+                message: "First.".into(),
+                start: 0,
+                end: 3,
+                replacements: vec!["z".into()],
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            format_suggestions("abc def", &suggestions),
+            "> abc def\n0..3 A [z] First.\n4..7 B [x | y] Second.\n"
+        );
+    }
+}
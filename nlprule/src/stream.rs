@@ -0,0 +1,253 @@
+//! Checking of large inputs without reading them into memory at once.
+
+use crate::{types::*, utils::parallelism::MaybeParallelIterator, Rules, Tokenizer};
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read},
+};
+
+/// Reads a text paragraph by paragraph and checks each paragraph with a set of rules.
+/// Paragraphs are separated by blank lines. Only the current paragraph is kept in memory.
+///
+/// Iterates over the suggestions with character indices relative to the start of the whole input.
+/// Created by [Rules::suggest_stream].
+pub struct StreamChecker<'a, R: Read> {
+    reader: BufReader<R>,
+    rules: &'a Rules,
+    tokenizer: &'a Tokenizer,
+    max_paragraph_chars: usize,
+    paragraphs_in_flight: usize,
+    offset: usize,
+    byte_offset: usize,
+    done: bool,
+    error: Option<io::Error>,
+    buffer: VecDeque<Suggestion>,
+}
+
+impl<'a, R: Read> StreamChecker<'a, R> {
+    pub(crate) fn new(reader: R, rules: &'a Rules, tokenizer: &'a Tokenizer) -> Self {
+        StreamChecker {
+            reader: BufReader::new(reader),
+            rules,
+            tokenizer,
+            max_paragraph_chars: 100_000,
+            paragraphs_in_flight: 1,
+            offset: 0,
+            byte_offset: 0,
+            done: false,
+            error: None,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Sets the number of characters after which a paragraph is checked even if no blank line has been reached.
+    /// At most this many characters are read into memory for a paragraph, so a longer line is split (at a
+    /// character boundary, possibly inside a word). Defaults to 100 000.
+    pub fn max_paragraph_chars(mut self, max_paragraph_chars: usize) -> Self {
+        self.max_paragraph_chars = max_paragraph_chars;
+        self
+    }
+
+    /// Sets how many paragraphs are read ahead and checked in parallel. Bounds the memory use to
+    /// roughly this many paragraphs. The order and indices of the suggestions do not depend on this setting.
+    /// Defaults to 1 i. e. paragraphs are checked one after another.
+    pub fn paragraphs_in_flight(mut self, paragraphs_in_flight: usize) -> Self {
+        self.paragraphs_in_flight = std::cmp::max(paragraphs_in_flight, 1);
+        self
+    }
+
+    /// Reads the next paragraph. Returns the character index of its start and its text.
+    fn next_paragraph(&mut self) -> io::Result<Option<(usize, String)>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let start = self.offset;
+        let mut paragraph = String::new();
+        let mut n_chars = 0;
+        // the byte index in `paragraph` where the current line starts
+        let mut line_start = 0;
+        let mut bytes = Vec::new();
+
+        loop {
+            // a char has at least one byte, so this reads at most the remaining number of chars
+            let limit = std::cmp::max(self.max_paragraph_chars.saturating_sub(n_chars), 1);
+            bytes.clear();
+            let chunk = match read_chunk(&mut self.reader, limit, &mut bytes)? {
+                Some(chunk) => chunk,
+                None => {
+                    self.done = true;
+                    break;
+                }
+            };
+
+            let chunk_chars = chunk.chars().count();
+            self.offset += chunk_chars;
+            n_chars += chunk_chars;
+            paragraph.push_str(chunk);
+
+            if chunk.ends_with('\n') {
+                if paragraph[line_start..].trim().is_empty() {
+                    break;
+                }
+                line_start = paragraph.len();
+            }
+
+            if n_chars >= self.max_paragraph_chars {
+                break;
+            }
+        }
+
+        if paragraph.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((start, paragraph)))
+        }
+    }
+}
+
+/// Reads up to the end of the line, but at most `limit` bytes plus the rest of a char split by the limit.
+/// Returns `None` at the end of the input.
+fn read_chunk<'b, R: BufRead>(
+    reader: &mut R,
+    limit: usize,
+    bytes: &'b mut Vec<u8>,
+) -> io::Result<Option<&'b str>> {
+    if reader.take(limit as u64).read_until(b'\n', bytes)? == 0 {
+        return Ok(None);
+    }
+
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(_) => break,
+            // the chunk ends inside a char
+            Err(error) if error.error_len().is_none() => {
+                if reader.take(1).read_until(b'\n', bytes)? == 0 {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    std::str::from_utf8(bytes).map(Some).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })
+}
+
+impl<'a, R: Read> Iterator for StreamChecker<'a, R> {
+    type Item = io::Result<Suggestion>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.is_empty() {
+            if let Some(error) = self.error.take() {
+                return Some(Err(error));
+            }
+
+            let mut paragraphs = Vec::new();
+            while paragraphs.len() < self.paragraphs_in_flight {
+                match self.next_paragraph() {
+                    Ok(Some(paragraph)) => paragraphs.push(paragraph),
+                    Ok(None) => break,
+                    Err(error) => {
+                        // report the error after the paragraphs read so far
+                        self.done = true;
+                        self.error = Some(error);
+                        break;
+                    }
+                }
+            }
+
+            if paragraphs.is_empty() && self.error.is_none() {
+                return None;
+            }
+
+            // paragraphs are read in order, so their byte offsets are the sums of the previous lengths
+            let paragraphs: Vec<_> = paragraphs
+                .into_iter()
+                .map(|(start, paragraph)| {
+                    let byte_start = self.byte_offset;
+                    self.byte_offset += paragraph.len();
+                    (start, byte_start, paragraph)
+                })
+                .collect();
+
+            let (rules, tokenizer) = (self.rules, self.tokenizer);
+            let suggestions: Vec<Vec<Suggestion>> = paragraphs
+                .into_maybe_par_iter()
+                .map(|(start, byte_start, paragraph)| {
+                    let mut suggestions = rules.suggest(&paragraph, tokenizer);
+                    for suggestion in suggestions.iter_mut() {
+                        suggestion.shift(start, byte_start);
+                    }
+                    suggestions
+                })
+                .collect();
+
+            self.buffer.extend(suggestions.into_iter().flatten());
+        }
+
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_paragraphs_with_absolute_offsets() {
+        let rules = Rules::default();
+        let tokenizer = Tokenizer::default();
+        let text = "Ä first.\nStill first.\n\nSecond.\n";
+
+        let mut checker = StreamChecker::new(text.as_bytes(), &rules, &tokenizer);
+        let mut paragraphs = Vec::new();
+        while let Some(paragraph) = checker.next_paragraph().unwrap() {
+            paragraphs.push(paragraph);
+        }
+
+        assert_eq!(
+            paragraphs,
+            vec![
+                (0, "Ä first.\nStill first.\n\n".to_string()),
+                (23, "Second.\n".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_long_lines() {
+        let rules = Rules::default();
+        let tokenizer = Tokenizer::default();
+        let text = "abcdefäöüéxyz\n\nEnd.";
+
+        let mut checker =
+            StreamChecker::new(text.as_bytes(), &rules, &tokenizer).max_paragraph_chars(4);
+        let mut paragraphs = Vec::new();
+        while let Some(paragraph) = checker.next_paragraph().unwrap() {
+            paragraphs.push(paragraph);
+        }
+
+        // multi-byte chars are not split
+        assert_eq!(
+            paragraphs,
+            vec![
+                (0, "abcd".to_string()),
+                (4, "efäö".to_string()),
+                (8, "üéxy".to_string()),
+                (12, "z\n\n".to_string()),
+                (15, "End.".to_string())
+            ]
+        );
+
+        let mut checker = StreamChecker::new(&b"ab\xffcd"[..], &rules, &tokenizer);
+        assert_eq!(
+            checker.next_paragraph().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
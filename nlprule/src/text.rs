@@ -0,0 +1,244 @@
+//! Text helpers used to post-process suggestions, e. g. to compare replacements with the original text
+//! or to clean up whitespace after applying them. All functions work on chars, not bytes.
+
+use lazy_static::lazy_static;
+use onig::{Captures, Regex};
+
+/// Applies `func` to the first char of the string and keeps the rest. Returns an empty string for an empty string.
+///
+/// ```
+/// use nlprule::text::apply_to_first;
+///
+/// assert_eq!(apply_to_first("élan", |c| c.to_uppercase().collect()), "Élan");
+/// ```
+// see https://stackoverflow.com/questions/38406793/why-is-capitalizing-the-first-letter-of-a-string-so-convoluted-in-rust
+pub fn apply_to_first<F>(string: &str, func: F) -> String
+where
+    F: Fn(char) -> String,
+{
+    let mut c = string.chars();
+    match c.next() {
+        None => String::new(),
+        Some(first) => func(first) + c.as_str(),
+    }
+}
+
+/// Whether the first char is uppercase. `false` for an empty string.
+pub fn starts_uppercase(string: &str) -> bool {
+    string.chars().next().filter(|x| x.is_uppercase()).is_some()
+}
+
+/// Words which stay lowercase in title case unless they are the first word, e. g. "Rules of the Road".
+const MINOR_WORDS: [&str; 22] = [
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "onto", "or", "over", "the", "to", "up", "via", "with",
+];
+
+fn is_minor_word(word: &str) -> bool {
+    MINOR_WORDS.contains(&word.to_lowercase().as_str())
+}
+
+/// Makes the first char of every space-separated word uppercase except for minor words such as "of" or "the".
+/// The other chars are not changed.
+///
+/// ```
+/// use nlprule::text::title_case_words;
+///
+/// assert_eq!(title_case_words("rules of the road"), "Rules of the Road");
+/// ```
+pub fn title_case_words(string: &str) -> String {
+    string
+        .split(' ')
+        .map(|word| {
+            if is_minor_word(word) {
+                word.to_string()
+            } else {
+                apply_to_first(word, |c| c.to_uppercase().collect())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether the text is in Title Case i. e. every word except minor words such as "of" or "the" starts uppercase,
+/// e. g. "Rules of the Road" but not "Rules of the road". Words which do not start with a letter are ignored.
+pub fn is_title_case_heading(text: &str) -> bool {
+    let mut words = text
+        .split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-')
+        .filter(|x| x.chars().next().is_some_and(char::is_alphabetic));
+
+    match words.next() {
+        Some(first) => {
+            starts_uppercase(first) && words.all(|x| is_minor_word(x) || starts_uppercase(x))
+        }
+        None => false,
+    }
+}
+
+/// Whether the first char is uppercase and no other char is, e. g. "Hello" but not "HeLLo" or "hello".
+pub fn is_title_case(string: &str) -> bool {
+    let mut char_case = string.chars().map(|x| x.is_uppercase());
+
+    char_case.next().unwrap_or(false) && !char_case.any(|x| x)
+}
+
+/// Whether no char is lowercase. Also `true` for strings without letters, e. g. "123".
+pub fn is_uppercase(string: &str) -> bool {
+    !string.chars().any(|x| x.is_lowercase())
+}
+
+/// The number of single char insertions, deletions and substitutions to get from `a` to `b`.
+///
+/// ```
+/// use nlprule::text::levenshtein;
+///
+/// assert_eq!(levenshtein("teh", "the"), 2);
+/// ```
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = std::cmp::min(std::cmp::min(prev[j + 1] + 1, cur[j] + 1), prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+/// The number of chars of whole words shared by `original` and all `candidates` at the start and at the end.
+/// The shared prefix ends after whitespace and the shared suffix starts with whitespace. At least one char of
+/// `original` is never shared. Used to trim replacements to the words which change.
+///
+/// ```
+/// use nlprule::text::common_word_affixes;
+///
+/// let candidates = vec!["She has not been here".to_string()];
+/// assert_eq!(common_word_affixes("She was not been here", &candidates), (4, 14));
+/// ```
+pub fn common_word_affixes(original: &str, candidates: &[String]) -> (usize, usize) {
+    let original: Vec<char> = original.chars().collect();
+    let candidates: Vec<Vec<char>> = candidates.iter().map(|x| x.chars().collect()).collect();
+
+    if original.is_empty() || candidates.is_empty() {
+        return (0, 0);
+    }
+
+    let max_prefix = candidates
+        .iter()
+        .map(|x| x.len())
+        .fold(original.len() - 1, std::cmp::min);
+    let mut prefix = (0..max_prefix)
+        .take_while(|i| candidates.iter().all(|x| x[*i] == original[*i]))
+        .count();
+    while prefix > 0 && !original[prefix - 1].is_whitespace() {
+        prefix -= 1;
+    }
+
+    let max_suffix = candidates
+        .iter()
+        .map(|x| x.len() - prefix)
+        .fold(original.len() - prefix - 1, std::cmp::min);
+    let mut suffix = (1..=max_suffix)
+        .take_while(|i| {
+            candidates
+                .iter()
+                .all(|x| x[x.len() - i] == original[original.len() - i])
+        })
+        .count();
+    while suffix > 0 && !original[original.len() - suffix].is_whitespace() {
+        suffix -= 1;
+    }
+
+    (prefix, suffix)
+}
+
+/// Replaces each run of whitespace with its first char, e. g. "a  \n b" with "a b".
+pub fn normalize_whitespace(string: &str) -> String {
+    lazy_static! {
+        static ref REGEX: Regex = Regex::new(r"(\s)\s+").unwrap();
+    }
+
+    REGEX.replace_all(string, |caps: &Captures| caps.at(1).unwrap().to_string())
+}
+
+/// Whether the char is an invisible bidirectional control char, e. g. the right-to-left mark U+200F which is common
+/// in Arabic and Hebrew text.
+pub fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Removes whitespace before chars which are never preceded by a space, e. g. "a , b" becomes "a, b".
+/// Bidirectional control chars between the whitespace and the char are kept.
+pub fn fix_nospace_chars(text: &str) -> String {
+    text.char_indices()
+        .filter(|(i, c)| {
+            if c.is_whitespace() {
+                let next = text[(i + c.len_utf8())..].trim_start_matches(is_bidi_control);
+                !crate::utils::no_space_chars()
+                    .chars()
+                    .any(|nospace_c| next.starts_with(nospace_c))
+            } else {
+                true
+            }
+        })
+        .map(|x| x.1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_cases_words() {
+        assert_eq!(title_case_words("it is a test"), "It Is a Test");
+        assert!(is_title_case_heading("Rules of the Road"));
+        assert!(is_title_case_heading("How to Use Rust's Borrow-Checker"));
+        assert!(!is_title_case_heading("Rules of the road"));
+        assert!(!is_title_case_heading("getting Started"));
+        assert!(!is_title_case_heading("123"));
+        assert!(starts_uppercase("Its"));
+        assert!(!starts_uppercase(""));
+    }
+
+    #[test]
+    fn finds_common_word_affixes() {
+        let candidates = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            common_word_affixes(
+                "She was not been here",
+                &candidates(&["She has not been here"])
+            ),
+            (4, 14)
+        );
+        assert_eq!(
+            common_word_affixes("the the", &candidates(&["the"])),
+            (0, 0)
+        );
+        assert_eq!(
+            common_word_affixes("a teh b", &candidates(&["a the b", "a tea b"])),
+            (2, 2)
+        );
+        assert_eq!(common_word_affixes("a b", &candidates(&["a b c"])), (2, 0));
+    }
+
+    #[test]
+    fn normalizes_whitespace() {
+        assert_eq!(normalize_whitespace("a  \n b\tc"), "a b\tc");
+        assert_eq!(fix_nospace_chars("a , b"), "a, b");
+        assert_eq!(fix_nospace_chars("א \u{200F}, ב"), "א\u{200F}, ב");
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert!(is_title_case("Hello") && !is_title_case("HeLLo"));
+        assert!(is_uppercase("ABC1") && !is_uppercase("AbC"));
+    }
+}
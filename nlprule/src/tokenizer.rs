@@ -4,13 +4,19 @@
 //! Tokens are *disambiguated* (i. e. information from the initial assignment is changed) in a rule-based way by
 //! [DisambiguationRule][crate::rule::DisambiguationRule]s.
 
-use crate::{types::*, utils::parallelism::MaybeParallelRefIterator};
+use crate::{
+    trace::{Trace, Verbosity},
+    types::*,
+    utils::{self, parallelism::MaybeParallelRefIterator},
+};
 use lazy_static::lazy_static;
 use onig::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     collections::HashSet,
     fs::File,
+    hash::{Hash, Hasher},
     io::{BufReader, Read},
     path::Path,
     sync::Arc,
@@ -18,10 +24,18 @@ use std::{
 use unicode_segmentation::UnicodeSegmentation;
 
 pub mod chunk;
+pub mod morph;
+pub mod placeholder;
+pub mod sentence;
 pub mod tag;
+pub mod upos;
 
 use chunk::Chunker;
+use morph::{Features, TagParser};
+use placeholder::Placeholders;
+use sentence::{Sentence, SentenceExceptions};
 use tag::Tagger;
+use upos::{Upos, UposMapping};
 
 use crate::rule::DisambiguationRule;
 
@@ -46,7 +60,28 @@ where
     result
 }
 
-fn get_token_strs(text: &str) -> Vec<&str> {
+fn get_token_strs<'t>(
+    text: &'t str,
+    placeholders: Option<&Placeholders>,
+    split_bidi_controls: bool,
+) -> Vec<&'t str> {
+    let mut tokens = Vec::new();
+    let mut prev = 0;
+
+    // placeholders are kept as one token like URLs
+    if let Some(placeholders) = placeholders {
+        for (start, end) in placeholders.find_iter(text) {
+            tokens.extend(split_urls(&text[prev..start], split_bidi_controls));
+            tokens.push(&text[start..end]);
+            prev = end;
+        }
+    }
+
+    tokens.extend(split_urls(&text[prev..], split_bidi_controls));
+    tokens
+}
+
+fn split_urls(text: &str, split_bidi_controls: bool) -> Vec<&str> {
     let mut tokens = Vec::new();
 
     lazy_static! {
@@ -55,7 +90,11 @@ fn get_token_strs(text: &str) -> Vec<&str> {
     }
 
     let mut prev = 0;
-    let split_func = |c: char| c.is_whitespace() || crate::utils::splitting_chars().contains(c);
+    let split_func = |c: char| {
+        c.is_whitespace()
+            || crate::utils::splitting_chars().contains(c)
+            || (split_bidi_controls && crate::text::is_bidi_control(c))
+    };
 
     for (start, end) in URL_REGEX.find_iter(text) {
         tokens.extend(split(&text[prev..start], split_func));
@@ -68,6 +107,101 @@ fn get_token_strs(text: &str) -> Vec<&str> {
     tokens
 }
 
+/// Replaces the chars of the text which are in the normalization map, see [TokenizerOptions::normalization].
+fn normalize<'t>(text: &'t str, normalization: &[(char, String)]) -> Cow<'t, str> {
+    let lookup = |c: char| normalization.iter().find(|(from, _)| *from == c);
+    if !text.chars().any(|c| lookup(c).is_some()) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars() {
+        match lookup(c) {
+            Some((_, to)) => normalized.push_str(to),
+            None => normalized.push(c),
+        }
+    }
+    Cow::Owned(normalized)
+}
+
+/// Maps opening quotation marks to the marks which can close them.
+/// Straight single quotes and `’` are not used to open quotations since they are mostly apostrophes.
+const QUOTES: [(&str, &str); 9] = [
+    ("\"", "\""),
+    ("“", "”"),
+    ("„", "“”"),
+    ("«", "»"),
+    ("»", "«"),
+    ("‹", "›"),
+    ("‘", "’"),
+    ("‚", "‘’"),
+    ("「", "」"),
+];
+
+/// Computes how many quotations each word is in. Quotation marks are outside the quotation they delimit.
+/// Quotations which are never closed are ignored.
+pub(crate) fn quote_depths(words: &[&str]) -> Vec<usize> {
+    let mut depths = Vec::with_capacity(words.len());
+    // the marks which close the open quotations and the index of their opening mark
+    let mut open: Vec<(&str, usize)> = Vec::new();
+
+    for (i, word) in words.iter().enumerate() {
+        let closes = open
+            .last()
+            .filter(|(closing, _)| word.chars().count() == 1 && closing.contains(word))
+            .is_some();
+
+        if closes {
+            open.pop();
+            depths.push(open.len());
+        } else {
+            depths.push(open.len());
+            if let Some((_, closing)) = QUOTES.iter().find(|(opening, _)| opening == word) {
+                open.push((closing, i));
+            }
+        }
+    }
+
+    for (_, start) in open {
+        for depth in &mut depths[start + 1..] {
+            *depth -= 1;
+        }
+    }
+
+    depths
+}
+
+/// Computes which words are in a run of at least `min_words` words written in capitals, e. g. a headline in ALL CAPS.
+/// Words without cased letters (e. g. punctuation) do not count towards the run but do not end it either.
+pub(crate) fn all_caps_words(words: &[&str], min_words: usize) -> Vec<bool> {
+    let mut all_caps = vec![false; words.len()];
+    // the indices of the capitalized words in the current run
+    let mut run: Vec<usize> = Vec::new();
+
+    for i in 0..=words.len() {
+        let (has_upper, has_lower) = match words.get(i) {
+            Some(word) => (
+                word.chars().any(char::is_uppercase),
+                word.chars().any(char::is_lowercase),
+            ),
+            None => (false, true),
+        };
+
+        if has_upper && !has_lower {
+            run.push(i);
+        } else if has_lower {
+            if run.len() >= min_words.max(1) {
+                for flag in &mut all_caps[run[0]..=run[run.len() - 1]] {
+                    *flag = true;
+                }
+            }
+            run.clear();
+        }
+    }
+
+    all_caps
+}
+
 /// *Finalizes* the tokens by e. g. adding a specific UNKNOWN part-of-speech tag.
 /// After finalization grammatical error correction rules can be used on the tokens.
 pub fn finalize(tokens: Vec<IncompleteToken>) -> Vec<Token> {
@@ -105,6 +239,36 @@ pub struct TokenizerOptions {
     /// Used part-of-speech tags which are not in the tagger dictionary.
     #[serde(default)]
     pub extra_tags: Vec<String>,
+    /// How to parse morphological features from the part-of-speech tags, see [Tokenizer::features].
+    #[serde(default)]
+    pub tag_parser: Option<TagParser>,
+    /// Whether to tag unknown hyphenated compounds (e. g. "COVID-19-Test") with the tags of their last element,
+    /// see [Tagger::get_hyphen_compound_tags][crate::tokenizer::tag::Tagger::get_hyphen_compound_tags].
+    #[serde(default)]
+    pub tag_hyphen_compounds: bool,
+    /// Whether to ignore bidirectional control chars (e. g. the right-to-left mark) in right-to-left text,
+    /// see [is_bidi_control][crate::text::is_bidi_control]. They are split off words and do not become tokens.
+    /// The spans of the tokens still refer to the text including the control chars.
+    #[serde(default)]
+    pub ignore_bidi_controls: bool,
+    /// Chars in words which are replaced before tagging and matching, e. g. curly quotes by straight quotes so rules
+    /// written with straight quotes also match curly ones. The spans and the text of suggestions still refer to the
+    /// original text. Regex rules which match the whole text are not affected. See [TokenizerOptions::default_normalization].
+    #[serde(default)]
+    pub normalization: Vec<(char, String)>,
+    /// Whether to tag and match words in ALL CAPS spans (see `min_all_caps_words`) as lowercase, since most words
+    /// are only in the tagger dictionary in lowercase. The replacements of suggestions in such spans are uppercased
+    /// again by [Rules][crate::Rules].
+    #[serde(default)]
+    pub lowercase_all_caps: bool,
+    /// The minimum number of consecutive words in capitals which are an ALL CAPS span, e. g. a headline.
+    /// See also [RulesOptions::skip_in_all_caps][crate::rules::RulesOptions::skip_in_all_caps].
+    #[serde(default = "default_min_all_caps_words")]
+    pub min_all_caps_words: usize,
+}
+
+fn default_min_all_caps_words() -> usize {
+    3
 }
 
 impl Default for TokenizerOptions {
@@ -118,10 +282,198 @@ impl Default for TokenizerOptions {
             ignore_ids: Vec::new(),
             known_failures: Vec::new(),
             extra_tags: Vec::new(),
+            tag_parser: None,
+            tag_hyphen_compounds: false,
+            ignore_bidi_controls: false,
+            normalization: Vec::new(),
+            lowercase_all_caps: false,
+            min_all_caps_words: default_min_all_caps_words(),
         }
     }
 }
 
+impl TokenizerOptions {
+    /// Creates a builder for tokenizer options, starting from the defaults.
+    pub fn builder() -> TokenizerOptionsBuilder {
+        TokenizerOptionsBuilder::default()
+    }
+
+    /// A normalization map for curly quotes, primes and zero-width chars, see [TokenizerOptions::normalization].
+    pub fn default_normalization() -> Vec<(char, String)> {
+        [
+            ('‘', "'"),
+            ('’', "'"),
+            ('‚', "'"),
+            ('‛', "'"),
+            ('′', "'"),
+            ('“', "\""),
+            ('”', "\""),
+            ('„', "\""),
+            ('‟', "\""),
+            ('″', "\""),
+            ('\u{200B}', ""),
+            ('\u{2060}', ""),
+            ('\u{FEFF}', ""),
+        ]
+        .iter()
+        .map(|(from, to)| (*from, to.to_string()))
+        .collect()
+    }
+}
+
+/// Builds and validates [TokenizerOptions].
+///
+/// `allow_errors`, `ids`, `ignore_ids` and `extra_tags` are only used when compiling a tokenizer.
+/// The other options can be changed on a compiled tokenizer with [Tokenizer::set_options], e. g.
+///
+/// ```no_run
+/// use nlprule::{tokenizer::TokenizerOptionsBuilder, Tokenizer};
+///
+/// let mut tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+/// let options = TokenizerOptionsBuilder::from(tokenizer.options().clone())
+///     .always_add_lower_tags(true)
+///     .build()?;
+/// tokenizer.set_options(options);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct TokenizerOptionsBuilder {
+    options: TokenizerOptions,
+}
+
+impl From<TokenizerOptions> for TokenizerOptionsBuilder {
+    fn from(options: TokenizerOptions) -> Self {
+        TokenizerOptionsBuilder { options }
+    }
+}
+
+impl TokenizerOptionsBuilder {
+    /// Sets whether to allow errors while compiling the tokenizer. Defaults to `false`.
+    pub fn allow_errors(mut self, allow_errors: bool) -> Self {
+        self.options.allow_errors = allow_errors;
+        self
+    }
+
+    /// Sets whether to retain the last tag if disambiguation would remove all tags of a token. Defaults to `false`.
+    pub fn retain_last(mut self, retain_last: bool) -> Self {
+        self.options.retain_last = retain_last;
+        self
+    }
+
+    /// Sets whether to split unknown words into known parts to tag potential compound words
+    /// (e. g. for German). Defaults to `false`.
+    pub fn use_compound_split_heuristic(mut self, use_compound_split_heuristic: bool) -> Self {
+        self.options.use_compound_split_heuristic = use_compound_split_heuristic;
+        self
+    }
+
+    /// Sets whether to also add the tags of the lowercase version of a word. Defaults to `false`.
+    pub fn always_add_lower_tags(mut self, always_add_lower_tags: bool) -> Self {
+        self.options.always_add_lower_tags = always_add_lower_tags;
+        self
+    }
+
+    /// Sets the IDs of the disambiguation rules to compile. All rules are compiled if this is empty (the default).
+    pub fn ids(mut self, ids: Vec<String>) -> Self {
+        self.options.ids = ids;
+        self
+    }
+
+    /// Sets the IDs of the disambiguation rules not to compile.
+    pub fn ignore_ids(mut self, ignore_ids: Vec<String>) -> Self {
+        self.options.ignore_ids = ignore_ids;
+        self
+    }
+
+    /// Sets the examples of disambiguation rules which are known to fail, in the notation `{id}:{example_index}`.
+    /// Failures of these examples are logged as warnings instead of errors when testing.
+    pub fn known_failures(mut self, known_failures: Vec<String>) -> Self {
+        self.options.known_failures = known_failures;
+        self
+    }
+
+    /// Sets part-of-speech tags which are used in the rules but not in the tagger dictionary.
+    pub fn extra_tags(mut self, extra_tags: Vec<String>) -> Self {
+        self.options.extra_tags = extra_tags;
+        self
+    }
+
+    /// Sets how to parse morphological features from the part-of-speech tags. Defaults to `None`.
+    pub fn tag_parser(mut self, tag_parser: Option<TagParser>) -> Self {
+        self.options.tag_parser = tag_parser;
+        self
+    }
+
+    /// Sets whether to tag unknown hyphenated compounds with the tags of their last element. Defaults to `false`.
+    pub fn tag_hyphen_compounds(mut self, tag_hyphen_compounds: bool) -> Self {
+        self.options.tag_hyphen_compounds = tag_hyphen_compounds;
+        self
+    }
+
+    /// Sets the chars which are replaced in words before tagging and matching. Defaults to none.
+    pub fn normalization(mut self, normalization: Vec<(char, String)>) -> Self {
+        self.options.normalization = normalization;
+        self
+    }
+
+    /// Sets whether to ignore bidirectional control chars when tokenizing. Defaults to `false`.
+    pub fn ignore_bidi_controls(mut self, ignore_bidi_controls: bool) -> Self {
+        self.options.ignore_bidi_controls = ignore_bidi_controls;
+        self
+    }
+
+    /// Sets whether to tag and match words in ALL CAPS spans as lowercase. Defaults to `false`.
+    pub fn lowercase_all_caps(mut self, lowercase_all_caps: bool) -> Self {
+        self.options.lowercase_all_caps = lowercase_all_caps;
+        self
+    }
+
+    /// Sets the minimum number of consecutive words in capitals which are an ALL CAPS span. Defaults to 3.
+    pub fn min_all_caps_words(mut self, min_all_caps_words: usize) -> Self {
+        self.options.min_all_caps_words = min_all_caps_words;
+        self
+    }
+
+    /// Validates the options and builds them.
+    pub fn build(self) -> Result<TokenizerOptions, crate::Error> {
+        let options = self.options;
+
+        if let Some(id) = options.ids.iter().find(|x| options.ignore_ids.contains(x)) {
+            return Err(crate::Error::InvalidOptions(format!(
+                "rule {} is both used and ignored",
+                id
+            )));
+        }
+
+        for failure in &options.known_failures {
+            let valid = match failure.rsplit_once(':') {
+                Some((id, index)) => !id.is_empty() && index.parse::<usize>().is_ok(),
+                None => false,
+            };
+
+            if !valid {
+                return Err(crate::Error::InvalidOptions(format!(
+                    "known failure {} must have the format {{id}}:{{example_index}}",
+                    failure
+                )));
+            }
+        }
+
+        if let Some(tag) = options
+            .extra_tags
+            .iter()
+            .find(|x| x.is_empty() || x.contains(char::is_whitespace))
+        {
+            return Err(crate::Error::InvalidOptions(format!(
+                "extra tag \"{}\" must be non-empty and must not contain whitespace",
+                tag
+            )));
+        }
+
+        Ok(options)
+    }
+}
+
 /// The complete Tokenizer doing tagging, chunking and disambiguation.
 #[derive(Serialize, Deserialize, Default)]
 pub struct Tokenizer {
@@ -129,24 +481,38 @@ pub struct Tokenizer {
     pub(crate) chunker: Option<Chunker>,
     pub(crate) tagger: Arc<Tagger>,
     pub(crate) options: TokenizerOptions,
+    pub(crate) metadata: Metadata,
+    pub(crate) upos_mapping: Option<UposMapping>,
+    #[serde(skip)]
+    pub(crate) placeholders: Option<Placeholders>,
+    pub(crate) sentence_exceptions: Option<SentenceExceptions>,
 }
 
 impl Tokenizer {
     /// Creates a new tokenizer from a file.
-    pub fn new<P: AsRef<Path>>(p: P) -> bincode::Result<Self> {
-        let reader = BufReader::new(File::open(p).unwrap());
-        bincode::deserialize_from(reader)
+    /// Fails if the file can not be read or deserialized or was built with an incompatible version of nlprule.
+    pub fn new<P: AsRef<Path>>(p: P) -> Result<Self, crate::Error> {
+        let reader = BufReader::new(File::open(p)?);
+        Tokenizer::new_from(reader)
     }
 
-    /// Creates a new tokenizer from a reader.
-    pub fn new_from<R: Read>(reader: R) -> bincode::Result<Self> {
-        bincode::deserialize_from(reader)
+    /// Creates a new tokenizer from a reader. Fails like [Tokenizer::new].
+    pub fn new_from<R: Read>(reader: R) -> Result<Self, crate::Error> {
+        let tokenizer: Tokenizer = bincode::deserialize_from(reader)?;
+        tokenizer.metadata.check_version()?;
+        Ok(tokenizer)
     }
 
+    /// Gets the disambiguation rules in the order they are applied.
     pub fn rules(&self) -> &Vec<DisambiguationRule> {
         &self.rules
     }
 
+    /// Finds a disambiguation rule by ID.
+    pub fn rule(&self, id: &str) -> Option<&DisambiguationRule> {
+        self.rules.iter().find(|x| x.id() == id)
+    }
+
     pub fn tagger(&self) -> &Arc<Tagger> {
         &self.tagger
     }
@@ -159,6 +525,178 @@ impl Tokenizer {
         &self.options
     }
 
+    /// Tokenizes, tags and disambiguates a text into an [Analysis] which can be serialized and checked later.
+    pub fn analyze(&self, text: &str) -> Analysis {
+        let tokens = finalize(self.disambiguate(self.tokenize(text)));
+
+        Analysis {
+            text: text.to_string(),
+            tokens: tokens.iter().map(|x| x.to_owned_token()).collect(),
+        }
+    }
+
+    /// Replaces the options of this tokenizer e. g. to change them without recompiling, see [TokenizerOptionsBuilder].
+    pub fn set_options(&mut self, options: TokenizerOptions) {
+        self.options = options;
+    }
+
+    /// Sets the mapping of the tags of this tokenizer to Universal POS tags, see [Tokenizer::upos].
+    pub fn set_upos_mapping(&mut self, upos_mapping: Option<UposMapping>) {
+        self.upos_mapping = upos_mapping;
+    }
+
+    pub fn upos_mapping(&self) -> &Option<UposMapping> {
+        &self.upos_mapping
+    }
+
+    /// Sets the placeholders which are kept as one token with the tag of the placeholders, e. g. `{0}` in UI strings.
+    /// Placeholders are not stored when the tokenizer is serialized.
+    ///
+    /// # Errors
+    /// If the tag of the placeholders is not known to the tagger.
+    pub fn set_placeholders(
+        &mut self,
+        placeholders: Option<Placeholders>,
+    ) -> Result<(), crate::Error> {
+        if let Some(placeholders) = &placeholders {
+            if self.tagger.try_id_tag(placeholders.tag()).is_none() {
+                return Err(crate::Error::InvalidOptions(format!(
+                    "placeholder tag \"{}\" is not known to the tagger",
+                    placeholders.tag()
+                )));
+            }
+        }
+
+        self.placeholders = placeholders;
+        Ok(())
+    }
+
+    pub fn placeholders(&self) -> &Option<Placeholders> {
+        &self.placeholders
+    }
+
+    /// Computes a fingerprint of everything which influences the tokens at runtime: the tokenizer as built,
+    /// its [options][Tokenizer::options] and the [placeholders][Tokenizer::set_placeholders]. Stable across
+    /// processes like [Rules::fingerprint][crate::Rules::fingerprint].
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = utils::StableHasher::default();
+
+        self.metadata.source_hash.hash(&mut hasher);
+        self.metadata.nlprule_version.hash(&mut hasher);
+        bincode::serialize(&self.options)
+            .expect("options must be serializable")
+            .hash(&mut hasher);
+        if let Some(placeholders) = &self.placeholders {
+            placeholders.pattern().hash(&mut hasher);
+            placeholders.tag().hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+
+    /// Sets the exceptions to sentence boundaries, see [Tokenizer::sentences].
+    pub fn set_sentence_exceptions(&mut self, sentence_exceptions: Option<SentenceExceptions>) {
+        self.sentence_exceptions = sentence_exceptions;
+    }
+
+    pub fn sentence_exceptions(&self) -> &Option<SentenceExceptions> {
+        &self.sentence_exceptions
+    }
+
+    /// Splits the text into sentences at the Unicode sentence boundaries, except for boundaries matched by the
+    /// [sentence exceptions][Tokenizer::set_sentence_exceptions] e. g. after initials. Joining the sentences gives the text.
+    pub fn sentences<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        match &self.sentence_exceptions {
+            Some(exceptions) => exceptions.split(text),
+            None => text.unicode_sentences().collect(),
+        }
+    }
+
+    /// Splits the text into sentences like [Tokenizer::sentences] and gets their positions in the text.
+    pub fn sentencize<'t>(&self, text: &'t str) -> Vec<Sentence<'t>> {
+        let (mut offset, mut byte_offset) = (0, 0);
+
+        self.sentences(text)
+            .into_iter()
+            .map(|sentence| {
+                let char_len = sentence.chars().count();
+                let sentence = Sentence {
+                    text: sentence,
+                    char_span: (offset, offset + char_len),
+                    byte_span: (byte_offset, byte_offset + sentence.len()),
+                };
+                offset += char_len;
+                byte_offset += sentence.text.len();
+                sentence
+            })
+            .collect()
+    }
+
+    /// Splits the text into sentences like [Tokenizer::sentencize] and tokenizes each of them like [Tokenizer::tokenize].
+    /// Unlike tokenizing the sentences one by one, the [quote depth][IncompleteToken::quote_depth] is computed over
+    /// the whole text so quotations can span sentences. The spans of the tokens are relative to their sentence.
+    pub fn tokenize_sentences<'t>(
+        &'t self,
+        text: &'t str,
+    ) -> Vec<(Sentence<'t>, Vec<IncompleteToken<'t>>)> {
+        let mut sentences: Vec<_> = self
+            .sentencize(text)
+            .into_iter()
+            .map(|sentence| (sentence, self.tokenize(sentence.text)))
+            .collect();
+
+        let words: Vec<_> = sentences
+            .iter()
+            .flat_map(|(_, tokens)| tokens.iter().map(|x| x.word.text.as_ref()))
+            .collect();
+        let depths = quote_depths(&words);
+        for (token, depth) in sentences
+            .iter_mut()
+            .flat_map(|(_, tokens)| tokens.iter_mut())
+            .zip(depths)
+        {
+            token.quote_depth = depth;
+        }
+
+        sentences
+    }
+
+    /// Gets the Universal POS tag of a native tag. Returns `None` if no mapping is set or it does not cover the tag.
+    pub fn upos(&self, tag: &str) -> Option<Upos> {
+        self.upos_mapping.as_ref().and_then(|x| x.upos(tag))
+    }
+
+    /// Gets the Universal POS tag of the first reading of a token which has one.
+    pub fn token_upos(&self, token: &Token) -> Option<Upos> {
+        token
+            .word
+            .tags
+            .iter()
+            .find_map(|data| self.upos(data.pos.as_ref()))
+    }
+
+    /// Gets the morphological features of a reading with the [tag parser][TokenizerOptions::tag_parser] of this tokenizer.
+    /// The features are empty if no tag parser is set.
+    pub fn features(&self, data: &WordData) -> Features {
+        self.options
+            .tag_parser
+            .map(|parser| data.features(parser))
+            .unwrap_or_default()
+    }
+
+    /// Gets information about how this tokenizer was built.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Removes the [source locations][crate::rule::DisambiguationRule::source_location] of all disambiguation rules
+    /// e. g. to not ship local paths.
+    pub fn strip_source_locations(&mut self) {
+        for rule in self.rules.iter_mut() {
+            rule.source_location = None;
+        }
+    }
+
     pub(crate) fn disambiguate_up_to_id<'t>(
         &'t self,
         mut tokens: Vec<IncompleteToken<'t>>,
@@ -195,6 +733,27 @@ impl Tokenizer {
         tokens
     }
 
+    /// Tokenize and disambiguate the text while recording the matches of the disambiguation rules.
+    /// With [Verbosity::Filtered] matches which were discarded by a filter are also recorded,
+    /// together with the name of the filter and the reason it discarded the match.
+    pub fn explain(&self, text: &str, verbosity: Verbosity) -> Trace {
+        let mut trace = Trace::new(verbosity);
+
+        let mut tokens = self.tokenize(text);
+        let mut finalized = finalize(tokens.clone());
+
+        for rule in &self.rules {
+            let changes = rule.apply_with_trace(&finalized, self, Some(&mut trace));
+
+            if !changes.is_empty() {
+                rule.change(&mut tokens, self, changes);
+                finalized = finalize(tokens.clone());
+            }
+        }
+
+        trace
+    }
+
     /// Apply rule-based disambiguation to the tokens.
     /// This does not change the number of tokens, but can change the content arbitrarily.
     pub fn disambiguate<'t>(
@@ -206,8 +765,9 @@ impl Tokenizer {
 
     /// Tokenize the given text. This applies chunking and tagging, but does not do disambiguation.
     pub fn tokenize<'t>(&'t self, text: &'t str) -> Vec<IncompleteToken<'t>> {
-        let sentence_indices = text
-            .unicode_sentences()
+        let sentence_indices = self
+            .sentences(text)
+            .into_iter()
             .map(|sentence| {
                 let ptr = sentence.as_ptr() as usize;
                 (ptr, ptr + sentence.len())
@@ -219,34 +779,84 @@ impl Tokenizer {
             });
 
         let mut current_char = 0;
-        let token_strs = get_token_strs(text);
+        let ignore_bidi = self.options.ignore_bidi_controls;
+        let token_strs = get_token_strs(text, self.placeholders.as_ref(), ignore_bidi);
+        let lowercase = if self.options.lowercase_all_caps {
+            all_caps_words(&token_strs, self.options.min_all_caps_words)
+        } else {
+            vec![false; token_strs.len()]
+        };
         let mut tokens: Vec<_> = token_strs
             .into_iter()
-            .map(|x| {
+            .zip(lowercase)
+            .map(|(x, lowercase)| {
                 let char_start = current_char;
                 let ptr = x.as_ptr() as usize;
                 current_char += x.chars().count();
 
                 let byte_start = ptr - text.as_ptr() as usize;
-                let trimmed = x.trim();
+                let trimmed = if ignore_bidi {
+                    x.trim_matches(|c: char| c.is_whitespace() || crate::text::is_bidi_control(c))
+                } else {
+                    x.trim()
+                };
 
                 let is_sentence_start = sentence_indices.0.contains(&ptr);
                 let is_sentence_end = sentence_indices.1.contains(&(ptr + x.len()));
 
-                IncompleteToken {
-                    word: Word::new_with_tags(
-                        self.tagger.id_word(trimmed.into()),
-                        self.tagger.get_tags(
-                            trimmed,
-                            is_sentence_start || self.options.always_add_lower_tags,
+                let is_placeholder = match &self.placeholders {
+                    Some(placeholders) => placeholders.is_placeholder(trimmed),
+                    None => false,
+                };
+                let word = if is_placeholder {
+                    Cow::Borrowed(trimmed)
+                } else if lowercase {
+                    Cow::Owned(normalize(trimmed, &self.options.normalization).to_lowercase())
+                } else {
+                    normalize(trimmed, &self.options.normalization)
+                };
+
+                let tags = match &self.placeholders {
+                    Some(placeholders) if is_placeholder => {
+                        vec![WordData::new(
+                            self.tagger.id_word(trimmed.into()),
+                            self.tagger.id_tag(placeholders.tag()),
+                        )]
+                    }
+                    _ => {
+                        let add_lower = is_sentence_start || self.options.always_add_lower_tags;
+                        let tags = self.tagger.get_tags(
+                            &word,
+                            add_lower,
                             self.options.use_compound_split_heuristic,
-                        ),
-                    ),
+                        );
+
+                        if tags.is_empty() && self.options.tag_hyphen_compounds {
+                            self.tagger.get_hyphen_compound_tags(
+                                &word,
+                                add_lower,
+                                self.options.use_compound_split_heuristic,
+                            )
+                        } else {
+                            tags
+                        }
+                    }
+                };
+
+                IncompleteToken {
+                    word: Word::new_with_tags(self.tagger.id_word(word), tags),
                     char_span: (char_start, current_char),
                     byte_span: (byte_start, byte_start + x.len()),
                     is_sentence_end,
-                    has_space_before: text[..byte_start].ends_with(char::is_whitespace),
+                    has_space_before: if ignore_bidi {
+                        text[..byte_start]
+                            .trim_end_matches(crate::text::is_bidi_control)
+                            .ends_with(char::is_whitespace)
+                    } else {
+                        text[..byte_start].ends_with(char::is_whitespace)
+                    },
                     chunks: Vec::new(),
+                    quote_depth: 0,
                     text,
                     tagger: self.tagger.as_ref(),
                 }
@@ -258,6 +868,12 @@ impl Tokenizer {
             let last_idx = tokens.len() - 1;
             tokens[last_idx].is_sentence_end = true;
 
+            let words: Vec<_> = tokens.iter().map(|x| x.word.text.as_ref()).collect();
+            let depths = quote_depths(&words);
+            for (token, depth) in tokens.iter_mut().zip(depths) {
+                token.quote_depth = depth;
+            }
+
             if let Some(chunker) = &self.chunker {
                 chunker.apply(&mut tokens);
             }
@@ -269,9 +885,12 @@ impl Tokenizer {
 
 #[cfg(test)]
 mod tests {
-    use super::Tokenizer;
+    use super::{
+        all_caps_words, get_token_strs, normalize, quote_depths, Tokenizer, TokenizerOptions,
+    };
     use lazy_static::lazy_static;
     use quickcheck_macros::quickcheck;
+    use std::borrow::Cow;
     use std::fs::File;
     use std::io::BufReader;
 
@@ -287,4 +906,108 @@ mod tests {
         TOKENIZER.tokenize(&text);
         true
     }
+
+    #[test]
+    fn tracks_quote_depth() {
+        let words = [
+            "He", "said", "\"", "read", "„", "Faust", "“", "\"", "and", "«", "left",
+        ];
+
+        assert_eq!(quote_depths(&words), vec![0, 0, 0, 1, 1, 2, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn sentencizes_with_offsets() {
+        let tokenizer = Tokenizer::default();
+        let spans: Vec<_> = tokenizer
+            .sentencize("Wär das so? Ja.")
+            .into_iter()
+            .map(|x| (x.text, x.char_span, x.byte_span))
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                ("Wär das so? ", (0, 12), (0, 13)),
+                ("Ja.", (12, 15), (13, 16))
+            ]
+        );
+    }
+
+    #[test]
+    fn tracks_quote_depth_across_sentences() {
+        let tokenizer = Tokenizer::default();
+        let depths: Vec<Vec<_>> = tokenizer
+            .tokenize_sentences("He said \"Stop. Now.\" Then")
+            .into_iter()
+            .map(|(_, tokens)| tokens.iter().map(|x| x.quote_depth).collect())
+            .collect();
+
+        assert_eq!(depths, vec![vec![0, 0, 0, 1, 1], vec![1, 1, 0], vec![0]]);
+    }
+
+    #[test]
+    fn finds_all_caps_runs() {
+        let words = [
+            "BREAKING", ":", "MAYOR", "WINS", "VOTE", "in", "Paris", ",", "I", "am", "OK",
+        ];
+
+        let expected = [
+            true, true, true, true, true, false, false, false, false, false, false,
+        ];
+        assert_eq!(all_caps_words(&words, 3), expected);
+        assert!(!all_caps_words(&words, 5).contains(&true));
+    }
+
+    #[test]
+    fn normalizes_words() {
+        let normalization = TokenizerOptions::default_normalization();
+
+        assert_eq!(normalize("don’t\u{200B}", &normalization), "don't");
+        assert!(matches!(
+            normalize("dont", &normalization),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn splits_off_bidi_controls() {
+        let text = "\u{200F}שלום\u{200F}، עולם";
+
+        assert_eq!(
+            get_token_strs(text, None, true),
+            vec!["\u{200F}", "שלום", "\u{200F}", "،", " ", "עולם"]
+        );
+        assert_eq!(
+            get_token_strs(text, None, false),
+            vec!["\u{200F}שלום\u{200F}", "،", " ", "עולם"]
+        );
+    }
+
+    #[test]
+    fn validates_options() {
+        let strings = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+        assert!(
+            TokenizerOptions::builder()
+                .retain_last(true)
+                .known_failures(strings(&["SOME_RULE:2"]))
+                .build()
+                .unwrap()
+                .retain_last
+        );
+        assert!(TokenizerOptions::builder()
+            .ids(strings(&["A"]))
+            .ignore_ids(strings(&["A"]))
+            .build()
+            .is_err());
+        assert!(TokenizerOptions::builder()
+            .known_failures(strings(&["SOME_RULE"]))
+            .build()
+            .is_err());
+        assert!(TokenizerOptions::builder()
+            .extra_tags(strings(&["NN S"]))
+            .build()
+            .is_err());
+    }
 }
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 use std::{cmp::Ordering, collections::BinaryHeap};
 
-use crate::types::{DefaultHashMap, DefaultHasher};
+use crate::types::{DefaultHashMap, DefaultHasher, SpanConverter};
 
 use super::IncompleteToken;
 
@@ -635,12 +635,7 @@ impl Chunker {
         // replacements must not change char indices
         let text = tokens[0].text.replace('’', "\'");
 
-        let mut byte_to_char_idx: DefaultHashMap<usize, usize> = text
-            .char_indices()
-            .enumerate()
-            .map(|(ci, (bi, _))| (bi, ci))
-            .collect();
-        byte_to_char_idx.insert(text.len(), text.chars().count());
+        let converter = SpanConverter::new(&text);
 
         // the chunker expects tokens tokenized with a maximum entropy tokenizer
         let internal_tokens = self.token_model.tokenize(&text);
@@ -661,10 +656,11 @@ impl Chunker {
             .zip(internal_tokens)
             .map(|(chunk, token)| {
                 let byte_start = token.as_ptr() as usize - text.as_ptr() as usize;
-                let char_start = *byte_to_char_idx.get(&byte_start).unwrap();
-                let char_end = *byte_to_char_idx.get(&(byte_start + token.len())).unwrap();
+                let char_span = converter
+                    .char_span((byte_start, byte_start + token.len()))
+                    .unwrap();
 
-                (*chunk, (char_start, char_end))
+                (*chunk, char_span)
             })
             .collect();
         let mut chunks = Vec::new();
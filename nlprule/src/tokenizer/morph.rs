@@ -0,0 +1,137 @@
+//! Morphological features (gender, number, case and person) encoded in the part-of-speech tags of some languages,
+//! e. g. "SUB:NOM:SIN:MAS" in German or "N m s" in French, so they do not have to be parsed with regexes.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Gender {
+    Masculine,
+    Feminine,
+    Neuter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Number {
+    Singular,
+    Plural,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Case {
+    Nominative,
+    Genitive,
+    Dative,
+    Accusative,
+}
+
+/// The morphological features of one reading of a word. A feature is `None` if the tag does not encode it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Features {
+    pub gender: Option<Gender>,
+    pub number: Option<Number>,
+    pub case: Option<Case>,
+    /// The grammatical person from 1 to 3.
+    pub person: Option<u8>,
+}
+
+impl Features {
+    /// Whether every feature set in `required` has the same value in these features.
+    pub fn satisfies(&self, required: &Features) -> bool {
+        fn check<T: PartialEq>(value: Option<T>, required: Option<T>) -> bool {
+            required.is_none() || value == required
+        }
+
+        check(self.gender, required.gender)
+            && check(self.number, required.number)
+            && check(self.case, required.case)
+            && check(self.person, required.person)
+    }
+}
+
+/// Parses the features from the tags of a language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagParser {
+    /// Colon-separated tags of the German LanguageTool dictionary e. g. "ADJ:AKK:PLU:FEM:GRU:SOL" or "VER:3:SIN:PRÄ:NON".
+    German,
+    /// Space-separated tags of the French LanguageTool dictionary e. g. "J f p" or "V etre ind pres 3 s".
+    French,
+}
+
+impl TagParser {
+    /// Gets the features encoded in a tag. Returns empty features for tags without any.
+    pub fn parse(&self, tag: &str) -> Features {
+        let mut features = Features::default();
+
+        match self {
+            TagParser::German => {
+                for part in tag.split(':') {
+                    match part {
+                        "MAS" => features.gender = Some(Gender::Masculine),
+                        "FEM" => features.gender = Some(Gender::Feminine),
+                        "NEU" => features.gender = Some(Gender::Neuter),
+                        "SIN" => features.number = Some(Number::Singular),
+                        "PLU" => features.number = Some(Number::Plural),
+                        "NOM" => features.case = Some(Case::Nominative),
+                        "GEN" => features.case = Some(Case::Genitive),
+                        "DAT" => features.case = Some(Case::Dative),
+                        "AKK" => features.case = Some(Case::Accusative),
+                        "1" => features.person = Some(1),
+                        "2" => features.person = Some(2),
+                        "3" => features.person = Some(3),
+                        _ => {}
+                    }
+                }
+            }
+            TagParser::French => {
+                // the first part is the word class e. g. "N" for nouns, it is never a feature
+                for part in tag.split(' ').skip(1) {
+                    match part {
+                        "m" => features.gender = Some(Gender::Masculine),
+                        "f" => features.gender = Some(Gender::Feminine),
+                        "s" => features.number = Some(Number::Singular),
+                        "p" => features.number = Some(Number::Plural),
+                        "1" => features.person = Some(1),
+                        "2" => features.person = Some(2),
+                        "3" => features.person = Some(3),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        features
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_features() {
+        let features = TagParser::German.parse("ART:DEF:DAT:SIN:NEU");
+        assert_eq!(
+            features,
+            Features {
+                gender: Some(Gender::Neuter),
+                number: Some(Number::Singular),
+                case: Some(Case::Dative),
+                person: None,
+            }
+        );
+        assert!(features.satisfies(&Features {
+            case: Some(Case::Dative),
+            ..Default::default()
+        }));
+        assert!(!features.satisfies(&Features {
+            number: Some(Number::Plural),
+            ..Default::default()
+        }));
+
+        let features = TagParser::French.parse("V etre ind pres 3 s");
+        assert_eq!(features.person, Some(3));
+        assert_eq!(features.number, Some(Number::Singular));
+        assert_eq!(features.gender, None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Placeholders in localization strings such as `{0}`, `%s` or `{{name}}` which are kept as one opaque token
+//! with a configurable part-of-speech tag, so rules around them behave as if they were a word.
+
+use onig::Regex;
+
+/// Patterns of placeholders in the printf, ICU / .NET and Mustache / Handlebars styles.
+const UI_STRING_PATTERNS: [&str; 3] = [
+    r"%(?:\d+\$)?[-+0#]*\d*(?:\.\d+)?[sdifuxXeEgGc@]",
+    r"\{\{\s*[\w.]+\s*\}\}",
+    r"\{\s*\w+(?:\s*,[^{}]*)?\s*\}",
+];
+
+/// Recognizes placeholders in the text, see [Tokenizer::set_placeholders][crate::Tokenizer::set_placeholders].
+///
+/// Together with [fragment mode][crate::rules::RulesOptions::fragment] this is a profile for checking UI strings:
+///
+/// ```no_run
+/// use nlprule::{tokenizer::placeholder::Placeholders, Rules, Tokenizer};
+///
+/// let mut tokenizer = Tokenizer::new("path/to/en_tokenizer.bin")?;
+/// let mut rules = Rules::new("path/to/en_rules.bin")?;
+///
+/// tokenizer.set_placeholders(Some(Placeholders::ui_strings("NNP")?))?;
+/// rules.options_mut().fragment = true;
+///
+/// let suggestions = rules.suggest("Delete {0} files?", &tokenizer);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Placeholders {
+    regex: Regex,
+    pattern: String,
+    tag: String,
+}
+
+impl Placeholders {
+    /// Creates placeholders matching any of the regex `patterns`. Placeholder tokens get the part-of-speech `tag`,
+    /// which must be known to the tagger, e. g. "NNP" to treat them like a proper noun in English.
+    ///
+    /// # Errors
+    /// If a pattern is not a valid regex.
+    pub fn new(patterns: &[&str], tag: &str) -> Result<Self, crate::Error> {
+        let regex_str = patterns
+            .iter()
+            .map(|x| format!("(?:{})", x))
+            .collect::<Vec<_>>()
+            .join("|");
+        let regex = Regex::new(&regex_str).map_err(|source| crate::Error::Regex {
+            regex: regex_str.clone(),
+            source,
+        })?;
+
+        Ok(Placeholders {
+            regex,
+            pattern: regex_str,
+            tag: tag.to_string(),
+        })
+    }
+
+    /// Creates placeholders for common localization formats: `%s`, `%1$d`, `{0}`, `{name}`, `{count, plural, ...}`
+    /// without nested braces and `{{name}}`.
+    pub fn ui_strings(tag: &str) -> Result<Self, crate::Error> {
+        Placeholders::new(&UI_STRING_PATTERNS, tag)
+    }
+
+    /// Gets the part-of-speech tag of placeholder tokens.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Gets the regex matching any of the patterns.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Gets the byte spans of the placeholders in the text.
+    pub(crate) fn find_iter<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.regex
+            .find_iter(text)
+            .filter(|(start, end)| start != end)
+    }
+
+    /// Whether the token text is exactly one placeholder.
+    pub(crate) fn is_placeholder(&self, text: &str) -> bool {
+        self.regex.find(text) == Some((0, text.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_ui_string_placeholders() {
+        let placeholders = Placeholders::ui_strings("NNP").unwrap();
+        let text = "Delete {0} of %d files from {{folder}} (100%)?";
+
+        let found: Vec<_> = placeholders
+            .find_iter(text)
+            .map(|(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(found, vec!["{0}", "%d", "{{folder}}"]);
+        assert!(placeholders.is_placeholder("%1$s"));
+        assert!(!placeholders.is_placeholder("files"));
+    }
+}
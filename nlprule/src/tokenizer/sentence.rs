@@ -0,0 +1,118 @@
+//! Exceptions to the Unicode sentence boundaries, e. g. after initials ("J. R. R. Tolkien") or German ordinals
+//! ("am 3. Mai"). An exception has the form of a non-breaking rule in the `segment.srx` of LanguageTool:
+//! a regex for the text before and optionally one for the text after the boundary.
+
+use crate::{utils::regex::SerializeRegex, Error};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A sentence of a text, see [Tokenizer::sentencize][crate::Tokenizer::sentencize].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sentence<'t> {
+    /// The text of the sentence, including the whitespace after it.
+    pub text: &'t str,
+    /// The character span of the sentence in the text.
+    pub char_span: (usize, usize),
+    /// The byte span of the sentence in the text.
+    pub byte_span: (usize, usize),
+}
+
+/// A boundary is not a sentence boundary if the text before it ends with a match of `before` and the text after it
+/// starts with a match of `after`. Whitespace at the boundary is not part of the texts. The regexes are Java regexes
+/// like in LanguageTool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentenceException {
+    pub before: String,
+    #[serde(default)]
+    pub after: Option<String>,
+}
+
+/// Compiled [SentenceException]s, see [Tokenizer::sentences][crate::Tokenizer::sentences].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SentenceExceptions {
+    exceptions: Vec<(SerializeRegex, Option<SerializeRegex>)>,
+}
+
+impl SentenceExceptions {
+    /// Compiles the exceptions.
+    ///
+    /// # Errors
+    /// If a regex is not valid.
+    pub fn new(exceptions: &[SentenceException]) -> Result<Self, Error> {
+        let exceptions = exceptions
+            .iter()
+            .map(|exception| {
+                let before =
+                    SerializeRegex::new(&format!("(?:{})\\z", exception.before), false, true)?;
+                let after = exception
+                    .after
+                    .as_ref()
+                    .map(|after| SerializeRegex::new(&format!("\\A(?:{})", after), false, true))
+                    .transpose()?;
+                Ok((before, after))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(SentenceExceptions { exceptions })
+    }
+
+    fn is_exception(&self, before: &str, after: &str) -> bool {
+        let (before, after) = (before.trim_end(), after.trim_start());
+
+        self.exceptions.iter().any(|(before_regex, after_regex)| {
+            before_regex.find(before).is_some()
+                && match after_regex {
+                    Some(regex) => regex.find(after).is_some(),
+                    None => true,
+                }
+        })
+    }
+
+    /// Splits the text at the Unicode sentence boundaries which are not exceptions.
+    pub fn split<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let mut sentences = Vec::new();
+        let mut start = 0;
+
+        for sentence in text.unicode_sentences() {
+            let end = sentence.as_ptr() as usize - text.as_ptr() as usize + sentence.len();
+            if end < text.len() && self.is_exception(&text[start..end], &text[end..]) {
+                continue;
+            }
+
+            sentences.push(&text[start..end]);
+            start = end;
+        }
+
+        sentences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_exceptions() {
+        let exceptions = SentenceExceptions::new(&[
+            SentenceException {
+                before: "\\b\\p{Lu}\\.".to_string(),
+                after: None,
+            },
+            SentenceException {
+                before: "\\b\\d{1,2}\\.".to_string(),
+                after: Some("Mai".to_string()),
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(
+            exceptions.split("J. R. R. Tolkien wrote books. Am 3. Mai. Am 3. Tag."),
+            vec![
+                "J. R. R. Tolkien wrote books. ",
+                "Am 3. Mai. ",
+                "Am 3. ",
+                "Tag."
+            ]
+        );
+    }
+}
@@ -18,6 +18,19 @@ pub struct Tagger {
     groups: DefaultHashMap<u32, Vec<u32>>,
 }
 
+/// Splits a hyphenated compound into the elements before the last hyphen and the last element.
+/// Both must contain a letter or digit, so e. g. "-" and "e-" are not compounds.
+fn split_hyphen_compound(word: &str) -> Option<(&str, &str)> {
+    let (prefix, last) = word.rsplit_once('-')?;
+    let has_content = |x: &str| x.chars().any(char::is_alphanumeric);
+
+    if has_content(prefix) && has_content(last) {
+        Some((prefix, last))
+    } else {
+        None
+    }
+}
+
 impl Tagger {
     fn get_lines<S1: AsRef<str>, S2: AsRef<str>>(
         paths: &[S1],
@@ -209,6 +222,11 @@ impl Tagger {
         PosId(tag, *self.tag_store.get_by_left(tag).unwrap())
     }
 
+    /// Like [Tagger::id_tag], but returns `None` if the tag is not known.
+    pub(crate) fn try_id_tag<'a>(&self, tag: &'a str) -> Option<PosId<'a>> {
+        Some(PosId(tag, *self.tag_store.get_by_left(tag)?))
+    }
+
     pub fn id_word<'t>(&'t self, text: Cow<'t, str>) -> WordId<'t> {
         let id = self.word_store.get_by_left(text.as_ref()).copied();
         WordId(text, id)
@@ -270,6 +288,30 @@ impl Tagger {
         tags
     }
 
+    /// Get the tags of the last element of a hyphenated compound e. g. the tags of "related" for "COVID-19-related".
+    /// The head of compounds is their last element in e. g. English and German, so the compound inflects like it.
+    /// The lemmas are the lemmas of the last element prefixed with the other elements. Returns no tags if the word
+    /// is not a hyphenated compound. Arguments as in [Tagger::get_tags].
+    pub fn get_hyphen_compound_tags(
+        &self,
+        word: &str,
+        add_lower: bool,
+        use_compound_split_heuristic: bool,
+    ) -> Vec<WordData<'_>> {
+        let (prefix, last) = match split_hyphen_compound(word) {
+            Some(parts) => parts,
+            None => return Vec::new(),
+        };
+
+        self.get_tags(last, add_lower, use_compound_split_heuristic)
+            .into_iter()
+            .map(|mut x| {
+                x.lemma = self.id_word(format!("{}-{}", prefix, x.lemma.as_ref()).into());
+                x
+            })
+            .collect()
+    }
+
     /// Get the words with the same lemma as the given lemma.
     #[allow(clippy::clippy::ptr_arg)]
     pub fn get_group_members(&self, lemma: &String) -> Vec<&str> {
@@ -284,3 +326,23 @@ impl Tagger {
             .unwrap_or_else(Vec::new)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_hyphen_compounds() {
+        assert_eq!(
+            split_hyphen_compound("COVID-19-related"),
+            Some(("COVID-19", "related"))
+        );
+        assert_eq!(
+            split_hyphen_compound("state-of-the-art"),
+            Some(("state-of-the", "art"))
+        );
+        assert_eq!(split_hyphen_compound("e-"), None);
+        assert_eq!(split_hyphen_compound("-"), None);
+        assert_eq!(split_hyphen_compound("word"), None);
+    }
+}
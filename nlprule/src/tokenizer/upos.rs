@@ -0,0 +1,166 @@
+//! Mapping of the language specific part-of-speech tags of LanguageTool to [Universal POS tags](https://universaldependencies.org/u/pos/)
+//! so tokens of different languages can be handled uniformly.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// A Universal POS tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Upos {
+    Adj,
+    Adp,
+    Adv,
+    Aux,
+    Cconj,
+    Det,
+    Intj,
+    Noun,
+    Num,
+    Part,
+    Pron,
+    Propn,
+    Punct,
+    Sconj,
+    Sym,
+    Verb,
+    X,
+}
+
+impl Upos {
+    const ALL: [Upos; 17] = [
+        Upos::Adj,
+        Upos::Adp,
+        Upos::Adv,
+        Upos::Aux,
+        Upos::Cconj,
+        Upos::Det,
+        Upos::Intj,
+        Upos::Noun,
+        Upos::Num,
+        Upos::Part,
+        Upos::Pron,
+        Upos::Propn,
+        Upos::Punct,
+        Upos::Sconj,
+        Upos::Sym,
+        Upos::Verb,
+        Upos::X,
+    ];
+
+    /// Gets the tag as written in Universal Dependencies e. g. "PROPN".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Upos::Adj => "ADJ",
+            Upos::Adp => "ADP",
+            Upos::Adv => "ADV",
+            Upos::Aux => "AUX",
+            Upos::Cconj => "CCONJ",
+            Upos::Det => "DET",
+            Upos::Intj => "INTJ",
+            Upos::Noun => "NOUN",
+            Upos::Num => "NUM",
+            Upos::Part => "PART",
+            Upos::Pron => "PRON",
+            Upos::Propn => "PROPN",
+            Upos::Punct => "PUNCT",
+            Upos::Sconj => "SCONJ",
+            Upos::Sym => "SYM",
+            Upos::Verb => "VERB",
+            Upos::X => "X",
+        }
+    }
+}
+
+impl fmt::Display for Upos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Upos {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Upos::ALL
+            .iter()
+            .find(|x| x.as_str() == s)
+            .copied()
+            .ok_or_else(|| crate::Error::InvalidInput(format!("unknown UPOS tag: {}", s)))
+    }
+}
+
+/// Maps native tags to Universal POS tags by prefix. If multiple prefixes match a tag, the longest one is used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UposMapping {
+    prefixes: Vec<(String, Upos)>,
+}
+
+impl UposMapping {
+    /// Creates a new mapping from tag prefixes to Universal POS tags.
+    pub fn new(prefixes: Vec<(String, Upos)>) -> Self {
+        UposMapping { prefixes }
+    }
+
+    /// Gets the mapping for the Penn Treebank based tagset of the English LanguageTool tagger.
+    pub fn penn() -> Self {
+        let prefixes = [
+            ("NNP", Upos::Propn),
+            ("NN", Upos::Noun),
+            ("MD", Upos::Aux),
+            ("VB", Upos::Verb),
+            ("JJ", Upos::Adj),
+            ("RB", Upos::Adv),
+            ("WRB", Upos::Adv),
+            ("PRP", Upos::Pron),
+            ("WP", Upos::Pron),
+            ("EX", Upos::Pron),
+            ("DT", Upos::Det),
+            ("PDT", Upos::Det),
+            ("WDT", Upos::Det),
+            ("IN", Upos::Adp),
+            ("RP", Upos::Adp),
+            ("TO", Upos::Part),
+            ("POS", Upos::Part),
+            ("CC", Upos::Cconj),
+            ("CD", Upos::Num),
+            ("UH", Upos::Intj),
+            ("SYM", Upos::Sym),
+            ("PCT", Upos::Punct),
+            ("FW", Upos::X),
+            ("LS", Upos::X),
+        ];
+
+        UposMapping::new(
+            prefixes
+                .iter()
+                .map(|(prefix, upos)| (prefix.to_string(), *upos))
+                .collect(),
+        )
+    }
+
+    /// Gets the Universal POS tag for a native tag. Returns `None` if no prefix matches.
+    pub fn upos(&self, tag: &str) -> Option<Upos> {
+        self.prefixes
+            .iter()
+            .filter(|(prefix, _)| tag.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, upos)| *upos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_longest_prefix() {
+        let mapping = UposMapping::penn();
+
+        assert_eq!(mapping.upos("NNPS"), Some(Upos::Propn));
+        assert_eq!(mapping.upos("NNS"), Some(Upos::Noun));
+        assert_eq!(mapping.upos("VBZ"), Some(Upos::Verb));
+        assert_eq!(mapping.upos("SENT_END"), None);
+        assert_eq!("CCONJ".parse::<Upos>().unwrap(), Upos::Cconj);
+    }
+}
@@ -0,0 +1,163 @@
+//! Traces of rule application to explain why a rule did or did not take effect.
+
+use crate::types::SourceLocation;
+
+/// Controls how much information is recorded in a [Trace].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only record matches which were kept.
+    Matches,
+    /// Additionally record matches which were discarded by a filter together with the reason.
+    Filtered,
+}
+
+/// What happened to a match.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    /// The match was kept and the rule took effect.
+    Kept,
+    /// The match was discarded by a filter.
+    Filtered {
+        /// The name of the filter e. g. "NoDisambiguationEnglishPartialPosTagFilter".
+        filter: String,
+        /// A human-readable reason why the filter discarded the match.
+        reason: String,
+    },
+}
+
+/// A single match of a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    /// The ID of the rule which matched.
+    pub rule_id: String,
+    /// Where the rule is defined in the XML, if it was not stripped from the binary.
+    pub source_location: Option<SourceLocation>,
+    /// The start character index of the match (inclusive).
+    pub start: usize,
+    /// The end character index of the match (exclusive).
+    pub end: usize,
+    /// What happened to this match.
+    pub decision: Decision,
+}
+
+/// A record of the matches encountered while applying rules.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    verbosity: Verbosity,
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Creates a new, empty trace.
+    pub fn new(verbosity: Verbosity) -> Self {
+        Trace {
+            verbosity,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Gets the verbosity of this trace.
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Gets all recorded entries in the order they were encountered.
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Gets the entries of matches which were discarded by a filter.
+    pub fn filtered(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries
+            .iter()
+            .filter(|x| matches!(x.decision, Decision::Filtered { .. }))
+    }
+
+    /// Whether matches discarded by a filter are recorded in this trace.
+    pub(crate) fn records_filtered(&self) -> bool {
+        self.verbosity >= Verbosity::Filtered
+    }
+
+    pub(crate) fn push(&mut self, entry: TraceEntry) {
+        if matches!(entry.decision, Decision::Kept) || self.records_filtered() {
+            self.entries.push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_by_verbosity() {
+        let entry = |decision| TraceEntry {
+            rule_id: "RULE".to_string(),
+            source_location: None,
+            start: 0,
+            end: 1,
+            decision,
+        };
+        let filtered = || {
+            entry(Decision::Filtered {
+                filter: "Filter".to_string(),
+                reason: "reason".to_string(),
+            })
+        };
+
+        let mut trace = Trace::new(Verbosity::Matches);
+        trace.push(entry(Decision::Kept));
+        trace.push(filtered());
+        assert_eq!(trace.entries(), &[entry(Decision::Kept)]);
+        assert_eq!(trace.filtered().count(), 0);
+
+        let mut trace = Trace::new(Verbosity::Filtered);
+        trace.push(entry(Decision::Kept));
+        trace.push(filtered());
+        assert_eq!(trace.entries().len(), 2);
+        assert_eq!(trace.filtered().collect::<Vec<_>>(), vec![&filtered()]);
+    }
+
+    #[cfg(feature = "compile")]
+    #[test]
+    fn explains_filter_decisions() {
+        let xml = r#"<rule id="UN" name="un">
+            <pattern><token regexp="yes">un.*</token></pattern>
+            <filter class="org.languagetool.rules.en.NoDisambiguationEnglishPartialPosTagFilter"
+                args="no:1 regexp:un(.*) postag_regexp:JJ"/>
+            <disambig action="replace"><wd pos="JJ"/></disambig>
+        </rule>"#;
+        let tokenizer = crate::compile::testing::disambiguator(&[("happy", "happy", "JJ")], xml);
+
+        let trace = tokenizer.explain("unhappy unfoo", Verbosity::Filtered);
+        let decisions: Vec<_> = trace
+            .entries()
+            .iter()
+            .map(|x| (x.rule_id.as_str(), x.start, x.end, &x.decision))
+            .collect();
+        assert_eq!(
+            decisions,
+            vec![
+                ("UN", 0, 7, &Decision::Kept),
+                (
+                    "UN",
+                    8,
+                    13,
+                    &Decision::Filtered {
+                        filter: "NoDisambiguationEnglishPartialPosTagFilter".to_string(),
+                        reason:
+                            "no tag of the part of \"unfoo\" matching `^(un(.*))$` matches `^(JJ)$`"
+                                .to_string()
+                    }
+                )
+            ]
+        );
+        assert_eq!(
+            tokenizer
+                .explain("unhappy unfoo", Verbosity::Matches)
+                .entries()
+                .len(),
+            1
+        );
+    }
+}
@@ -4,10 +4,14 @@ use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    cmp::Ordering,
     collections::{hash_map, HashMap, HashSet},
 };
 
-use crate::tokenizer::tag::Tagger;
+use crate::tokenizer::{
+    morph::{Features, TagParser},
+    tag::Tagger,
+};
 
 pub(crate) type DefaultHashMap<K, V> = HashMap<K, V>;
 pub(crate) type DefaultHashSet<T> = HashSet<T>;
@@ -17,7 +21,7 @@ pub(crate) type DefaultHasher = hash_map::DefaultHasher;
 pub mod owned {
     use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
     pub struct WordId(pub String, pub Option<u32>);
 
     impl WordId {
@@ -32,7 +36,7 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
     pub struct PosId(pub String, pub u16);
 
     impl PosId {
@@ -47,7 +51,7 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
     pub struct WordData {
         pub lemma: WordId,
         pub pos: PosId,
@@ -59,19 +63,21 @@ pub mod owned {
         }
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
     pub struct Word {
         pub text: WordId,
         pub tags: Vec<WordData>,
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
     pub struct Token {
         pub word: Word,
         pub char_span: (usize, usize),
         pub byte_span: (usize, usize),
         pub has_space_before: bool,
         pub chunks: Vec<String>,
+        #[serde(default)]
+        pub quote_depth: usize,
     }
 }
 
@@ -127,6 +133,11 @@ impl<'t> WordData<'t> {
         WordData { lemma, pos }
     }
 
+    /// Gets the morphological features encoded in the part-of-speech tag.
+    pub fn features(&self, parser: TagParser) -> Features {
+        parser.parse(self.pos.as_ref())
+    }
+
     pub fn to_owned_word_data(&self) -> owned::WordData {
         owned::WordData {
             lemma: self.lemma.to_owned_id(),
@@ -166,13 +177,15 @@ pub struct IncompleteToken<'t> {
     pub is_sentence_end: bool,
     pub has_space_before: bool,
     pub chunks: Vec<String>,
+    /// How many quotations this token is in. Quotation marks themselves are outside the quotation they delimit.
+    pub quote_depth: usize,
     pub text: &'t str,
     #[derivative(PartialEq = "ignore", Debug = "ignore")]
     pub tagger: &'t Tagger,
 }
 
 /// A finished token with all information set.
-#[derive(Derivative)]
+#[derive(Derivative, Clone)]
 #[derivative(Debug)]
 pub struct Token<'t> {
     pub word: Word<'t>,
@@ -180,6 +193,8 @@ pub struct Token<'t> {
     pub byte_span: (usize, usize),
     pub has_space_before: bool,
     pub chunks: Vec<String>,
+    /// How many quotations this token is in, see [IncompleteToken::quote_depth].
+    pub quote_depth: usize,
     pub text: &'t str,
     #[derivative(Debug = "ignore")]
     pub tagger: &'t Tagger,
@@ -202,6 +217,7 @@ impl<'t> Token<'t> {
             byte_span: (0, 0),
             has_space_before: false,
             chunks: Vec::new(),
+            quote_depth: 0,
             text,
             tagger,
         }
@@ -214,6 +230,7 @@ impl<'t> Token<'t> {
             byte_span: self.byte_span,
             has_space_before: self.has_space_before,
             chunks: self.chunks.clone(),
+            quote_depth: self.quote_depth,
         }
     }
 }
@@ -247,23 +264,500 @@ impl<'t> From<IncompleteToken<'t>> for Token<'t> {
             char_span: data.char_span,
             has_space_before: data.has_space_before,
             chunks: data.chunks,
+            quote_depth: data.quote_depth,
             text: data.text,
             tagger: data.tagger,
         }
     }
 }
 
-/// Suggestion for change in a text.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A tokenized, tagged and disambiguated text. Can be serialized to split analysis and checking
+/// e. g. across processes, see [Tokenizer::analyze][crate::Tokenizer::analyze] and [Rules::apply_tokens][crate::Rules::apply_tokens].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Analysis {
+    /// The analyzed text.
+    pub text: String,
+    /// The finalized tokens of the text, starting with the special sentence start token.
+    pub tokens: Vec<owned::Token>,
+}
+
+impl Analysis {
+    /// Converts the tokens back to tokens bound to the text of this analysis.
+    /// The tagger must be the one of the tokenizer the analysis was made with.
+    ///
+    /// # Errors
+    /// If a token has a part-of-speech tag which is not known to the tagger.
+    pub fn tokens<'t>(&'t self, tagger: &'t Tagger) -> Result<Vec<Token<'t>>, crate::Error> {
+        let id_word = |word: &'t owned::WordId| tagger.id_word(word.0.as_str().into());
+
+        self.tokens
+            .iter()
+            .map(|token| {
+                let tags = token
+                    .word
+                    .tags
+                    .iter()
+                    .map(|data| {
+                        let pos = tagger.try_id_tag(data.pos.0.as_str()).ok_or_else(|| {
+                            crate::Error::InvalidInput(format!("unknown tag: {}", data.pos.0))
+                        })?;
+                        Ok(WordData::new(id_word(&data.lemma), pos))
+                    })
+                    .collect::<Result<Vec<_>, crate::Error>>()?;
+
+                Ok(Token {
+                    word: Word::new_with_tags(id_word(&token.word.text), tags),
+                    char_span: token.char_span,
+                    byte_span: token.byte_span,
+                    has_space_before: token.has_space_before,
+                    chunks: token.chunks.clone(),
+                    quote_depth: token.quote_depth,
+                    text: &self.text,
+                    tagger,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Converts between character and byte indices of a text.
+/// Built once per text in linear time, afterwards conversions are cheap.
+#[derive(Debug, Clone)]
+pub struct SpanConverter {
+    // byte index of each char and the length of the text in bytes at the end
+    char_to_byte: Vec<usize>,
+}
+
+impl SpanConverter {
+    /// Creates a new converter for the given text.
+    pub fn new(text: &str) -> Self {
+        let mut char_to_byte: Vec<_> = text.char_indices().map(|(i, _)| i).collect();
+        char_to_byte.push(text.len());
+
+        SpanConverter { char_to_byte }
+    }
+
+    /// The number of chars in the text.
+    pub fn char_len(&self) -> usize {
+        self.char_to_byte.len() - 1
+    }
+
+    /// Gets the byte index of a char index. The char index may be equal to the number of chars in the text.
+    ///
+    /// # Panics
+    /// If the char index is out of bounds.
+    pub fn byte_index(&self, char_index: usize) -> usize {
+        self.char_to_byte[char_index]
+    }
+
+    /// Gets the char index of a byte index. Returns `None` if the byte index is not on a char boundary.
+    pub fn char_index(&self, byte_index: usize) -> Option<usize> {
+        self.char_to_byte.binary_search(&byte_index).ok()
+    }
+
+    /// Converts a char span to a byte span.
+    ///
+    /// # Panics
+    /// If the span is out of bounds.
+    pub fn byte_span(&self, char_span: (usize, usize)) -> (usize, usize) {
+        (self.byte_index(char_span.0), self.byte_index(char_span.1))
+    }
+
+    /// Converts a byte span to a char span. Returns `None` if the span is not on char boundaries.
+    pub fn char_span(&self, byte_span: (usize, usize)) -> Option<(usize, usize)> {
+        Some((self.char_index(byte_span.0)?, self.char_index(byte_span.1)?))
+    }
+}
+
+/// Tracks which parts of a text are covered by non-overlapping spans, e. g. of accepted suggestions.
+/// A zero-width span (`start == end`) is an insertion at `start`: it conflicts with other insertions at the same position
+/// and with spans which contain the position in their interior, but not with spans which start or end there.
+#[derive(Debug, Clone)]
+pub(crate) struct SpanMask {
+    chars: Vec<bool>,
+    // for each position between chars (including the start and end of the text)
+    interior: Vec<bool>,
+    insertions: Vec<bool>,
+}
+
+impl SpanMask {
+    pub fn new(char_len: usize) -> Self {
+        SpanMask {
+            chars: vec![false; char_len],
+            interior: vec![false; char_len + 1],
+            insertions: vec![false; char_len + 1],
+        }
+    }
+
+    /// Whether the span does not conflict with any span added so far.
+    pub fn is_free(&self, (start, end): (usize, usize)) -> bool {
+        if start == end {
+            !self.interior[start] && !self.insertions[start]
+        } else {
+            self.chars[start..end].iter().all(|x| !x)
+                && self.insertions[start + 1..end].iter().all(|x| !x)
+        }
+    }
+
+    /// Adds the span if it is free. Returns whether it was added.
+    pub fn insert(&mut self, (start, end): (usize, usize)) -> bool {
+        if !self.is_free((start, end)) {
+            return false;
+        }
+
+        if start == end {
+            self.insertions[start] = true;
+        } else {
+            self.chars[start..end].iter_mut().for_each(|x| *x = true);
+            self.interior[start + 1..end]
+                .iter_mut()
+                .for_each(|x| *x = true);
+        }
+        true
+    }
+}
+
+/// How severe the issue flagged by a suggestion is, e. g. to color-code suggestions in an editor.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Hint,
+}
+
+/// A part of the message of a suggestion.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum MessagePart {
+    /// Fixed text from the message template of the rule.
+    Literal(String),
+    /// Text inserted from the checked text, possibly converted e. g. to another inflection.
+    Match(String),
+}
+
+impl MessagePart {
+    pub fn text(&self) -> &str {
+        match self {
+            MessagePart::Literal(text) | MessagePart::Match(text) => text,
+        }
+    }
+}
+
+/// A group of the pattern of a rule in the match a suggestion was computed from, see [Suggestion::groups].
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MatchedGroup {
+    /// The ID of the group, as referenced by the matches in the suggestions and the message of the rule.
+    pub id: usize,
+    /// The char span of the group, relative to the sentence. Empty if the group matched no tokens.
+    pub char_span: (usize, usize),
+    /// The text of the group.
+    pub text: String,
+    /// The texts of the tokens in the group.
+    pub tokens: Vec<String>,
+    /// Whether the group is in the marked part of the pattern, i. e. the text the suggestion replaces.
+    pub marked: bool,
+    /// Whether text from this group is inserted in the replacements.
+    pub in_replacement: bool,
+}
+
+/// Suggestion for change in a text. Two suggestions are equal if all their fields are equal, see
+/// [Suggestion::same_correction] for a looser comparison.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Suggestion {
     /// The ID of the rule this suggestion is from.
     pub source: String,
     /// A human-readable message.
     pub message: String,
+    /// The message split into literal text and text from the checked text. Joining the parts gives the message.
+    /// Empty if the suggestion has no structured message (e. g. from a native check), then the message is literal text.
+    #[serde(default)]
+    pub message_parts: Vec<MessagePart>,
+    /// A short, one-line title e. g. "Possible typo" if the rule has one.
+    pub short_message: Option<String>,
     /// The start character index (inclusive).
     pub start: usize,
-    /// The end character index (exclusive).
+    /// The end character index (exclusive). If it is equal to `start`, the suggestion inserts text before the char at `start`
+    /// (e. g. a missing comma). Such an insertion is applied before a suggestion which starts at the same index.
     pub end: usize,
+    /// The byte span of the text from `start` to `end`, e. g. to slice the checked `&str` directly.
+    #[serde(default)]
+    pub byte_span: (usize, usize),
     /// The suggested replacement options for the text.
     pub replacements: Vec<String>,
+    /// The severity as configured in the [RulesOptions][crate::rules::RulesOptions], if any.
+    pub severity: Option<Severity>,
+    /// The char span of the text (usually a sentence) the suggestion was computed on.
+    /// Uses the same frame as `start` and `end`, so it covers the whole input unless the suggestion was shifted.
+    #[serde(default)]
+    pub sentence_span: (usize, usize),
+    /// The indices of the sentence tokens covered by this suggestion. Index 0 is the special sentence start token.
+    /// The span is empty for insertions. `None` if the suggestion was not computed by [Rules][crate::rules::Rules].
+    #[serde(default)]
+    pub token_span: Option<(usize, usize)>,
+    /// The tokens in `token_span`, with spans relative to the sentence.
+    /// Only set if [RulesOptions::include_tokens][crate::rules::RulesOptions::include_tokens] is enabled.
+    #[serde(default)]
+    pub tokens: Vec<owned::Token>,
+    /// Features of the context of this suggestion e. g. for a [Reranker][crate::rules::Reranker] or an external model.
+    /// Only set if [RulesOptions::include_features][crate::rules::RulesOptions::include_features] is enabled.
+    #[serde(default)]
+    pub features: Option<ContextFeatures>,
+    /// The groups of the match of the rule, sorted by ID, e. g. to highlight the matched tokens.
+    /// Only set if [RulesOptions::include_groups][crate::rules::RulesOptions::include_groups] is enabled.
+    #[serde(default)]
+    pub groups: Vec<MatchedGroup>,
+    /// The priority of this suggestion in [0, 1] when it overlaps other suggestions, higher wins, see
+    /// [Suggestion::cmp_priority]. Native checks have priority 1. Rules with a higher [priority][crate::rule::Rule::priority]
+    /// and, if it is the same, rules later in the rule set have a higher priority.
+    #[serde(default)]
+    pub priority: f32,
+}
+
+/// Cheap features of the context of a [Suggestion] which are known anyway when the suggestion is computed.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ContextFeatures {
+    /// The text of the token before the suggestion. `None` at the start of the sentence.
+    pub prev_text: Option<String>,
+    /// The POS tags of the token before the suggestion.
+    pub prev_pos: Vec<String>,
+    /// The text of the token after the suggestion. `None` at the end of the sentence.
+    pub next_text: Option<String>,
+    /// The POS tags of the token after the suggestion.
+    pub next_pos: Vec<String>,
+    /// The number of tokens in the sentence without the special sentence start token.
+    pub sentence_tokens: usize,
+    /// The category ID of the rule. `None` for native checks.
+    pub category: Option<String>,
+}
+
+impl ContextFeatures {
+    /// Computes the features of a suggestion covering the tokens `token_span` of the sentence `tokens`.
+    pub(crate) fn new(
+        tokens: &[Token],
+        token_span: (usize, usize),
+        category: Option<&str>,
+    ) -> Self {
+        let pos = |token: &Token| {
+            let mut pos: Vec<String> = token
+                .word
+                .tags
+                .iter()
+                .map(|x| x.pos.as_ref().to_string())
+                .collect();
+            pos.sort();
+            pos.dedup();
+            pos
+        };
+        // index 0 is the special sentence start token
+        let prev = Some(token_span.0)
+            .filter(|i| *i > 1)
+            .and_then(|i| tokens.get(i - 1));
+        let next = tokens.get(token_span.1);
+
+        ContextFeatures {
+            prev_text: prev.map(|x| x.word.text.as_ref().to_string()),
+            prev_pos: prev.map(pos).unwrap_or_default(),
+            next_text: next.map(|x| x.word.text.as_ref().to_string()),
+            next_pos: next.map(pos).unwrap_or_default(),
+            sentence_tokens: tokens.len().saturating_sub(1),
+            category: category.map(|x| x.to_string()),
+        }
+    }
+}
+
+impl Suggestion {
+    /// Whether this suggestion is at the same position as the other one and they have a replacement in common.
+    /// This is how the suggestions of a rule are compared with the corrections of its examples.
+    pub fn same_correction(&self, other: &Suggestion) -> bool {
+        self.start == other.start
+            && self.end == other.end
+            && self
+                .replacements
+                .iter()
+                .any(|x| other.replacements.contains(x))
+    }
+
+    /// Orders suggestions by position: by start, insertions before other suggestions at the same start, then by end.
+    pub fn cmp_position(&self, other: &Suggestion) -> Ordering {
+        self.start
+            .cmp(&other.start)
+            .then_with(|| (self.start != self.end).cmp(&(other.start != other.end)))
+            .then_with(|| self.end.cmp(&other.end))
+    }
+
+    /// Orders suggestions by priority, higher first, then by position. Overlapping suggestions are resolved in
+    /// this order by [Rules::apply][crate::Rules::apply]: a suggestion is kept if it does not overlap any kept before.
+    pub fn cmp_priority(&self, other: &Suggestion) -> Ordering {
+        other
+            .priority
+            .partial_cmp(&self.priority)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.cmp_position(other))
+    }
+
+    /// Gets a score for each replacement: the priority of the suggestion divided by the rank of the replacement,
+    /// so replacements listed first score higher.
+    pub fn scores(&self) -> Vec<f32> {
+        (0..self.replacements.len())
+            .map(|i| self.priority / (i + 1) as f32)
+            .collect()
+    }
+
+    /// Moves this suggestion `offset` characters and `byte_offset` bytes to the right, e. g. to make indices
+    /// relative to a larger text.
+    pub fn shift(&mut self, offset: usize, byte_offset: usize) {
+        self.start += offset;
+        self.end += offset;
+        self.byte_span = (
+            self.byte_span.0 + byte_offset,
+            self.byte_span.1 + byte_offset,
+        );
+        self.sentence_span = (self.sentence_span.0 + offset, self.sentence_span.1 + offset);
+    }
+
+    /// Sets the byte span from `start` and `end` with a converter of the text the indices refer to.
+    pub(crate) fn set_byte_span(&mut self, converter: &SpanConverter) {
+        self.byte_span = converter.byte_span((self.start, self.end));
+    }
+
+    /// Gets the text of the sentence this suggestion was computed on.
+    /// `text` must be the text the indices of this suggestion refer to.
+    pub fn sentence<'a>(&self, text: &'a str) -> &'a str {
+        let (start, end) = SpanConverter::new(text).byte_span(self.sentence_span);
+        &text[start..end]
+    }
+
+    /// Gets the tokens covered by this suggestion from the tokens of the sentence it was computed on.
+    /// Returns an empty slice if the suggestion has no token span.
+    pub fn covered_tokens<'a, 't>(&self, tokens: &'a [Token<'t>]) -> &'a [Token<'t>] {
+        match self.token_span {
+            Some((start, end)) if end <= tokens.len() => &tokens[start..end],
+            _ => &[],
+        }
+    }
+}
+
+/// Where a rule is defined in the XML it was compiled from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SourceLocation {
+    /// The path of the XML file as passed to the compiler.
+    pub path: String,
+    /// The line the rule starts on (1-based).
+    pub line: usize,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path, self.line)
+    }
+}
+
+/// Information about how a [Rules][crate::Rules] or [Tokenizer][crate::Tokenizer] binary was built.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// The version of LanguageTool the XML source was taken from, if it was given at compile time.
+    pub lt_version: Option<String>,
+    /// When the binary was compiled in seconds since the UNIX epoch.
+    pub compiled_at: Option<u64>,
+    /// The version of nlprule used to compile the binary.
+    pub nlprule_version: String,
+    /// A hash of the XML source in hexadecimal notation.
+    pub source_hash: Option<String>,
+}
+
+impl Metadata {
+    /// Checks that the binary was built with a compatible version of nlprule, i. e. the same major version
+    /// and for `0.x` versions the same minor version. Binaries without a version are not checked.
+    pub(crate) fn check_version(&self) -> Result<(), crate::Error> {
+        let expected = env!("CARGO_PKG_VERSION");
+        let release = |version: &str| -> Vec<String> {
+            let parts: Vec<_> = version.split('.').map(|x| x.to_string()).collect();
+            match parts.first().map(|x| x.as_str()) {
+                Some("0") => parts.into_iter().take(2).collect(),
+                _ => parts.into_iter().take(1).collect(),
+            }
+        };
+
+        if self.nlprule_version.is_empty() || release(&self.nlprule_version) == release(expected) {
+            Ok(())
+        } else {
+            Err(crate::Error::IncompatibleVersion {
+                found: self.nlprule_version.clone(),
+                expected: expected.to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_suggestions() {
+        let suggestion = |start, end, replacements: &[&str], priority| Suggestion {
+            start,
+            end,
+            replacements: replacements.iter().map(|x| x.to_string()).collect(),
+            priority,
+            ..Default::default()
+        };
+
+        let a = suggestion(3, 5, &["x", "y"], 0.5);
+        assert!(a.same_correction(&suggestion(3, 5, &["y"], 0.2)));
+        assert_ne!(a, suggestion(3, 5, &["y"], 0.2));
+        assert_eq!(a.scores(), vec![0.5, 0.25]);
+
+        assert_eq!(
+            a.cmp_priority(&suggestion(0, 4, &["z"], 0.2)),
+            Ordering::Less
+        );
+        assert_eq!(
+            a.cmp_position(&suggestion(3, 3, &["z"], 0.2)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn checks_versions() {
+        let metadata = |version: &str| Metadata {
+            nlprule_version: version.to_string(),
+            ..Default::default()
+        };
+
+        assert!(metadata("").check_version().is_ok());
+        assert!(metadata(env!("CARGO_PKG_VERSION")).check_version().is_ok());
+        let error = metadata("99.0.0").check_version().unwrap_err();
+        assert_eq!(error.category(), crate::ErrorCategory::Model);
+    }
+
+    #[test]
+    fn converts_spans() {
+        let converter = SpanConverter::new("aä€b");
+
+        assert_eq!(converter.char_len(), 4);
+        assert_eq!(converter.byte_span((1, 3)), (1, 6));
+        assert_eq!(converter.char_span((1, 6)), Some((1, 3)));
+        assert_eq!(converter.char_index(2), None);
+        assert_eq!(converter.byte_index(4), 7);
+    }
+
+    #[test]
+    fn masks_zero_width_spans() {
+        let mut mask = SpanMask::new(10);
+
+        assert!(mask.insert((2, 5)));
+        assert!(!mask.insert((4, 6)));
+        // insertions at the boundaries of a span are fine, but not inside it
+        assert!(mask.insert((2, 2)));
+        assert!(mask.insert((5, 5)));
+        assert!(!mask.insert((3, 3)));
+        assert!(!mask.insert((5, 5)));
+        // a span can not contain an insertion, but may start or end at one
+        assert!(mask.insert((8, 8)));
+        assert!(!mask.insert((6, 10)));
+        assert!(mask.insert((5, 8)));
+        assert!(mask.insert((10, 10)));
+        assert!(mask.insert((8, 10)));
+    }
 }
@@ -1,69 +1,66 @@
-use lazy_static::lazy_static;
-use onig::{Captures, Regex};
+use onig::Captures;
 
+pub mod lru;
 pub mod parallelism;
 pub mod regex;
 
-// see https://stackoverflow.com/questions/38406793/why-is-capitalizing-the-first-letter-of-a-string-so-convoluted-in-rust
-pub fn apply_to_first<F>(string: &str, func: F) -> String
-where
-    F: Fn(char) -> String,
-{
-    let mut c = string.chars();
-    match c.next() {
-        None => String::new(),
-        Some(first) => func(first) + c.as_str(),
+// the text helpers are public in [crate::text]
+pub use crate::text::{
+    apply_to_first, common_word_affixes, fix_nospace_chars, is_title_case, is_title_case_heading,
+    is_uppercase, levenshtein, title_case_words,
+};
+
+// see https://github.com/rust-onig/rust-onig/issues/59#issuecomment-340160520
+pub fn dollar_replace(mut replacement: String, caps: &Captures) -> String {
+    for i in 1..caps.len() {
+        replacement = replacement.replace(&format!("${}", i), caps.at(i).unwrap_or(""));
     }
+    replacement
 }
 
-pub fn is_title_case(string: &str) -> bool {
-    let mut char_case = string.chars().map(|x| x.is_uppercase());
+// highest group referenced as `$n` in a replacement, zero if no group is referenced
+pub fn max_dollar_reference(replacement: &str) -> usize {
+    let mut max = 0;
+    let mut chars = replacement.chars().peekable();
 
-    char_case.next().unwrap_or(false) && !char_case.any(|x| x)
+    while let Some(c) = chars.next() {
+        if c == '$' {
+            if let Some(digit) = chars.peek().and_then(|x| x.to_digit(10)) {
+                max = std::cmp::max(max, digit as usize);
+            }
+        }
+    }
+    max
 }
 
-pub fn is_uppercase(string: &str) -> bool {
-    !string.chars().any(|x| x.is_lowercase())
-}
+/// FNV-1a. Unlike the default hasher its output is the same across Rust versions, so hashes can be stored.
+pub struct StableHasher(u64);
 
-// see https://github.com/rust-onig/rust-onig/issues/59#issuecomment-340160520
-pub fn dollar_replace(mut replacement: String, caps: &Captures) -> String {
-    for i in 1..caps.len() {
-        replacement = replacement.replace(&format!("${}", i), caps.at(i).unwrap_or(""));
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
     }
-    replacement
 }
 
-// remove duplicate whitespaces
-pub fn normalize_whitespace(string: &str) -> String {
-    lazy_static! {
-        static ref REGEX: Regex = Regex::new(r"(\s)\s+").unwrap();
+impl std::hash::Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
     }
 
-    REGEX.replace_all(string, |caps: &Captures| caps.at(1).unwrap().to_string())
+    fn finish(&self) -> u64 {
+        self.0
+    }
 }
 
 #[inline]
 pub fn splitting_chars() -> &'static str {
-    r##"«»'’`´‘],.:;!?/\()<=>„“”"+#…*"##
+    r##"«»'’`´‘],.:;!?/\()<=>„“”"+#…*،؛؟"##
 }
 
 #[inline]
 pub fn no_space_chars() -> &'static str {
-    r##","##
-}
-
-pub fn fix_nospace_chars(text: &str) -> String {
-    text.char_indices()
-        .filter(|(i, c)| {
-            if c.is_whitespace() {
-                !no_space_chars()
-                    .chars()
-                    .any(|nospace_c| text[(i + c.len_utf8())..].starts_with(nospace_c))
-            } else {
-                true
-            }
-        })
-        .map(|x| x.1)
-        .collect()
+    r##",،"##
 }
@@ -14,6 +14,276 @@ fn unescape<S: AsRef<str>>(string: S, c: &str) -> String {
         .replace(placeholder, r"\\")
 }
 
+// POSIX classes which only match ASCII chars in Java, but all matching Unicode chars in onig
+const ASCII_CLASSES: &[(&str, &str)] = &[
+    ("Lower", "a-z"),
+    ("Upper", "A-Z"),
+    ("Alpha", "a-zA-Z"),
+    ("Digit", "0-9"),
+    ("Alnum", "a-zA-Z0-9"),
+    ("XDigit", "0-9a-fA-F"),
+    ("Punct", r"!-/:-@\[-`{-~"),
+    ("Space", r" \t\n\x0B\f\r"),
+];
+
+// properties of `java.lang.Character` and their Unicode equivalent
+const JAVA_PROPERTIES: &[(&str, &str)] = &[
+    ("javaLowerCase", "Lowercase"),
+    ("javaUpperCase", "Uppercase"),
+    ("javaAlphabetic", "Alphabetic"),
+    ("javaLetter", "L"),
+    ("javaDigit", "Nd"),
+];
+
+fn translate_property(
+    negate: bool,
+    name: &str,
+    in_class: bool,
+    warnings: &mut Vec<String>,
+) -> String {
+    let original = format!(r"\{}{{{}}}", if negate { 'P' } else { 'p' }, name);
+    let property = |name: &str| format!(r"\{}{{{}}}", if negate { 'P' } else { 'p' }, name);
+
+    if let Some((_, range)) = ASCII_CLASSES.iter().find(|(x, _)| *x == name) {
+        return match (negate, in_class) {
+            (false, false) => {
+                warnings.push(format!(
+                    "`{}` only matches ASCII, rewritten to `[{}]`",
+                    original, range
+                ));
+                format!("[{}]", range)
+            }
+            (true, false) => {
+                warnings.push(format!(
+                    "`{}` only matches ASCII, rewritten to `[^{}]`",
+                    original, range
+                ));
+                format!("[^{}]", range)
+            }
+            (false, true) => {
+                warnings.push(format!(
+                    "`{}` only matches ASCII, rewritten to `{}`",
+                    original, range
+                ));
+                range.to_string()
+            }
+            (true, true) => {
+                warnings.push(format!(
+                    "`{}` in a character class matches non-ASCII chars in onig",
+                    original
+                ));
+                original
+            }
+        };
+    }
+
+    if let Some((_, unicode_name)) = JAVA_PROPERTIES.iter().find(|(x, _)| *x == name) {
+        property(unicode_name)
+    } else if name.starts_with("java") {
+        warnings.push(format!("`{}` is not supported", original));
+        original
+    } else if name.starts_with("In") && name.len() > 2 {
+        warnings.push(format!("Unicode block `{}` is not supported", original));
+        original
+    } else if name.starts_with("Is") && name.len() > 2 {
+        // scripts, categories and binary properties e. g. `IsLatin`, `IsL`, `IsAlphabetic`
+        property(&name[2..])
+    } else {
+        original
+    }
+}
+
+/// Rewrites constructs of Java regexes which onig does not support or interprets differently.
+/// Returns the rewritten regex and a warning for each construct which changes meaning or can not be translated.
+/// Possessive quantifiers are left unchanged since the Java syntax of onig supports them.
+pub(crate) fn translate_java(regex_str: &str) -> (String, Vec<String>) {
+    let chars: Vec<char> = regex_str.chars().collect();
+    let mut output = String::new();
+    let mut warnings = Vec::new();
+    let mut class_depth = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        match c {
+            '\\' if i + 1 < chars.len() => {
+                let next = chars[i + 1];
+
+                if (next == 'p' || next == 'P') && chars.get(i + 2) == Some(&'{') {
+                    if let Some(len) = chars[i + 3..].iter().position(|x| *x == '}') {
+                        let name: String = chars[i + 3..i + 3 + len].iter().collect();
+                        output.push_str(&translate_property(
+                            next == 'P',
+                            &name,
+                            class_depth > 0,
+                            &mut warnings,
+                        ));
+                        i += len + 4;
+                        continue;
+                    }
+                }
+
+                output.push(c);
+                output.push(next);
+                i += 2;
+                continue;
+            }
+            '[' => class_depth += 1,
+            ']' if class_depth > 0 => class_depth -= 1,
+            '(' if class_depth == 0 && chars.get(i + 1) == Some(&'?') => {
+                // inline flags e. g. `(?iu)` or `(?u:...)`
+                let len = chars[i + 2..]
+                    .iter()
+                    .take_while(|x| x.is_ascii_alphabetic() || **x == '-')
+                    .count();
+                let end = chars.get(i + 2 + len).cloned();
+
+                if len > 0 && (end == Some(')') || end == Some(':')) {
+                    let flags: String = chars[i + 2..i + 2 + len].iter().collect();
+                    for flag in flags.chars().filter(|x| *x == 'd' || *x == 'U') {
+                        warnings.push(format!("inline flag `{}` is not supported", flag));
+                    }
+
+                    // onig is Unicode-aware by default
+                    let flags = flags.replace('u', "");
+                    match (flags.trim_end_matches('-').is_empty(), end) {
+                        (true, Some(')')) => {}
+                        (true, _) => output.push_str("(?:"),
+                        (false, _) => {
+                            output.push_str("(?");
+                            output.push_str(&flags);
+                            output.push(end.unwrap());
+                        }
+                    }
+
+                    i += len + 3;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        output.push(c);
+        i += 1;
+    }
+
+    (output, warnings)
+}
+
+/// Gets the index after the group or class which starts before `i`. Classes can contain unescaped parentheses
+/// and groups can contain classes, so both are tracked.
+fn skip_nested(chars: &[char], mut i: usize, close: char) -> Option<usize> {
+    if close == ']' {
+        // a negation and a `]` at the start of a class are part of the class
+        if chars.get(i) == Some(&'^') {
+            i += 1;
+        }
+        if chars.get(i) == Some(&']') {
+            i += 1;
+        }
+    }
+
+    loop {
+        let c = *chars.get(i)?;
+        i += 1;
+
+        match c {
+            '\\' => i += 1,
+            '[' => i = skip_nested(chars, i, ']')?,
+            '(' if close == ')' => i = skip_nested(chars, i, ')')?,
+            c if c == close => return Some(i),
+            _ => {}
+        }
+    }
+}
+
+/// Finds the longest literal which every match of the regex must contain, so the regex can not match a text
+/// without it. Conservative: returns `None` for alternations at the top level, inline flags and unknown escapes.
+/// For case-insensitive regexes only ASCII chars are part of literals.
+fn required_literal(regex_str: &str, case_sensitive: bool) -> Option<String> {
+    let chars: Vec<_> = regex_str.chars().collect();
+
+    // inline flags e. g. `(?x)` change how the rest of the regex is interpreted
+    if chars
+        .windows(3)
+        .any(|x| x[0] == '(' && x[1] == '?' && (x[2].is_alphabetic() || x[2] == '-'))
+    {
+        return None;
+    }
+
+    let is_literal = |c: char| case_sensitive || (c.is_ascii() && !c.is_ascii_control());
+
+    let mut best = String::new();
+    let mut run = String::new();
+    let mut end_run = |run: &mut String| {
+        if run.chars().count() > best.chars().count() {
+            best = run.clone();
+        }
+        run.clear();
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        match c {
+            '\\' => {
+                let escaped = *chars.get(i)?;
+                i += 1;
+
+                if escaped.is_alphanumeric() {
+                    // zero-width assertions and classes without arguments, other escapes are not analyzed
+                    if !"bBdDsSwWAzZG".contains(escaped) {
+                        return None;
+                    }
+                    end_run(&mut run);
+                } else if is_literal(escaped) {
+                    run.push(escaped);
+                } else {
+                    end_run(&mut run);
+                }
+            }
+            '|' => return None,
+            '(' => {
+                end_run(&mut run);
+                i = skip_nested(&chars, i, ')')?;
+            }
+            '[' => {
+                end_run(&mut run);
+                i = skip_nested(&chars, i, ']')?;
+            }
+            '*' | '?' | '{' => {
+                // the previous char is optional or repeated a variable number of times
+                run.pop();
+                end_run(&mut run);
+                if c == '{' {
+                    while *chars.get(i)? != '}' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            '+' | '.' | '^' | '$' => end_run(&mut run),
+            c if is_literal(c) => run.push(c),
+            _ => end_run(&mut run),
+        }
+
+        // lazy and possessive quantifiers
+        if matches!(c, '*' | '?' | '+' | '{') && matches!(chars.get(i), Some('?') | Some('+')) {
+            i += 1;
+        }
+    }
+    end_run(&mut run);
+
+    if best.is_empty() {
+        None
+    } else {
+        Some(best)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct RegexFields {
     regex_str: String,
@@ -26,6 +296,8 @@ pub struct SerializeRegex {
     case_sensitive: bool,
     #[serde(skip_serializing)]
     regex: Regex,
+    #[serde(skip_serializing)]
+    required_literal: Option<String>,
 }
 
 impl Hash for SerializeRegex {
@@ -42,7 +314,9 @@ impl<'de> Deserialize<'de> for SerializeRegex {
     {
         let fields: RegexFields = Deserialize::deserialize(deserializer)?;
         Ok(SerializeRegex {
-            regex: SerializeRegex::compile(&fields.regex_str, fields.case_sensitive).unwrap(),
+            regex: SerializeRegex::compile(&fields.regex_str, fields.case_sensitive)
+                .map_err(serde::de::Error::custom)?,
+            required_literal: required_literal(&fields.regex_str, fields.case_sensitive),
             regex_str: fields.regex_str,
             case_sensitive: fields.case_sensitive,
         })
@@ -67,6 +341,17 @@ impl SerializeRegex {
         must_fully_match: bool,
         case_sensitive: bool,
     ) -> Result<Self, Error> {
+        SerializeRegex::new_with_warnings(regex_str, must_fully_match, case_sensitive)
+            .map(|(regex, _)| regex)
+    }
+
+    /// Like [SerializeRegex::new], but also returns warnings about Java regex constructs which
+    /// had to be rewritten or could not be translated, see [translate_java].
+    pub(crate) fn new_with_warnings(
+        regex_str: &str,
+        must_fully_match: bool,
+        case_sensitive: bool,
+    ) -> Result<(Self, Vec<String>), Error> {
         // TODO: more exhaustive backslash check
         let mut fixed = unescape(unescape(unescape(regex_str, "!"), ","), "/");
         let mut case_sensitive = case_sensitive;
@@ -82,8 +367,16 @@ impl SerializeRegex {
             .replace("###escaped_dollar###", "\\$")
             .replace("###backslash_before_s###", "\\\\s");
 
+        let (mut fixed, mut warnings) = translate_java(&fixed);
+
         for pattern in &["(?iu)", "(?i)"] {
-            if fixed.contains(pattern) {
+            if let Some(index) = fixed.find(pattern) {
+                if index > 0 {
+                    warnings.push(format!(
+                        "inline flag `{}` after the start is applied to the whole regex",
+                        pattern
+                    ));
+                }
                 case_sensitive = false;
                 fixed = fixed.replace(pattern, "");
             }
@@ -95,12 +388,39 @@ impl SerializeRegex {
             fixed
         };
 
-        Ok(SerializeRegex {
-            regex: SerializeRegex::compile(&fixed, case_sensitive)
-                .map_err(|x| Error::Unexpected(format!("{}", x)))?,
+        let regex = SerializeRegex {
+            regex: SerializeRegex::compile(&fixed, case_sensitive).map_err(|source| {
+                Error::Regex {
+                    regex: fixed.clone(),
+                    source,
+                }
+            })?,
+            required_literal: required_literal(&fixed, case_sensitive),
             regex_str: fixed,
             case_sensitive,
-        })
+        };
+        Ok((regex, warnings))
+    }
+
+    /// Gets the (preprocessed) regex string.
+    pub fn as_str(&self) -> &str {
+        &self.regex_str
+    }
+
+    /// Whether the regex could match the text. If `false`, it certainly does not match so the regex does not have to run.
+    pub(crate) fn could_match(&self, text: &str) -> bool {
+        match &self.required_literal {
+            Some(literal) if self.case_sensitive => text.contains(literal.as_str()),
+            // non-ASCII chars can be case-insensitively equal to ASCII chars e. g. "ﬀ" and "ff"
+            Some(_) if !text.is_ascii() => true,
+            Some(literal) => {
+                let literal = literal.as_bytes();
+                text.as_bytes()
+                    .windows(literal.len())
+                    .any(|x| x.eq_ignore_ascii_case(literal))
+            }
+            None => true,
+        }
     }
 }
 
@@ -111,3 +431,55 @@ impl Deref for SerializeRegex {
         &self.regex
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_java_constructs() {
+        assert_eq!(
+            translate_java(r"\p{Lower}[\p{Upper}_]\\p{Alpha}"),
+            (
+                r"[a-z][A-Z_]\\p{Alpha}".to_string(),
+                vec![
+                    r"`\p{Lower}` only matches ASCII, rewritten to `[a-z]`".to_string(),
+                    r"`\p{Upper}` only matches ASCII, rewritten to `A-Z`".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            translate_java(r"(?u)\p{IsAlphabetic}(?iu:\P{javaLowerCase})").0,
+            r"\p{Alphabetic}(?i:\P{Lowercase})"
+        );
+        assert_eq!(translate_java(r"\p{InGreek}a++").1.len(), 1);
+        assert!(SerializeRegex::new(r"(?u)\p{IsL}+", true, true).is_ok());
+    }
+
+    #[test]
+    fn finds_required_literals() {
+        assert_eq!(
+            required_literal(r"\b(an?) (hour|honest)\b", true),
+            Some(" ".to_string())
+        );
+        assert_eq!(
+            required_literal(r"[Tt]he the\.? +(\w+)", true),
+            Some("he the".to_string())
+        );
+        assert_eq!(required_literal(r"colou?r", true), Some("colo".to_string()));
+        assert_eq!(required_literal(r"a{2,3}bc", true), Some("bc".to_string()));
+        assert_eq!(required_literal(r"foo|bar", true), None);
+        assert_eq!(required_literal(r"\p{Lu}abc", true), None);
+        assert_eq!(required_literal(r"mask", false), Some("mask".to_string()));
+        assert_eq!(
+            required_literal(r"a[]()]bc(x[)])de", true),
+            Some("bc".to_string())
+        );
+        assert_eq!(required_literal(r"(?x)a b", true), None);
+
+        let regex = SerializeRegex::new(r"Colou?r", false, false).unwrap();
+        assert!(regex.could_match("my COLOR"));
+        assert!(!regex.could_match("my hue"));
+        assert!(regex.could_match("my hüe"));
+    }
+}